@@ -1,19 +1,40 @@
-use ant_act::{ActExt, TokenInfo, Wallet as ActWallet, WalletExt};
+use ant_act::{
+    account_sk, inbox_pk, issuer_pk, issuer_sk, mint_pk, mint_sk, now, signed_from_hex,
+    signed_to_hex, treasury_member_sk, ActExt, BurnExt, CheckpointExt, Direction, InboxExt,
+    Invoice, InvoiceExt, InvoiceStatus, LineItem, MarkerExt, Memo, MintExt, MintPolicy, Notice,
+    PaymentRequest, ProofExt, Receipt, ReceiptExt, Received, RequestMatch, RequestStatus,
+    SignedInvoice, TokenInfo, Transaction, Treasury, TreasuryExt, TxStatus, UnsignedSpend,
+    ValidateExt, ValidationCache, Wallet as ActWallet, WalletExt, WatchOnly, DEFAULT_CACHE_ENTRIES,
+    MAIN_ACCOUNT, MAX_VALIDATION_BUDGET,
+};
 use autonomi::{
-    client::payment::PaymentOption, Client, GraphEntry, GraphEntryAddress, PublicKey, SecretKey,
-    Wallet, XorName,
+    client::payment::PaymentOption, Client, GraphEntryAddress, PublicKey, SecretKey, Wallet,
+    XorName,
 };
 use futures::{lock::Mutex, stream, FutureExt, StreamExt};
 use ruint::aliases::U256;
 use sn_curv::elliptic::curves::ECScalar;
-use std::collections::HashMap;
-use tauri::{Manager, State, Theme};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+use tauri::{AppHandle, Emitter, Manager, State, Theme};
+
+/// How often the background watcher looks for incoming payments.
+const WATCH_INTERVAL: Duration = Duration::from_secs(30);
+/// Most spends followed by a supply audit.
+const SUPPLY_AUDIT_SPENDS: usize = 10_000;
 
 struct AppState {
     client: Client,
     wallet: Wallet,
-    sk: SecretKey,
+    /// Key of current account, `None` for watch-only wallets.
+    sk: Option<SecretKey>,
     act_wallet: ActWallet,
+    /// Key of the main account, `None` for watch-only wallets.
+    seed: Option<SecretKey>,
+    account: u32,
+    /// Names of accounts, kept in the main account's wallet.
+    accounts: Vec<String>,
+    /// Shared by all wallets opened by the app.
+    validation_cache: ValidationCache,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -27,17 +48,13 @@ enum Network {
 async fn connect(
     network: Network,
     evm_pk: Option<String>,
+    app: AppHandle,
     state: State<'_, Mutex<Option<AppState>>>,
 ) -> Result<String, String> {
     let mut state = state.lock().await;
 
     if state.is_none() {
-        let mut client = match network {
-            Network::Local => Client::init_local().await,
-            Network::Main => Client::init().await,
-            Network::Alpha => Client::init_alpha().await,
-        }
-        .map_err(|e| format!("{}", e))?;
+        let mut client = init_client(network).await?;
 
         let evm_pk = evm_pk.unwrap_or(SecretKey::random().to_hex()); // bls secret key can be used as eth privkey
         let evm_wallet = Wallet::new_from_private_key(client.evm_network().clone(), &evm_pk)
@@ -53,23 +70,8 @@ async fn connect(
             )
         );
 
-        let evm_pk = if &evm_pk[0..2] == "0x" {
-            &evm_pk[2..]
-        } else {
-            &evm_pk
-        };
-
-        let sk = SecretKey::from_bytes(
-            sn_bls_ckd::derive_master_sk(
-                hex::decode(evm_pk).map_err(|e| format!("{}", e))?[0..32]
-                    .try_into()
-                    .unwrap(),
-            )
-            .expect("Wrong bytes")
-            .serialize()
-            .into(),
-        )
-        .expect("Wrong bytes");
+        let evm_pk = evm_pk.strip_prefix("0x").unwrap_or(&evm_pk);
+        let sk = seed_sk(evm_pk)?;
         println!("sk: {:.4}(...)", sk.to_hex());
 
         let client_clone = client.clone();
@@ -94,11 +96,17 @@ async fn connect(
             .map_err(|e| format!("{e}"))?
             .ok_or("Wallet could not be loaded nor created".to_string())?;
 
+        let accounts = act_wallet.accounts().to_vec();
+
         *state = Some(AppState {
             client: client_clone,
             wallet: evm_wallet_clone,
-            sk: sk_clone,
+            sk: Some(sk_clone.clone()),
             act_wallet,
+            seed: Some(sk_clone),
+            account: MAIN_ACCOUNT,
+            accounts,
+            validation_cache: open_validation_cache(&app),
         });
 
         Ok(evm_pk.to_string())
@@ -107,6 +115,263 @@ async fn connect(
     }
 }
 
+/// Wallet key derived from EVM private key (hex without "0x").
+fn seed_sk(evm_pk: &str) -> Result<SecretKey, String> {
+    let bytes: [u8; 32] = hex::decode(evm_pk)
+        .map_err(|e| format!("{}", e))?
+        .get(0..32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Wrong private key length.".to_string())?;
+
+    SecretKey::from_bytes(sn_bls_ckd::derive_master_sk(&bytes)?.serialize().into())
+        .map_err(|e| format!("{}", e))
+}
+
+async fn init_client(network: Network) -> Result<Client, String> {
+    match network {
+        Network::Local => Client::init_local().await,
+        Network::Main => Client::init().await,
+        Network::Alpha => Client::init_alpha().await,
+    }
+    .map_err(|e| format!("{}", e))
+}
+
+fn watch_only_path(app: &AppHandle, master: &PublicKey) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| format!("{}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}", e))?;
+
+    Ok(dir.join(format!("watch-only-{:.16}.act", master.to_hex())))
+}
+
+/// Validation cache in app's data directory, or in memory only if it can't be opened.
+fn open_validation_cache(app: &AppHandle) -> ValidationCache {
+    let path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("{}", e))
+        .and_then(|dir| {
+            std::fs::create_dir_all(&dir).map_err(|e| format!("{}", e))?;
+            Ok(dir.join("validation-cache.act"))
+        });
+
+    match path.and_then(|path| ValidationCache::open(path, DEFAULT_CACHE_ENTRIES)) {
+        Ok(cache) => cache,
+        Err(e) => {
+            println!("Validation cache not opened: {}", e);
+            ValidationCache::default()
+        }
+    }
+}
+
+/// Saves wallet on the network, or locally if it is watch-only. Saves validation cache too.
+async fn save_act_wallet(app: &AppHandle, state: &mut AppState) -> Result<(), String> {
+    if let Err(e) = state.validation_cache.save() {
+        println!("Validation cache not saved: {}", e);
+    }
+
+    match &state.sk {
+        Some(sk) => state
+            .client
+            .act_wallet_save(
+                &state.act_wallet,
+                sk,
+                &PaymentOption::from(state.wallet.clone()),
+            )
+            .await
+            .map(|_| ()),
+        None => std::fs::write(
+            watch_only_path(app, &state.act_wallet.master())?,
+            state.act_wallet.to_bytes()?,
+        )
+        .map_err(|e| format!("{}", e)),
+    }
+}
+
+#[tauri::command]
+async fn connect_watch_only(
+    network: Network,
+    descriptor: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+
+    if state_opt.is_some() {
+        return Err("Already connected.".to_string());
+    }
+
+    let client = init_client(network).await?;
+    let descriptor = WatchOnly::from_hex(&descriptor)?;
+
+    // no payments are made, but the state needs some EVM wallet
+    let evm_wallet =
+        Wallet::new_from_private_key(client.evm_network().clone(), &SecretKey::random().to_hex())
+            .map_err(|e| format!("{}", e))?;
+
+    let act_wallet = match std::fs::read(watch_only_path(&app, &descriptor.master)?) {
+        Ok(bytes) => {
+            let local = ActWallet::from_bytes(&bytes)?;
+            if local.watch_only().last_index >= descriptor.last_index {
+                local
+            } else {
+                // descriptor is newer, but keep locally collected history
+                let mut imported = ActWallet::from(descriptor);
+                for tx in local.history(None, None, None) {
+                    imported.record(tx.clone());
+                }
+                *imported.book_mut() = local.book().clone();
+                imported
+            }
+        }
+        Err(_) => ActWallet::from(descriptor),
+    };
+    println!("Watch-only wallet: {:?}", act_wallet);
+
+    *state_opt = Some(AppState {
+        client,
+        wallet: evm_wallet,
+        sk: None,
+        act_wallet,
+        seed: None,
+        account: MAIN_ACCOUNT,
+        accounts: Vec::new(),
+        validation_cache: open_validation_cache(&app),
+    });
+
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+    save_act_wallet(&app, state).await
+}
+
+#[tauri::command]
+async fn is_watch_only(state: State<'_, Mutex<Option<AppState>>>) -> Result<bool, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    Ok(state.sk.is_none())
+}
+
+#[tauri::command]
+async fn export_watch_only(state: State<'_, Mutex<Option<AppState>>>) -> Result<String, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    state.act_wallet.watch_only().to_hex()
+}
+
+/// Follows spends made from the wallet elsewhere. Returns number of found outgoing transactions.
+#[tauri::command]
+async fn sync(app: AppHandle, state: State<'_, Mutex<Option<AppState>>>) -> Result<usize, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let sent = state.client.act_wallet_sync(&mut state.act_wallet).await?;
+    println!("Sync sent: {:?}", sent);
+    save_act_wallet(&app, state).await?;
+
+    Ok(sent.len())
+}
+
+/// Prepares a spend to be signed on other machine, eg. offline. Returns it in hex.
+#[tauri::command]
+async fn export_unsigned_spend(
+    token_id: String,
+    amount: String,
+    to: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let act_wallet = &state.act_wallet;
+
+    let token_id: XorName = parse_xorname(&token_id)?;
+    let info = state.client.act_token_info(&token_id).await?;
+    let amount: U256 = Decimal::from_string(amount, info.decimals)?;
+
+    let to: PublicKey = match PublicKey::from_hex(&to) {
+        Ok(to) => to,
+        Err(e) => act_wallet.book().contact_key(&to, token_id).ok_or(format!(
+            "Neither a contact with key for this token, nor {}",
+            e
+        ))?,
+    };
+
+    act_wallet
+        .unsigned_spend(token_id, vec![(to, amount)])?
+        .to_hex()
+}
+
+/// Publishes a spend signed on other machine. Returns the spend address.
+#[tauri::command]
+async fn publish_signed(
+    signed: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let spend = signed_from_hex(&signed)?;
+    let sent = state
+        .client
+        .act_publish_signed(
+            &mut state.act_wallet,
+            &spend,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await?;
+    println!("Signed spend sent: {:?}", sent);
+    save_act_wallet(&app, state).await?;
+
+    Ok(spend.owner.to_hex())
+}
+
+fn offline_path(app: &AppHandle, master: &PublicKey) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| format!("{}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}", e))?;
+
+    Ok(dir.join(format!("offline-{:.16}.act", master.to_hex())))
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct SignedView {
+    signed: String,
+    token_id: String,
+    /// Keys with amounts in token's smallest units, the last one being change.
+    outputs: Vec<(String, String)>,
+}
+
+/// Signs a spend exported by a watch-only copy of the wallet, without connecting to the network.
+/// Wallet of this machine is kept locally.
+#[tauri::command]
+async fn sign_offline(
+    evm_pk: String,
+    account: u32,
+    unsigned: String,
+    app: AppHandle,
+) -> Result<SignedView, String> {
+    let sk = account_sk(
+        &seed_sk(evm_pk.trim().strip_prefix("0x").unwrap_or(evm_pk.trim()))?,
+        account,
+    );
+    let unsigned = UnsignedSpend::from_hex(&unsigned)?;
+
+    let path = offline_path(&app, &sk.public_key())?;
+    let mut act_wallet = match std::fs::read(&path) {
+        Ok(bytes) => ActWallet::from_bytes(&bytes)?,
+        Err(_) => ActWallet::new(sk.public_key()),
+    };
+    let spend = act_wallet.sign_offline(&sk, &unsigned)?;
+    std::fs::write(&path, act_wallet.to_bytes()?).map_err(|e| format!("{}", e))?;
+
+    Ok(SignedView {
+        signed: signed_to_hex(&spend)?,
+        token_id: format!("{:x}", unsigned.token_id),
+        outputs: unsigned
+            .outputs
+            .iter()
+            .map(|(key, amount)| (key.to_hex(), amount.to_string()))
+            .collect(),
+    })
+}
+
 #[tauri::command]
 async fn is_connected(state: State<'_, Mutex<Option<AppState>>>) -> Result<bool, String> {
     let state_opt = state.lock().await;
@@ -119,6 +384,9 @@ async fn create_token(
     symbol: String,
     decimals: u8,
     total_supply: String,
+    allocations: Vec<(String, String)>,
+    mint_cap: Option<String>,
+    mint_schedule: Vec<(u64, String)>,
     state: State<'_, Mutex<Option<AppState>>>,
 ) -> Result<String, String> {
     let mut state_opt = state.lock().await;
@@ -127,33 +395,126 @@ async fn create_token(
     let client = &mut state.client;
     let evm_wallet = state.wallet.clone();
     let act_wallet = &mut state.act_wallet;
-    let sk = &state.sk;
-
-    let owner = act_wallet.request(None)?;
-    let _ = client
-        .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet.clone()))
-        .await?;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
 
     let total_supply = U256::from_str_radix(&total_supply, 10).map_err(|e| format!("{}", e))?;
+    let mut allocations = allocations
+        .iter()
+        .map(|(key, amount)| {
+            Ok((
+                PublicKey::from_hex(key.trim()).map_err(|e| format!("{}: {}", key, e))?,
+                U256::from_str_radix(amount.trim(), 10).map_err(|e| format!("{}", e))?,
+            ))
+        })
+        .collect::<Result<Vec<(PublicKey, U256)>, String>>()?;
+
+    let mint_policy = non_empty(mint_cap)
+        .map(|cap| -> Result<MintPolicy, String> {
+            Ok(MintPolicy {
+                authority: mint_pk(&sk.public_key()),
+                cap: U256::from_str_radix(cap.trim(), 10).map_err(|e| format!("{}", e))?,
+                schedule: mint_schedule
+                    .iter()
+                    .map(|(from, amount)| {
+                        Ok((
+                            *from,
+                            U256::from_str_radix(amount.trim(), 10)
+                                .map_err(|e| format!("{}", e))?,
+                        ))
+                    })
+                    .collect::<Result<_, String>>()?,
+            })
+        })
+        .transpose()?;
+
+    // rest of the supply goes to this wallet
+    let allocated = allocations
+        .iter()
+        .try_fold(U256::ZERO, |sum, (_key, amount)| sum.checked_add(*amount))
+        .ok_or("Overflow.".to_string())?;
+    let rest = total_supply
+        .checked_sub(allocated)
+        .ok_or("Allocations exceed total supply.".to_string())?;
+    let owner = match rest.is_zero() {
+        true => None,
+        false => {
+            let owner = act_wallet.request(None)?;
+            let _ = client
+                .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet.clone()))
+                .await?;
+            allocations.push((owner, rest));
+            Some(owner)
+        }
+    };
+
     let (genesis_spend, token_id) = client
         .act_create(
             name,
             symbol,
             decimals,
             total_supply,
-            owner,
+            allocations,
+            Some(issuer_pk(&sk.public_key())),
+            mint_policy,
             &PaymentOption::from(evm_wallet.clone()),
         )
         .await?;
 
-    let received_balance = client.act_balance(&owner, vec![genesis_spend]).await?;
+    if let Some(owner) = owner {
+        let received_balance = client.act_balance(&owner, vec![genesis_spend]).await?;
+
+        act_wallet.receive_at(owner, received_balance, token_id, genesis_spend, now())?;
+        act_wallet.record(Transaction::received(
+            token_id,
+            received_balance,
+            genesis_spend,
+            None,
+        ));
+        let _ = client
+            .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet))
+            .await?;
+    }
+    println!("Genesis spend: {}", genesis_spend.to_hex());
+
+    Ok(format!("{:x}", token_id))
+}
+
+/// Mints tokens to this wallet, as allowed by token's minting policy. Returns the mint spend.
+#[tauri::command]
+async fn mint(
+    token_id: String,
+    amount: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let client = &mut state.client;
+    let evm_wallet = state.wallet.clone();
+    let act_wallet = &mut state.act_wallet;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+
+    let token_id: XorName = parse_xorname(&token_id)?;
+    let info = client.act_token_info(&token_id).await?;
+    let amount: U256 = Decimal::from_string(amount, info.decimals)?;
+
+    let owner = act_wallet.request(Some(token_id))?;
+    let mint_spend = client
+        .act_mint(
+            token_id,
+            &mint_sk(sk),
+            vec![(owner, amount)],
+            &PaymentOption::from(evm_wallet.clone()),
+        )
+        .await?;
 
-    act_wallet.receive(received_balance, token_id, genesis_spend)?;
+    act_wallet.receive_at(owner, amount, token_id, mint_spend, now())?;
+    act_wallet.record(Transaction::received(token_id, amount, mint_spend, None));
     let _ = client
         .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet))
         .await?;
 
-    Ok(format!("{:x}", token_id))
+    Ok(mint_spend.to_hex())
 }
 
 fn parse_xorname(xorname_str: &str) -> Result<XorName, String> {
@@ -168,6 +529,11 @@ fn parse_xorname(xorname_str: &str) -> Result<XorName, String> {
 #[tauri::command]
 async fn request(
     token_id: String,
+    label: Option<String>,
+    amount: Option<String>,
+    memo: Option<String>,
+    expiry: Option<u64>,
+    callback: Option<String>,
     state: State<'_, Mutex<Option<AppState>>>,
 ) -> Result<String, String> {
     let mut state_opt = state.lock().await;
@@ -176,18 +542,67 @@ async fn request(
     let client = &mut state.client;
     let evm_wallet = state.wallet.clone();
     let act_wallet = &mut state.act_wallet;
-    let sk = &state.sk;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
 
     let token_id = parse_xorname(&token_id)?;
 
-    client.act_token_info(&token_id).await?;
+    let info = client.act_token_info(&token_id).await?;
+
+    let amount_units = non_empty(amount.clone())
+        .map(|amount| Decimal::from_string(amount, info.decimals))
+        .transpose()?;
 
-    let public_key = act_wallet.request(Some(token_id))?;
+    let public_key = act_wallet.request_payment(Some(token_id), amount_units, expiry)?;
+    if let Some(label) = label {
+        act_wallet.book_mut().set_request_label(public_key, &label);
+    }
     client
         .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet))
         .await?;
 
-    Ok(public_key.to_hex())
+    let mut payment_request = PaymentRequest::new(token_id, public_key);
+    if let Some(amount) = non_empty(amount) {
+        payment_request = payment_request.with_amount(&amount)?;
+    }
+    payment_request.memo = non_empty(memo);
+    payment_request.expiry = expiry;
+    payment_request.callback = non_empty(callback);
+    payment_request.inbox = Some(inbox_pk(&act_wallet.master()));
+
+    Ok(payment_request.to_string())
+}
+
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.filter(|t| !t.trim().is_empty())
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct PaymentRequestView {
+    token_id: String,
+    key: String,
+    amount: Option<String>,
+    memo: Option<String>,
+    expiry: Option<u64>,
+    callback: Option<String>,
+    inbox: Option<String>,
+    expired: bool,
+}
+
+/// Decodes `act:` URI, eg. to fill in the payment form.
+#[tauri::command]
+fn parse_payment_request(uri: String) -> Result<PaymentRequestView, String> {
+    let request: PaymentRequest = uri.parse()?;
+
+    Ok(PaymentRequestView {
+        token_id: format!("{:x}", request.token_id),
+        key: request.key.to_hex(),
+        amount: request.amount.clone(),
+        memo: request.memo.clone(),
+        expiry: request.expiry,
+        callback: request.callback.clone(),
+        inbox: request.inbox.map(|inbox| inbox.to_hex()),
+        expired: request.is_expired(now()),
+    })
 }
 
 #[tauri::command]
@@ -195,140 +610,646 @@ async fn pay(
     token_id: String,
     amount: String,
     to: String,
+    request: Option<String>,
+    memo: Option<String>,
+    encrypt_memo: bool,
+    accept_unverified: bool,
+    lock: Vec<(u64, String)>,
     state: State<'_, Mutex<Option<AppState>>>,
-) -> Result<String, String> {
+) -> Result<PaymentView, String> {
     let mut state_opt = state.lock().await;
     let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
 
     let client = &mut state.client;
     let evm_wallet = &state.wallet;
     let act_wallet = &mut state.act_wallet;
-    let sk = &state.sk;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
 
     let token_id: XorName = parse_xorname(&token_id)?;
 
     let info = client.act_token_info(&token_id).await?;
     let amount: U256 = Decimal::from_string(amount, info.decimals)?;
+    let releases = lock
+        .into_iter()
+        .map(|(time, amount)| Ok((time, Decimal::from_string(amount, info.decimals)?)))
+        .collect::<Result<Vec<(u64, U256)>, String>>()?;
+
+    let to: PublicKey = match PublicKey::from_hex(&to) {
+        Ok(to) => to,
+        Err(e) => act_wallet.book().contact_key(&to, token_id).ok_or(format!(
+            "Neither a contact with key for this token, nor {}",
+            e
+        ))?,
+    };
+
+    let memo = non_empty(memo)
+        .map(|text| Memo::new(&text, encrypt_memo))
+        .transpose()?;
+
+    // payment request filled in, if the payment still fulfills it
+    let request = non_empty(request)
+        .map(|uri| uri.parse::<PaymentRequest>().map(|request| (uri, request)))
+        .transpose()?
+        .filter(|(_uri, request)| request.key == to && request.token_id == token_id);
+
+    if accept_unverified {
+        let accepted = act_wallet.accept_unverified(token_id);
+        println!("Accepted unverified spends: {}", accepted);
+    }
 
-    let to: PublicKey = PublicKey::from_hex(&to).map_err(|e| format!("{}", e))?;
-
-    let payer_sk = sk.derive_child(
-        &act_wallet
-            .index_of_token(token_id)
-            .ok_or("Key not found".to_string())?
-            .to_be_bytes::<32>(),
-    );
+    let spend_result = match releases.is_empty() {
+        true => {
+            client
+                .act_spend(
+                    act_wallet,
+                    sk,
+                    token_id,
+                    amount,
+                    to,
+                    memo.as_ref(),
+                    &PaymentOption::from(evm_wallet.clone()),
+                )
+                .await
+        }
+        false => {
+            client
+                .act_spend_locked(
+                    act_wallet,
+                    sk,
+                    token_id,
+                    amount,
+                    to,
+                    releases,
+                    memo.as_ref(),
+                    &PaymentOption::from(evm_wallet.clone()),
+                )
+                .await
+        }
+    };
+    // TODO: validate
 
-    let (input_spends, sum, rest_key) = act_wallet.take_to_spend(token_id.clone())?;
+    // failed payment is also recorded in history
     let _ = client
         .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet.clone()))
         .await?;
-    println!("Inputs: {:?}", (&input_spends, sum));
-
-    let rest_amount = sum
-        .checked_sub(amount) // arg?, arg
-        .ok_or("Overflow".to_string())?;
-
-    let spend = GraphEntry::new(
-        &payer_sk,    // arg
-        input_spends, // arg
-        token_id.0,   // arg
-        vec![
-            (to, amount.to_be_bytes()),            // arg
-            (rest_key, rest_amount.to_be_bytes()), // arg
-        ],
-    );
-    // TODO: validate
+    let mut receipt = spend_result?;
+    println!("Payer Wallet: {:?}", act_wallet);
+
+    let inbox = request.as_ref().and_then(|(_uri, request)| request.inbox);
+    receipt.request = request.map(|(uri, _request)| uri);
+
+    if let Some(inbox) = inbox {
+        // payee can still validate the spend from the network
+        let proof = match client
+            .act_proof_publish(&receipt.spend, &PaymentOption::from(evm_wallet.clone()))
+            .await
+        {
+            Ok(address) => Some(address),
+            Err(e) => {
+                println!("Could not publish proof bundle: {}", e);
+                None
+            }
+        };
+        let notice = Notice {
+            spend: receipt.spend.owner,
+            proof,
+        };
+        // payment is done anyway, payee can still receive it manually;
+        // inbox slot found free is saved with the wallet next time
+        if let Err(e) = client
+            .act_inbox_notify(
+                act_wallet,
+                &inbox,
+                &notice,
+                &PaymentOption::from(evm_wallet.clone()),
+            )
+            .await
+        {
+            println!("Could not notify payee: {}", e);
+        }
+    }
+
+    PaymentView::try_from(&receipt)
+}
 
-    let (_paid, spend_address) = client
-        .graph_entry_put(spend, PaymentOption::from(evm_wallet.clone()))
-        .await // arg
-        .map_err(|e| format!("{}", e))?;
+#[derive(Clone, Debug, serde::Serialize)]
+struct PaymentView {
+    spend: String,
+    receipt: String,
+}
 
-    println!("Spend GraphEntry: {}", spend_address);
+impl TryFrom<&Receipt> for PaymentView {
+    type Error = String;
 
-    act_wallet.receive(rest_amount, token_id, *spend_address.owner())?;
-    let _ = client
-        .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet.clone()))
-        .await?;
-    println!("Payer Wallet: {:?}", act_wallet);
+    fn try_from(receipt: &Receipt) -> Result<Self, Self::Error> {
+        Ok(Self {
+            spend: receipt.spend.owner.to_hex(),
+            receipt: receipt.to_hex()?,
+        })
+    }
+}
 
-    Ok(spend_address.owner().to_hex())
+#[derive(Clone, Debug, serde::Serialize)]
+struct ReceiptView {
+    token_id: String,
+    symbol: String,
+    amount: String,
+    payee: String,
+    spend: String,
+    request: Option<String>,
+    /// Receipt is a proof of burn.
+    burn: bool,
 }
 
+/// Checks a receipt against the network. Anyone can do this, no wallet needed.
 #[tauri::command]
-async fn receive(
-    spend_address: String,
+async fn verify_receipt(
+    receipt: String,
     state: State<'_, Mutex<Option<AppState>>>,
-) -> Result<(), String> {
+) -> Result<ReceiptView, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let receipt = Receipt::from_hex(&receipt)?;
+    state.client.act_verify_receipt(&receipt).await?;
+
+    let info = state.client.act_token_info(&receipt.token_id).await?;
+
+    Ok(ReceiptView {
+        token_id: format!("{:x}", receipt.token_id),
+        symbol: info.symbol,
+        amount: Decimal::to_string(receipt.amount, info.decimals)?,
+        payee: receipt.payee()?.to_hex(),
+        spend: receipt.spend.owner.to_hex(),
+        request: receipt.request.clone(),
+        burn: receipt.is_burn()?,
+    })
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct InvoiceView {
+    merchant: String,
+    merchant_name: String,
+    number: String,
+    token_id: String,
+    symbol: String,
+    /// Description and amount.
+    items: Vec<(String, String)>,
+    amount_due: String,
+    created: u64,
+    expiry: Option<u64>,
+    expired: bool,
+}
+
+/// Creates, signs and stores an invoice. Items are descriptions with amounts. Returns invoice ID.
+#[tauri::command]
+async fn invoice_create(
+    token_id: String,
+    merchant_name: String,
+    number: String,
+    items: Vec<(String, String)>,
+    expiry: Option<u64>,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
     let mut state_opt = state.lock().await;
     let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
 
     let client = &mut state.client;
-    let evm_wallet = state.wallet.clone();
+    let payment = PaymentOption::from(state.wallet.clone());
     let act_wallet = &mut state.act_wallet;
-    let sk = &state.sk;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
 
-    println!("Receive spend: {}", spend_address);
-    let spend_address =
-        GraphEntryAddress::from_hex(&spend_address).map_err(|e| format!("{}", e))?;
+    let token_id = parse_xorname(&token_id)?;
+    let info = client.act_token_info(&token_id).await?;
 
-    let spend = client
-        .graph_entry_get(&spend_address)
-        .await
-        .map_err(|e| format!("{}", e))?;
-    println!("Receive spend GE: {:?}", spend);
+    let items = items
+        .into_iter()
+        .map(|(description, amount)| {
+            Ok(LineItem {
+                description,
+                amount: Decimal::from_string(amount, info.decimals)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if items.is_empty() {
+        return Err("Invoice has no items.".into());
+    }
+    let amount_due = items
+        .iter()
+        .try_fold(U256::ZERO, |sum, item| sum.checked_add(item.amount))
+        .ok_or("Overflow.")?;
+
+    let invoice = Invoice {
+        merchant: sk.public_key(),
+        merchant_name,
+        number,
+        token_id,
+        key: act_wallet.request_payment(Some(token_id), Some(amount_due), expiry)?,
+        items,
+        created: now(),
+        expiry,
+        inbox: Some(inbox_pk(&act_wallet.master())),
+    };
+    let invoice_id = client
+        .act_invoice_put(&SignedInvoice::sign(invoice, sk)?, &payment)
+        .await?;
 
-    let token_id = XorName(spend.content);
-    let pk = act_wallet
-        .pk_of_token(token_id)
-        .ok_or("Payment has not been requested".to_string())?;
-    println!("Receive pk: {:.4}(...)", pk.to_hex());
+    client.act_wallet_save(act_wallet, sk, &payment).await?;
 
-    let (amount, overflow, empty) = spend
-        .descendants
-        .iter()
-        .filter(|(public_key, _)| *public_key == pk)
-        .map(|(_, data)| U256::from_be_bytes::<32>(*data))
-        .fold(
-            (U256::ZERO, false, true),
-            |(sum, any_overflow, _empty), n| {
-                let (added, this_overflow) = sum.overflowing_add(n);
-                (added, any_overflow || this_overflow, false)
-            },
-        );
-    println!(
-        "Receive (amount, overflow, empty): {:?}",
-        (&amount, &overflow, &empty)
-    );
+    Ok(format!("{:x}", invoice_id))
+}
 
-    if empty {
-        return Err("Could not find your Public Key in the spend".to_string());
-    }
+#[tauri::command]
+async fn invoice_get(
+    invoice_id: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<InvoiceView, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
 
-    if overflow {
-        return Err("Overflow".to_string());
-    }
+    let invoice = state
+        .client
+        .act_invoice_get(&parse_xorname(&invoice_id)?)
+        .await?
+        .invoice;
+    let info = state.client.act_token_info(&invoice.token_id).await?;
+
+    Ok(InvoiceView {
+        merchant: invoice.merchant.to_hex(),
+        merchant_name: invoice.merchant_name.clone(),
+        number: invoice.number.clone(),
+        token_id: format!("{:x}", invoice.token_id),
+        symbol: info.symbol,
+        items: invoice
+            .items
+            .iter()
+            .map(|item| {
+                Ok((
+                    item.description.clone(),
+                    Decimal::to_string(item.amount, info.decimals)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        amount_due: Decimal::to_string(invoice.amount_due()?, info.decimals)?,
+        created: invoice.created,
+        expiry: invoice.expiry,
+        expired: invoice.is_expired(now()),
+    })
+}
 
-    if act_wallet.received_spend(token_id, *spend_address.owner()) {
-        return Err("Already received this spend".to_string());
-    }
+#[tauri::command]
+async fn invoice_pay(
+    invoice_id: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<PaymentView, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
 
-    act_wallet.receive(amount, token_id, *spend_address.owner())?;
-    println!("Receive wallet: {:?}", act_wallet);
-    let _ = client
-        .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet))
-        .await?;
+    let client = &mut state.client;
+    let payment = PaymentOption::from(state.wallet.clone());
+    let act_wallet = &mut state.act_wallet;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
 
-    Ok(())
+    let spend_result = client
+        .act_invoice_pay(act_wallet, sk, &parse_xorname(&invoice_id)?, &payment)
+        .await;
+
+    // failed payment is also recorded in history
+    client.act_wallet_save(act_wallet, sk, &payment).await?;
+
+    PaymentView::try_from(&spend_result?)
 }
 
+/// Status of an invoice issued by this wallet, according to payments received so far.
 #[tauri::command]
-async fn balance(state: State<'_, Mutex<Option<AppState>>>) -> Result<(String, String), String> {
+async fn invoice_status(
+    invoice_id: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
     let state_opt = state.lock().await;
     let state = state_opt.as_ref().ok_or("Not connected.")?;
 
-    let wallet = state.wallet.clone();
+    let invoice_id = parse_xorname(&invoice_id)?;
+    let spends = state.act_wallet.invoice_payments(&invoice_id);
+    let unverified = state.act_wallet.invoice_unverified(&invoice_id).len();
+
+    let status = state.client.act_invoice_status(&invoice_id, spends).await?;
+    let invoice = state.client.act_invoice_get(&invoice_id).await?.invoice;
+    let info = state.client.act_token_info(&invoice.token_id).await?;
+
+    let status = match status {
+        InvoiceStatus::Unpaid => "Unpaid".to_string(),
+        InvoiceStatus::Underpaid(paid) => format!(
+            "Underpaid, {} of {} {} received",
+            Decimal::to_string(paid, info.decimals)?,
+            Decimal::to_string(invoice.amount_due()?, info.decimals)?,
+            info.symbol
+        ),
+        InvoiceStatus::Paid(paid) => format!(
+            "Paid, {} {} received",
+            Decimal::to_string(paid, info.decimals)?,
+            info.symbol
+        ),
+        InvoiceStatus::Expired => "Expired".to_string(),
+    };
+
+    Ok(match unverified {
+        0 => status,
+        _ => format!("{status}, {unverified} unverified payment(s) not counted"),
+    })
+}
+
+/// Describes mismatch of a payment with its request, `None` if it's as requested.
+fn describe_match(request: &RequestMatch, decimals: u8) -> Result<Option<String>, String> {
+    let mut notes = Vec::new();
+    if let Some(missing) = request.underpaid() {
+        notes.push(format!(
+            "underpaid by {}",
+            Decimal::to_string(missing, decimals)?
+        ));
+    }
+    if let Some(excess) = request.overpaid() {
+        notes.push(format!(
+            "overpaid by {}",
+            Decimal::to_string(excess, decimals)?
+        ));
+    }
+    if request.late {
+        notes.push("paid after expiry".to_string());
+    }
+
+    Ok(match notes.is_empty() {
+        true => None,
+        false => Some(notes.join(", ")),
+    })
+}
+
+/// Receives all outputs of the spend paying to this wallet. Returns note on how the payments
+/// match their requests, if they don't match.
+#[tauri::command]
+async fn receive(
+    spend_address: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<Option<String>, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let client = &mut state.client;
+    let act_wallet = &mut state.act_wallet;
+
+    let spend_address =
+        GraphEntryAddress::from_hex(&spend_address).map_err(|e| format!("{}", e))?;
+
+    let received = client
+        .act_receive(
+            act_wallet,
+            state.sk.as_ref(),
+            *spend_address.owner(),
+            &mut state.validation_cache,
+        )
+        .await?;
+    println!("Receive wallet: {:?}", act_wallet);
+
+    let mut notes = Vec::new();
+    for payment in received.iter() {
+        if !payment.verified {
+            notes.push("not fully verified".to_string());
+        }
+        if let Some(request) = &payment.request {
+            let decimals = client.act_token_info(&payment.token_id).await?.decimals;
+            notes.extend(describe_match(request, decimals)?);
+        }
+    }
+    save_act_wallet(&app, state).await?;
+
+    Ok(match notes.is_empty() {
+        true => None,
+        false => Some(notes.join("; ")),
+    })
+}
+
+/// Validates unverified funds again, with the biggest budget. Returns number of spends left unverified.
+#[tauri::command]
+async fn validate_unverified(
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<usize, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let left = state
+        .client
+        .act_validate_unverified(
+            &mut state.act_wallet,
+            MAX_VALIDATION_BUDGET,
+            &mut state.validation_cache,
+        )
+        .await?;
+    save_act_wallet(&app, state).await?;
+
+    Ok(left)
+}
+
+/// Publishes a marker of an invalid GraphEntry. Returns description of the violated rule.
+#[tauri::command]
+async fn mark_invalid(
+    entry: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let state_opt = state.lock().await;
+    let state: &AppState = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+    let entry = PublicKey::from_hex(entry.trim()).map_err(|e| format!("{}", e))?;
+
+    let marker = state
+        .client
+        .act_mark_invalid(entry, sk, &PaymentOption::from(state.wallet.clone()))
+        .await?;
+
+    Ok(marker.rule.description().to_string())
+}
+
+/// Checkpoints outputs of given spends of a token created by this wallet.
+/// Returns number of the checkpoint.
+#[tauri::command]
+async fn checkpoint(
+    token_id: String,
+    spends: Vec<String>,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<u64, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+    let token_id = parse_xorname(&token_id)?;
+    if state.client.act_token_info(&token_id).await?.issuer != Some(issuer_pk(&sk.public_key())) {
+        return Err("Token was not created by this wallet.".into());
+    }
+    let spends = spends
+        .iter()
+        .map(|spend| PublicKey::from_hex(spend.trim()).map_err(|e| format!("{}", e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let number = state
+        .client
+        .act_checkpoint_spends(
+            token_id,
+            &issuer_sk(sk),
+            spends,
+            &mut state.validation_cache,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await;
+    if let Err(e) = state.validation_cache.save() {
+        println!("Validation cache not saved: {}", e);
+    }
+
+    number
+}
+
+/// Receives payments announced in wallet's inbox. Returns number of received payments.
+#[tauri::command]
+async fn check_inbox(
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<usize, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let sk = state.sk.clone().ok_or("Watch-only wallet.")?;
+    let before = state.act_wallet.clone();
+
+    let received = state
+        .client
+        .act_inbox_receive(&mut state.act_wallet, &sk, &mut state.validation_cache)
+        .await?;
+    println!("Inbox received: {:?}", received);
+
+    if state.act_wallet != before {
+        save_act_wallet(&app, state).await?;
+    }
+
+    Ok(received.len())
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct PaymentReceived {
+    token_id: String,
+    symbol: String,
+    amount: String,
+    /// How the payment differs from its request.
+    note: Option<String>,
+}
+
+/// Runs in background for the whole app's lifetime, receiving payments announced in the inbox
+/// (or following spends of a watch-only wallet). Emits `payment-received` for every payment
+/// and `balances-changed` whenever the wallet changed.
+async fn watch_payments(app: AppHandle) {
+    loop {
+        tokio::time::sleep(WATCH_INTERVAL).await;
+
+        if let Err(e) = watch_once(&app).await {
+            println!("Watcher: {}", e);
+        }
+    }
+}
+
+async fn watch_once(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<Option<AppState>>>();
+
+    // network is read with copies, so that commands are not blocked meanwhile
+    let (client, sk, before, mut cache) = {
+        let mut state_opt = state.lock().await;
+        let Some(state) = state_opt.as_mut() else {
+            return Ok(()); // not connected yet
+        };
+        (
+            state.client.clone(),
+            state.sk.clone(),
+            state.act_wallet.clone(),
+            std::mem::take(&mut state.validation_cache),
+        )
+    };
+    let mut act_wallet = before.clone();
+
+    let received = match &sk {
+        Some(sk) => {
+            client
+                .act_inbox_receive(&mut act_wallet, sk, &mut cache)
+                .await
+        }
+        None => {
+            // inbox can't be read without the key, incoming payments are received by address
+            client
+                .act_wallet_sync(&mut act_wallet)
+                .await
+                .map(|_sent| Vec::new())
+        }
+    };
+
+    let (saved, received) = {
+        let mut state_opt = state.lock().await;
+        let Some(state) = state_opt.as_mut() else {
+            return Ok(()); // disconnected meanwhile
+        };
+        cache.merge(std::mem::take(&mut state.validation_cache));
+        state.validation_cache = cache;
+        let received = received?;
+
+        // changed meanwhile, unchanged inbox slot makes the next round read the payments again
+        if state.act_wallet != before || act_wallet == before {
+            return Ok(());
+        }
+        state.act_wallet = act_wallet;
+        (save_act_wallet(app, state).await, received)
+    };
+
+    for payment in received {
+        if let Err(e) = emit_payment(app, &client, &payment).await {
+            println!("Payment not announced: {}", e);
+        }
+    }
+    if let Err(e) = app.emit("balances-changed", ()) {
+        println!("Balances change not announced: {}", e);
+    }
+
+    saved
+}
+
+/// Emits `payment-received`, with note on how the payment differs from its request.
+async fn emit_payment(app: &AppHandle, client: &Client, payment: &Received) -> Result<(), String> {
+    let info = client.act_token_info(&payment.token_id).await?;
+    let mut note = match &payment.request {
+        Some(request) => describe_match(request, info.decimals)?,
+        None => None,
+    };
+    if !payment.verified {
+        note = Some(match note {
+            Some(note) => format!("{}, not fully verified", note),
+            None => "not fully verified".to_string(),
+        });
+    }
+
+    app.emit(
+        "payment-received",
+        PaymentReceived {
+            token_id: format!("{:x}", payment.token_id),
+            symbol: info.symbol,
+            amount: Decimal::to_string(payment.amount, info.decimals)?,
+            note,
+        },
+    )
+    .map_err(|e| format!("{}", e))
+}
+
+#[tauri::command]
+async fn balance(state: State<'_, Mutex<Option<AppState>>>) -> Result<(String, String), String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let wallet = state.wallet.clone();
 
     let ant = format!(
         "{}",
@@ -430,6 +1351,862 @@ async fn act_balances(
     )
 }
 
+#[derive(Clone, Debug, serde::Serialize)]
+struct LockedView {
+    symbol: String,
+    locked: String,
+    spendable: String,
+    /// Seconds since UNIX epoch.
+    next_unlock: Option<u64>,
+}
+
+/// Locked part of each token's balance, for tokens with locked funds.
+#[tauri::command]
+async fn locked_balances(
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<HashMap<String, LockedView>, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let wallet = &state.act_wallet;
+
+    let now = now();
+    let mut views = HashMap::new();
+    for token_id in wallet.balance_total().into_keys() {
+        let locked = wallet.locked_balance(token_id, now)?;
+        if locked.is_zero() {
+            continue;
+        }
+        let spendable = wallet
+            .balance(token_id)?
+            .checked_sub(locked)
+            .unwrap_or(U256::ZERO);
+        let info = state.client.act_token_info(&token_id).await?;
+
+        views.insert(
+            format!("{:x}", token_id),
+            LockedView {
+                symbol: info.symbol,
+                locked: Decimal::to_string(locked, info.decimals)?,
+                spendable: Decimal::to_string(spendable, info.decimals)?,
+                next_unlock: wallet.next_unlock(token_id, now),
+            },
+        );
+    }
+
+    Ok(views)
+}
+
+/// Loads wallet of an account, or creates it, if it's not yet on the network.
+async fn load_account(
+    client: &mut Client,
+    seed: &SecretKey,
+    account: u32,
+    evm_wallet: &Wallet,
+) -> Result<(SecretKey, ActWallet), String> {
+    let sk = account_sk(seed, account);
+
+    let act_wallet = match client.act_wallet_get(&sk).await? {
+        Some(w) => w,
+        None => {
+            let w = ActWallet::new(sk.public_key());
+            client
+                .act_wallet_save(&w, &sk, &PaymentOption::from(evm_wallet.clone()))
+                .await?;
+            w
+        }
+    };
+
+    Ok((sk, act_wallet))
+}
+
+/// Returns current account and names of all accounts, main account first.
+#[tauri::command]
+async fn accounts(state: State<'_, Mutex<Option<AppState>>>) -> Result<(u32, Vec<String>), String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let mut names = vec!["Main".to_string()];
+    names.extend(state.accounts.iter().cloned());
+
+    Ok((state.account, names))
+}
+
+#[tauri::command]
+async fn account_add(
+    name: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<u32, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let seed = state.seed.clone().ok_or("Watch-only wallet.")?;
+
+    let account = if state.account == MAIN_ACCOUNT {
+        let account = state.act_wallet.add_account(&name)?;
+        save_act_wallet(&app, state).await?;
+        state.accounts = state.act_wallet.accounts().to_vec();
+        account
+    } else {
+        let mut main = state
+            .client
+            .act_wallet_get(&seed)
+            .await?
+            .ok_or("Main wallet not found.")?;
+        let account = main.add_account(&name)?;
+        state
+            .client
+            .act_wallet_save(&main, &seed, &PaymentOption::from(state.wallet.clone()))
+            .await?;
+        state.accounts = main.accounts().to_vec();
+        account
+    };
+
+    Ok(account)
+}
+
+#[tauri::command]
+async fn account_switch(
+    account: u32,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let seed = state.seed.clone().ok_or("Watch-only wallet.")?;
+    if account as usize > state.accounts.len() {
+        return Err("No such account.".to_string());
+    }
+
+    let (sk, act_wallet) = load_account(&mut state.client, &seed, account, &state.wallet).await?;
+    println!("Account {} wallet: {:?}", account, act_wallet);
+
+    state.sk = Some(sk);
+    state.act_wallet = act_wallet;
+    state.account = account;
+
+    Ok(())
+}
+
+/// Destroys tokens. Returns the spend and its receipt, which is a proof of burn.
+#[tauri::command]
+async fn burn(
+    token_id: String,
+    amount: String,
+    accept_unverified: bool,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<PaymentView, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let client = &mut state.client;
+    let evm_wallet = &state.wallet;
+    let act_wallet = &mut state.act_wallet;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+
+    let token_id: XorName = parse_xorname(&token_id)?;
+
+    let info = client.act_token_info(&token_id).await?;
+    let amount: U256 = Decimal::from_string(amount, info.decimals)?;
+
+    if accept_unverified {
+        let accepted = act_wallet.accept_unverified(token_id);
+        println!("Accepted unverified spends: {}", accepted);
+    }
+
+    let burn_result = client
+        .act_burn(
+            act_wallet,
+            sk,
+            token_id,
+            amount,
+            &PaymentOption::from(evm_wallet.clone()),
+        )
+        .await;
+
+    // failed burn is also recorded in history
+    let _ = client
+        .act_wallet_save(&act_wallet, sk, &PaymentOption::from(evm_wallet.clone()))
+        .await?;
+
+    PaymentView::try_from(&burn_result?)
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct SupplyView {
+    token_id: String,
+    symbol: String,
+    total_supply: String,
+    minted: String,
+    burned: String,
+    circulating: String,
+    /// Burned by this wallet.
+    burned_here: String,
+    spends: usize,
+    complete: bool,
+}
+
+/// Follows spends of a token from its genesis, summing burned amounts.
+#[tauri::command]
+async fn supply_audit(
+    token_id: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<SupplyView, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let token_id = parse_xorname(token_id.trim())?;
+    let audit = state
+        .client
+        .act_supply_audit(token_id, SUPPLY_AUDIT_SPENDS)
+        .await?;
+    let info = state.client.act_token_info(&audit.token_id).await?;
+
+    Ok(SupplyView {
+        token_id: format!("{:x}", audit.token_id),
+        symbol: info.symbol,
+        total_supply: Decimal::to_string(audit.total_supply, info.decimals)?,
+        minted: Decimal::to_string(audit.minted, info.decimals)?,
+        burned: Decimal::to_string(audit.burned, info.decimals)?,
+        circulating: Decimal::to_string(audit.circulating(), info.decimals)?,
+        burned_here: Decimal::to_string(state.act_wallet.burned(audit.token_id)?, info.decimals)?,
+        spends: audit.spends,
+        complete: audit.complete,
+    })
+}
+
+/// Moves tokens from current account to other one. Returns spend address.
+#[tauri::command]
+async fn transfer(
+    token_id: String,
+    amount: String,
+    to_account: u32,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let seed = state.seed.clone().ok_or("Watch-only wallet.")?;
+    let from_sk = state.sk.clone().ok_or("Watch-only wallet.")?;
+    if to_account == state.account {
+        return Err("Cannot transfer to the same account.".to_string());
+    }
+    if to_account as usize > state.accounts.len() {
+        return Err("No such account.".to_string());
+    }
+
+    let token_id = parse_xorname(&token_id)?;
+    let info = state.client.act_token_info(&token_id).await?;
+    let amount: U256 = Decimal::from_string(amount, info.decimals)?;
+
+    let (to_sk, mut to_wallet) =
+        load_account(&mut state.client, &seed, to_account, &state.wallet).await?;
+    let payment = PaymentOption::from(state.wallet.clone());
+
+    let spend_result = state
+        .client
+        .act_transfer(
+            &mut state.act_wallet,
+            &from_sk,
+            &mut to_wallet,
+            token_id,
+            amount,
+            &payment,
+        )
+        .await;
+
+    let spend = match spend_result {
+        Ok(spend) => spend,
+        Err(e) => {
+            // failed transfer is also recorded in history
+            save_act_wallet(&app, state).await?;
+            return Err(e);
+        }
+    };
+
+    // receiving account first, sending one would find its spend again when synchronized
+    if let Err(e) = state
+        .client
+        .act_wallet_save(&to_wallet, &to_sk, &payment)
+        .await
+    {
+        save_act_wallet(&app, state).await?;
+        return Err(format!(
+            "Receiving account not saved, receive spend {} there: {}",
+            spend.to_hex(),
+            e
+        ));
+    }
+    save_act_wallet(&app, state).await?;
+
+    Ok(spend.to_hex())
+}
+
+/// Key to give to the creator of a treasury, to become its member.
+#[tauri::command]
+async fn treasury_member_key(state: State<'_, Mutex<Option<AppState>>>) -> Result<String, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+
+    Ok(treasury_member_sk(sk).public_key().to_hex())
+}
+
+/// Creates a treasury needing `threshold` of `members` (member keys) to spend. Returns its address.
+#[tauri::command]
+async fn treasury_create(
+    name: String,
+    threshold: usize,
+    members: Vec<String>,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let members = members
+        .iter()
+        .map(|key| PublicKey::from_hex(key.trim()).map_err(|e| format!("{}", e)))
+        .collect::<Result<Vec<PublicKey>, String>>()?;
+    let treasury = Treasury::deal(name, threshold, members)?;
+    let address = state
+        .client
+        .act_treasury_put(&treasury, &PaymentOption::from(state.wallet.clone()))
+        .await?;
+
+    Ok(format!("{:x}", address))
+}
+
+/// Treasury with its wallet, kept by each member under a key derived from member's key.
+async fn load_treasury(
+    state: &AppState,
+    address: &str,
+) -> Result<(Treasury, SecretKey, ActWallet), String> {
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+    let address = parse_xorname(address.trim())?;
+    let treasury = state.client.act_treasury_get(&address).await?;
+
+    let wallet_sk = treasury_member_sk(sk).derive_child(&address.0);
+    let wallet = match state.client.act_wallet_get(&wallet_sk).await? {
+        Some(wallet) => wallet,
+        None => ActWallet::new(treasury.pk()),
+    };
+
+    Ok((treasury, wallet_sk, wallet))
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct TreasuryView {
+    name: String,
+    threshold: usize,
+    members: Vec<String>,
+    /// Index of this wallet among members.
+    member: Option<usize>,
+    /// Token ID to symbol and balance.
+    balances: HashMap<String, (String, String)>,
+}
+
+/// Loads a treasury, following spends made by other members.
+#[tauri::command]
+async fn treasury(
+    address: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<TreasuryView, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let (treasury, wallet_sk, mut wallet) = load_treasury(state, &address).await?;
+    let sent = state.client.act_wallet_sync(&mut wallet).await?;
+    println!("Treasury sync sent: {:?}", sent);
+    state
+        .client
+        .act_wallet_save(
+            &wallet,
+            &wallet_sk,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await?;
+
+    let mut balances = HashMap::new();
+    for (token_id, balance) in wallet.balance_total() {
+        let info = state.client.act_token_info(&token_id).await?;
+        balances.insert(
+            format!("{:x}", token_id),
+            (info.symbol, Decimal::to_string(balance?, info.decimals)?),
+        );
+    }
+    let member = state
+        .sk
+        .as_ref()
+        .map(|sk| treasury_member_sk(sk).public_key())
+        .and_then(|key| treasury.members.iter().position(|member| *member == key));
+
+    Ok(TreasuryView {
+        name: treasury.name.clone(),
+        threshold: treasury.threshold(),
+        members: treasury.members.iter().map(|key| key.to_hex()).collect(),
+        member,
+        balances,
+    })
+}
+
+/// Key of the treasury to pay a token to.
+#[tauri::command]
+async fn treasury_deposit_key(
+    address: String,
+    token_id: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let (_treasury, wallet_sk, mut wallet) = load_treasury(state, &address).await?;
+    let key = wallet.request(Some(parse_xorname(&token_id)?))?;
+    state
+        .client
+        .act_wallet_save(
+            &wallet,
+            &wallet_sk,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await?;
+
+    Ok(key.to_hex())
+}
+
+/// Receives a spend paying to the treasury.
+#[tauri::command]
+async fn treasury_receive(
+    address: String,
+    spend_address: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let (_treasury, wallet_sk, mut wallet) = load_treasury(state, &address).await?;
+    let spend_address =
+        GraphEntryAddress::from_hex(&spend_address).map_err(|e| format!("{}", e))?;
+    state
+        .client
+        .act_receive(
+            &mut wallet,
+            None,
+            *spend_address.owner(),
+            &mut state.validation_cache,
+        )
+        .await?;
+    state
+        .client
+        .act_wallet_save(
+            &wallet,
+            &wallet_sk,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Proposes a spend of treasury's funds, for members to approve. Returns address of the proposal.
+#[tauri::command]
+async fn treasury_propose(
+    address: String,
+    token_id: String,
+    amount: String,
+    to: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let (treasury, _wallet_sk, wallet) = load_treasury(state, &address).await?;
+    let token_id = parse_xorname(&token_id)?;
+    let info = state.client.act_token_info(&token_id).await?;
+    let amount: U256 = Decimal::from_string(amount, info.decimals)?;
+    let to = PublicKey::from_hex(to.trim()).map_err(|e| format!("{}", e))?;
+
+    let proposal = wallet.unsigned_spend(token_id, vec![(to, amount)])?;
+    let proposal_address = state
+        .client
+        .act_propose(
+            &treasury,
+            &proposal,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await?;
+
+    Ok(format!("{:x}", proposal_address))
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct ProposalView {
+    token_id: String,
+    symbol: String,
+    spend: String,
+    /// Keys with amounts, the last one being change back to the treasury.
+    outputs: Vec<(String, String)>,
+    /// Indices of members who approved.
+    approvals: Vec<usize>,
+    threshold: usize,
+}
+
+#[tauri::command]
+async fn treasury_proposal(
+    address: String,
+    proposal: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<ProposalView, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+
+    let treasury = state
+        .client
+        .act_treasury_get(&parse_xorname(address.trim())?)
+        .await?;
+    let proposal_address = parse_xorname(proposal.trim())?;
+    let unsigned = state.client.act_proposal(&proposal_address).await?;
+    treasury.check_proposal(&unsigned)?;
+    let approvals = state
+        .client
+        .act_approvals(&treasury, &proposal_address)
+        .await?;
+    let info = state.client.act_token_info(&unsigned.token_id).await?;
+
+    Ok(ProposalView {
+        token_id: format!("{:x}", unsigned.token_id),
+        symbol: info.symbol,
+        spend: unsigned.owner.to_hex(),
+        outputs: unsigned
+            .outputs
+            .iter()
+            .map(|(key, amount)| Ok((key.to_hex(), Decimal::to_string(*amount, info.decimals)?)))
+            .collect::<Result<Vec<(String, String)>, String>>()?,
+        approvals: approvals.into_keys().collect(),
+        threshold: treasury.threshold(),
+    })
+}
+
+/// Approves a proposal with this wallet's key share.
+#[tauri::command]
+async fn treasury_approve(
+    address: String,
+    proposal: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let sk = state.sk.as_ref().ok_or("Watch-only wallet.")?;
+
+    let treasury = state
+        .client
+        .act_treasury_get(&parse_xorname(address.trim())?)
+        .await?;
+    state
+        .client
+        .act_approve(
+            &treasury,
+            &treasury_member_sk(sk),
+            &parse_xorname(proposal.trim())?,
+            &PaymentOption::from(state.wallet.clone()),
+        )
+        .await
+}
+
+/// Publishes an approved proposal. Returns the spend address.
+#[tauri::command]
+async fn treasury_publish(
+    address: String,
+    proposal: String,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<String, String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+    let payment = PaymentOption::from(state.wallet.clone());
+
+    let (treasury, wallet_sk, mut wallet) = load_treasury(state, &address).await?;
+    let spend = state
+        .client
+        .act_publish_approved(&treasury, &parse_xorname(proposal.trim())?, &payment)
+        .await?;
+
+    wallet.follow_spend(XorName(spend.content), &spend)?;
+    state
+        .client
+        .act_wallet_save(&wallet, &wallet_sk, &payment)
+        .await?;
+
+    Ok(spend.owner.to_hex())
+}
+
+/// SVG image of a QR code, eg. for payment request or spend address.
+#[tauri::command]
+fn qr_code(text: String) -> Result<String, String> {
+    let code = qrcode::QrCode::new(text.as_bytes()).map_err(|e| format!("{}", e))?;
+
+    Ok(code
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(200, 200)
+        .dark_color(qrcode::render::svg::Color("#000000"))
+        .light_color(qrcode::render::svg::Color("#ffffff"))
+        .build())
+}
+
+/// Reads text of the first QR code found in an image file's content.
+#[tauri::command]
+fn qr_decode(image: Vec<u8>) -> Result<String, String> {
+    let image = image::load_from_memory(&image)
+        .map_err(|e| format!("{}", e))?
+        .to_luma8();
+
+    let mut prepared = rqrr::PreparedImage::prepare(image);
+    let grids = prepared.detect_grids();
+    let grid = grids.first().ok_or("No QR code found in the image.")?;
+    let (_meta, content) = grid.decode().map_err(|e| format!("{}", e))?;
+
+    Ok(content)
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize)]
+struct AddressBookView {
+    /// Contact name -> token ID -> key
+    contacts: HashMap<String, HashMap<String, String>>,
+    token_labels: HashMap<String, String>,
+    request_labels: HashMap<String, String>,
+}
+
+#[tauri::command]
+async fn address_book(
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<AddressBookView, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let book = state.act_wallet.book();
+
+    Ok(AddressBookView {
+        contacts: book
+            .contacts
+            .iter()
+            .map(|(name, keys)| {
+                (
+                    name.clone(),
+                    keys.iter()
+                        .map(|(token_id, key)| (format!("{:x}", token_id), key.to_hex()))
+                        .collect(),
+                )
+            })
+            .collect(),
+        token_labels: book
+            .token_labels
+            .iter()
+            .map(|(token_id, label)| (format!("{:x}", token_id), label.clone()))
+            .collect(),
+        request_labels: book
+            .request_labels
+            .iter()
+            .map(|(key, label)| (key.to_hex(), label.clone()))
+            .collect(),
+    })
+}
+
+#[tauri::command]
+async fn contact_set(
+    name: String,
+    token_id: String,
+    key: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let token_id = parse_xorname(&token_id)?;
+    let key = PublicKey::from_hex(&key).map_err(|e| format!("{}", e))?;
+
+    state
+        .act_wallet
+        .book_mut()
+        .set_contact(&name, token_id, key)?;
+    save_act_wallet(&app, state).await
+}
+
+#[tauri::command]
+async fn contact_remove(
+    name: String,
+    token_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let token_id = token_id.map(|id| parse_xorname(&id)).transpose()?;
+
+    state
+        .act_wallet
+        .book_mut()
+        .remove_contact(&name, token_id)?;
+    save_act_wallet(&app, state).await
+}
+
+/// Empty label removes it.
+#[tauri::command]
+async fn label_token(
+    token_id: String,
+    label: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let token_id = parse_xorname(&token_id)?;
+
+    state
+        .act_wallet
+        .book_mut()
+        .set_token_label(token_id, &label);
+    save_act_wallet(&app, state).await
+}
+
+/// Empty label removes it.
+#[tauri::command]
+async fn label_request(
+    key: String,
+    label: String,
+    app: AppHandle,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<(), String> {
+    let mut state_opt = state.lock().await;
+    let state: &mut AppState = state_opt.as_mut().ok_or("Not connected.")?;
+
+    let key = PublicKey::from_hex(&key).map_err(|e| format!("{}", e))?;
+
+    state.act_wallet.book_mut().set_request_label(key, &label);
+    save_act_wallet(&app, state).await
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct RequestView {
+    key: String,
+    label: Option<String>,
+    /// `None` if any token is accepted.
+    token_id: Option<String>,
+    symbol: Option<String>,
+    amount: Option<String>,
+    received: String,
+    created: u64,
+    expiry: Option<u64>,
+    status: RequestStatus,
+}
+
+/// Payment requests made with their own keys. All of them if `status` is `None`.
+#[tauri::command]
+async fn requests(
+    status: Option<RequestStatus>,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<Vec<RequestView>, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let wallet = &state.act_wallet;
+    let client = &state.client;
+
+    let mut infos = HashMap::<XorName, TokenInfo>::new();
+    let mut views = Vec::new();
+
+    for (key, request, request_status) in wallet.requests(status, now())? {
+        let info = match request.token_id {
+            Some(token_id) => {
+                if !infos.contains_key(&token_id) {
+                    let info = client.act_token_info(&token_id).await?;
+                    infos.insert(token_id, info);
+                }
+                Some(&infos[&token_id])
+            }
+            None => None,
+        };
+        let decimals = info.map(|info| info.decimals).unwrap_or(0);
+
+        views.push(RequestView {
+            key: key.to_hex(),
+            label: wallet.book().request_label(&key).map(String::from),
+            token_id: request.token_id.map(|id| format!("{:x}", id)),
+            symbol: info.map(|info| info.symbol.clone()),
+            amount: request
+                .amount
+                .map(|amount| Decimal::to_string(amount, decimals))
+                .transpose()?,
+            received: Decimal::to_string(request.received()?, decimals)?,
+            created: request.created,
+            expiry: request.expiry,
+            status: request_status,
+        });
+    }
+
+    Ok(views)
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct HistoryEntry {
+    token_id: String,
+    symbol: String,
+    direction: Direction,
+    amount: String,
+    counterparty: String,
+    /// Name of the counterparty from address book.
+    contact: Option<String>,
+    spend: String,
+    burn: bool,
+    timestamp: u64,
+    memo: Option<String>,
+    status: TxStatus,
+}
+
+#[tauri::command]
+async fn history(
+    token_id: Option<String>,
+    from: Option<u64>,
+    to: Option<u64>,
+    state: State<'_, Mutex<Option<AppState>>>,
+) -> Result<Vec<HistoryEntry>, String> {
+    let state_opt = state.lock().await;
+    let state = state_opt.as_ref().ok_or("Not connected.")?;
+    let wallet = &state.act_wallet;
+    let client = &state.client;
+
+    let token_id = match token_id {
+        Some(id) if !id.is_empty() => Some(parse_xorname(&id)?),
+        _ => None,
+    };
+
+    let mut infos = HashMap::<XorName, TokenInfo>::new();
+    let mut entries = Vec::new();
+
+    for tx in wallet.history(token_id, from, to) {
+        if !infos.contains_key(&tx.token_id) {
+            let info = client.act_token_info(&tx.token_id).await?;
+            infos.insert(tx.token_id, info);
+        }
+        let info = &infos[&tx.token_id];
+
+        entries.push(HistoryEntry {
+            token_id: format!("{:x}", tx.token_id),
+            symbol: info.symbol.clone(),
+            direction: tx.direction,
+            amount: Decimal::to_string(tx.amount, info.decimals)?,
+            counterparty: tx.counterparty.to_hex(),
+            contact: wallet.book().contact_of(&tx.counterparty).map(String::from),
+            spend: tx.spend.to_hex(),
+            burn: tx.is_burn(),
+            timestamp: tx.timestamp,
+            memo: tx.memo.clone(),
+            status: tx.status,
+        });
+    }
+
+    Ok(entries)
+}
+
 struct Decimal;
 
 impl Decimal {
@@ -474,16 +2251,60 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             connect,
+            connect_watch_only,
             is_connected,
+            is_watch_only,
+            export_watch_only,
+            sync,
+            export_unsigned_spend,
+            publish_signed,
+            sign_offline,
             create_token,
             request,
             pay,
             receive,
+            check_inbox,
+            validate_unverified,
+            burn,
+            supply_audit,
+            mint,
+            mark_invalid,
+            checkpoint,
             balance,
             act_balances,
+            locked_balances,
+            history,
+            requests,
+            address_book,
+            contact_set,
+            contact_remove,
+            label_token,
+            label_request,
+            accounts,
+            account_add,
+            account_switch,
+            transfer,
+            treasury_member_key,
+            treasury_create,
+            treasury,
+            treasury_deposit_key,
+            treasury_receive,
+            treasury_propose,
+            treasury_proposal,
+            treasury_approve,
+            treasury_publish,
+            parse_payment_request,
+            invoice_create,
+            invoice_get,
+            invoice_pay,
+            invoice_status,
+            verify_receipt,
+            qr_code,
+            qr_decode,
         ])
         .setup(|app| {
             app.manage(Mutex::new(None::<AppState>));
+            tauri::async_runtime::spawn(watch_payments(app.handle().clone()));
             if let Some(window) = app.webview_windows().iter().next() {
                 window.1.set_theme(Some(Theme::Dark))?;
             }
@@ -511,6 +2332,10 @@ mod tests {
                 symbol: "EACT".to_string(),
                 name: "Example Autonomi Community Token".to_string(),
                 decimals: 18,
+                genesis: None,
+                total_supply: None,
+                issuer: None,
+                mint: None,
             }),
             U256::from_str_radix("10_000_000_000000_000000_000000", 10)
                 .map_err(|e| format!("{}", e)),
@@ -530,6 +2355,10 @@ mod tests {
                 symbol: "EACT2".to_string(),
                 name: "Example Autonomi Community Token".to_string(),
                 decimals: 18,
+                genesis: None,
+                total_supply: None,
+                issuer: None,
+                mint: None,
             }),
             Err("Some example error".to_string()),
         ));
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 function for_existing_element(id: string, f: (el: Element) => void) {
   let element = document.getElementById(id);
@@ -18,109 +19,1076 @@ async function refresh() {
   if (await invoke("is_connected")) {
     await balance();
     for_existing_element("connect", (el) => (el as HTMLElement).hidden = true);
+
+    const watchOnly: boolean = await invoke("is_watch_only");
+    for (const id of ["request", "pay", "invoices", "create-token", "accounts", "treasury"]) {
+      for_existing_query(`#menu li[data-targetid="${id}"]`, (el) => (el as HTMLElement).hidden = watchOnly);
+    }
+    document.querySelectorAll("#watch-only .full-wallet").forEach(
+      (el) => (el as HTMLElement).hidden = watchOnly
+    );
+    document.querySelectorAll("#watch-only .watch-only-wallet").forEach(
+      (el) => (el as HTMLElement).hidden = !watchOnly
+    );
+    for_existing_element("account-select", (el) => (el as HTMLElement).hidden = watchOnly);
+    if (watchOnly) {
+      for_existing_query(`#menu li[data-targetid="watch-only"]`, (el) => (el as HTMLElement).click());
+    }
+  }
+}
+
+async function connect(network: string) {
+  const pkInputEl: HTMLInputElement | null = document.querySelector("#pk-input");
+
+  if (pkInputEl) {
+    const pk = pkInputEl.value || null;
+    console.log("Connecting Autonomi...");
+    const currentPk = await invoke("connect", {
+      network: network,
+      evmPk: pk,
+    });
+    console.log("Connected.");
+
+    if (pk === null) {
+//      await navigator.clipboard.writeText(currentPk);
+      window.alert("Your EVM Private Key, keep it safely: " + currentPk);
+    }
+
+    await refresh();
+  }
+}
+
+async function connectWatchOnly(network: string) {
+  const descriptor = (document.querySelector("#watch-only-connect textarea") as HTMLTextAreaElement)?.value;
+
+  try {
+    console.log("Connecting Autonomi (watch-only)...");
+    await invoke("connect_watch_only", {
+      network: network,
+      descriptor: descriptor,
+    });
+    console.log("Connected.");
+
+    await refresh();
+  } catch (err) {
+    error("" + err, "connect");
+  }
+}
+
+async function exportWatchOnly() {
+  try {
+    const descriptor = await invoke("export_watch_only");
+
+    message("Watch-only descriptor: " + descriptor, "watch-only");
+
+  } catch (err) {
+    error("" + err, "watch-only");
+  }
+}
+
+async function sync() {
+  try {
+    const sent = await invoke("sync");
+
+    message("Synchronized, found outgoing transactions: " + sent, "watch-only");
+
+  } catch (err) {
+    error("" + err, "watch-only");
+  }
+  await balance();
+}
+
+type SignedView = {
+  signed: string,
+  token_id: string,
+  outputs: [string, string][],
+};
+
+async function signOffline() {
+  const evmPk = (document.querySelector("#pk-input") as HTMLInputElement)?.value;
+  const account = (document.querySelector("#sign-offline input") as HTMLInputElement)?.value;
+  const unsigned = (document.querySelector("#sign-offline textarea") as HTMLTextAreaElement)?.value;
+
+  try {
+    const signed: SignedView = await invoke("sign_offline", {
+      evmPk: evmPk,
+      account: parseInt(account),
+      unsigned: unsigned,
+    });
+
+    const outputs = signed.outputs.map(([key, amount]) => `${amount} to ${key}`).join(", ");
+    message(`Signed spend of token ${signed.token_id} (smallest units): ${outputs}`, "sign-offline");
+    for_existing_query("#sign-offline-signed textarea", (el) => (el as HTMLTextAreaElement).value = signed.signed);
+    for_existing_element("sign-offline-signed", (el) => (el as HTMLElement).hidden = false);
+  } catch (err) {
+    error("" + err, "sign-offline");
+  }
+}
+
+async function exportUnsigned() {
+  const tokenId = (document.querySelector("#unsigned-token-id input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#unsigned-amount input") as HTMLInputElement)?.value;
+  const to = (document.querySelector("#unsigned-to input") as HTMLInputElement)?.value;
+
+  try {
+    const unsigned: string = await invoke("export_unsigned_spend", {
+      tokenId: tokenId,
+      amount: amount,
+      to: to,
+    });
+
+    for_existing_query("#unsigned-spend textarea", (el) => (el as HTMLTextAreaElement).value = unsigned);
+    for_existing_element("unsigned-spend", (el) => (el as HTMLElement).hidden = false);
+  } catch (err) {
+    error("" + err, "watch-only");
+  }
+}
+
+async function publishSigned() {
+  const signed = (document.querySelector("#publish-signed textarea") as HTMLTextAreaElement)?.value;
+
+  try {
+    const spend = await invoke("publish_signed", { signed: signed });
+    message("Published spend: " + spend, "watch-only");
+  } catch (err) {
+    error("" + err, "watch-only");
+  }
+  await balance();
+}
+
+/// Lines of "date; amount" as [seconds since UNIX epoch, amount] pairs.
+function parseReleases(text: string): [number, string][] {
+  return text.split("\n")
+    .filter((line) => line.trim())
+    .map((line) => {
+      const [date, amount] = line.split(";", 2);
+      const from = Date.parse(date.trim());
+      if (isNaN(from)) {
+        throw "Wrong date: " + date;
+      }
+      return [Math.floor(from / 1000), (amount ?? "").trim()];
+    });
+}
+
+async function createToken() {
+  const name = (document.querySelector("#create-token-name input") as HTMLInputElement)?.value;
+  const symbol = (document.querySelector("#create-token-symbol input") as HTMLInputElement)?.value;
+  const supply = (document.querySelector("#create-token-supply input") as HTMLInputElement)?.value;
+  const decimals = (document.querySelector("#create-token-decimals input") as HTMLInputElement)?.value;
+  const allocationsText = (document.querySelector("#create-token-allocations textarea") as HTMLTextAreaElement)?.value ?? "";
+  const mintCap = (document.querySelector("#create-token-mint-cap input") as HTMLInputElement)?.value;
+  const scheduleText = (document.querySelector("#create-token-mint-schedule textarea") as HTMLTextAreaElement)?.value ?? "";
+
+  const allocations = allocationsText.split("\n")
+    .filter((line) => line.trim())
+    .map((line) => {
+      const [key, amount] = line.split(";", 2);
+      return [key.trim(), (amount ?? "").trim()];
+    });
+
+  let mintSchedule: [number, string][];
+  try {
+    mintSchedule = parseReleases(scheduleText);
+  } catch (err) {
+    error("" + err, "create-token");
+    return;
+  }
+
+  try {
+    const tokenId = await invoke("create_token", {
+      name: name,
+      symbol: symbol,
+      decimals: parseInt(decimals),
+      totalSupply: supply,
+      allocations: allocations,
+      mintCap: mintCap,
+      mintSchedule: mintSchedule,
+    });
+
+    message("Token ID: " + tokenId, "create-token");
+
+  } catch (err) {
+    error("" + err, "create-token");
+  }
+  await balance();
+}
+
+async function mint() {
+  const tokenId = (document.querySelector("#mint-token-id input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#mint-amount input") as HTMLInputElement)?.value;
+
+  try {
+    const spend = await invoke("mint", {
+      tokenId: tokenId,
+      amount: amount,
+    });
+
+    message("Minted with spend: " + spend, "create-token");
+
+  } catch (err) {
+    error("" + err, "create-token");
+  }
+  await balance();
+}
+
+async function checkpoint() {
+  const tokenId = (document.querySelector("#checkpoint-token-id input") as HTMLInputElement)?.value;
+  const spends = (document.querySelector("#checkpoint-spends textarea") as HTMLTextAreaElement)?.value
+    .split("\n")
+    .filter((line) => line.trim() !== "");
+
+  try {
+    const number = await invoke("checkpoint", {
+      tokenId: tokenId,
+      spends: spends,
+    });
+
+    message("Checkpoint published: " + number, "create-token");
+
+  } catch (err) {
+    error("" + err, "create-token");
+  }
+}
+
+type SupplyView = {
+  token_id: string,
+  symbol: string,
+  total_supply: string,
+  minted: string,
+  burned: string,
+  circulating: string,
+  burned_here: string,
+  spends: number,
+  complete: boolean,
+};
+
+async function supplyAudit() {
+  const tokenId = (document.querySelector("#supply-token-id input") as HTMLInputElement)?.value;
+
+  try {
+    const view: SupplyView = await invoke("supply_audit", {
+      tokenId: tokenId,
+    });
+
+    message(`${view.symbol}: total supply ${view.total_supply} (minted ${view.minted}), burned ${view.burned}`
+      + ` (${view.burned_here} by this wallet), circulating ${view.circulating}.`
+      + ` Spends followed: ${view.spends}` + (view.complete ? "" : ", not all of them, more may be burned"), "create-token");
+
+  } catch (err) {
+    error("" + err, "create-token");
+  }
+}
+
+async function request() {
+  const tokenId = (document.querySelector("#request-token-id input") as HTMLInputElement)?.value;
+  const label = (document.querySelector("#request-label input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#request-amount input") as HTMLInputElement)?.value;
+  const memo = (document.querySelector("#request-memo input") as HTMLInputElement)?.value;
+  const expiry = (document.querySelector("#request-expiry input") as HTMLInputElement)?.valueAsNumber;
+  const callback = (document.querySelector("#request-callback input") as HTMLInputElement)?.value;
+  console.log("tokenId: ", tokenId);
+
+  try {
+    const uri = await invoke("request", {
+      tokenId: tokenId,
+      label: label || null,
+      amount: amount || null,
+      memo: memo || null,
+      expiry: expiry ? Math.floor(expiry / 1000) : null,
+      callback: callback || null,
+    });
+
+    message("Payment request: " + uri, "request");
+    await showQrCode("" + uri, "request");
+
+  } catch (err) {
+    error("" + err, "request");
+  }
+  await balance();
+  await requests();
+}
+
+type RequestView = {
+  key: string,
+  label: string | null,
+  token_id: string | null,
+  symbol: string | null,
+  amount: string | null,
+  received: string,
+  created: number,
+  expiry: number | null,
+  status: string,
+};
+
+function requestRowHtml(view: RequestView): string {
+  const symbol = (view.token_id ? (addressBook.token_labels[view.token_id] ?? view.symbol) : null) ?? "";
+  return `<tr class="${view.status.toLowerCase()}">`
+    + `<td>${new Date(view.created * 1000).toLocaleString()}</td>`
+    + `<td>${view.label ?? ""}</td>`
+    + `<td>${view.amount ? view.amount + " " + symbol : "any"}</td>`
+    + `<td>${view.received} ${symbol}</td>`
+    + `<td>${view.expiry ? new Date(view.expiry * 1000).toLocaleString() : ""}</td>`
+    + `<td>${view.status}</td>`
+    + `<td title="${view.key}">${view.key.substring(0, 8)}...</td>`
+    + "</tr>";
+}
+
+async function requests() {
+  const status = (document.querySelector("#requests-status select") as HTMLSelectElement)?.value;
+
+  try {
+    const views: RequestView[] = await invoke("requests", {
+      status: status || null,
+    });
+
+    for_existing_query("#requests tbody", (el) => el.innerHTML =
+      views.reverse().map(requestRowHtml).join(""));
+
+  } catch (err) {
+    error("" + err, "request");
+  }
+}
+
+async function showQrCode(text: string, panelId: string) {
+  try {
+    const svg: string = await invoke("qr_code", {
+      text: text,
+    });
+    for_existing_query(`#${panelId} .qr`, (el) => el.innerHTML = svg);
+  } catch (err) {
+    error("" + err, panelId);
+  }
+}
+
+async function readQrCode(input: HTMLInputElement, panelId: string): Promise<string | null> {
+  const file = input.files?.[0];
+  if (!file) {
+    return null;
+  }
+
+  try {
+    const image = new Uint8Array(await file.arrayBuffer());
+    const text: string = await invoke("qr_decode", {
+      image: Array.from(image),
+    });
+    return text;
+  } catch (err) {
+    error("" + err, panelId);
+    return null;
+  } finally {
+    input.value = ""; // allow reading the same file again
+  }
+}
+
+type PaymentRequest = {
+  token_id: string,
+  key: string,
+  amount: string | null,
+  memo: string | null,
+  expiry: number | null,
+  callback: string | null,
+  inbox: string | null,
+  expired: boolean,
+};
+
+async function fillPayment() {
+  const uri = (document.querySelector("#pay-uri input") as HTMLInputElement)?.value;
+
+  try {
+    const request: PaymentRequest = await invoke("parse_payment_request", {
+      uri: uri,
+    });
+
+    for_existing_query("#pay-token-id input", (el) => (el as HTMLInputElement).value = request.token_id);
+    for_existing_query("#pay-amount input", (el) => (el as HTMLInputElement).value = request.amount ?? "");
+    for_existing_query("#pay-to input", (el) => (el as HTMLInputElement).value = request.key);
+    for_existing_query("#pay-memo input[type=text]", (el) => (el as HTMLInputElement).value = request.memo ?? "");
+    populateContactsList();
+
+    let info = [];
+    if (request.memo) {
+      info.push("Memo: " + request.memo);
+    }
+    if (request.expiry) {
+      info.push("Valid until: " + new Date(request.expiry * 1000).toLocaleString());
+    }
+    if (request.callback) {
+      info.push("Callback: " + request.callback);
+    }
+
+    if (request.expired) {
+      error("This payment request has expired. " + info.join(", "), "pay");
+    } else {
+      message("Payment request filled in. " + info.join(", "), "pay");
+    }
+
+  } catch (err) {
+    error("" + err, "pay");
+  }
+}
+
+type Payment = {
+  spend: string,
+  receipt: string,
+};
+
+function showReceipt(receipt: string) {
+  for_existing_query("#pay-receipt textarea", (el) => (el as HTMLTextAreaElement).value = receipt);
+  for_existing_element("pay-receipt", (el) => (el as HTMLElement).hidden = false);
+}
+
+async function pay() {
+  const tokenId = (document.querySelector("#pay-token-id input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#pay-amount input") as HTMLInputElement)?.value;
+  const to = (document.querySelector("#pay-to input") as HTMLInputElement)?.value;
+  const uri = (document.querySelector("#pay-uri input") as HTMLInputElement)?.value;
+  const memo = (document.querySelector("#pay-memo input[type=text]") as HTMLInputElement)?.value;
+  const encryptMemo = (document.querySelector("#pay-memo input[type=checkbox]") as HTMLInputElement)?.checked;
+  const acceptUnverified = (document.querySelector("#pay-unverified input") as HTMLInputElement)?.checked;
+  const lockText = (document.querySelector("#pay-lock textarea") as HTMLTextAreaElement)?.value ?? "";
+
+  let lock: [number, string][];
+  try {
+    lock = parseReleases(lockText);
+  } catch (err) {
+    error("" + err, "pay");
+    return;
+  }
+
+  try {
+    const payment: Payment = await invoke("pay", {
+      tokenId: tokenId,
+      amount: amount,
+      to: to,
+      request: uri,
+      memo: memo,
+      encryptMemo: encryptMemo ?? true,
+      acceptUnverified: acceptUnverified ?? false,
+      lock: lock,
+    });
+
+    message("Crated spend: " + payment.spend, "pay");
+    await showQrCode(payment.spend, "pay");
+    showReceipt(payment.receipt);
+
+  } catch (err) {
+    error("" + err, "pay");
+  }
+  await balance();
+}
+
+async function burn() {
+  const tokenId = (document.querySelector("#pay-token-id input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#pay-amount input") as HTMLInputElement)?.value;
+  const acceptUnverified = (document.querySelector("#pay-unverified input") as HTMLInputElement)?.checked;
+
+  if (!confirm(`Burn ${amount} tokens? They will be destroyed forever.`)) {
+    return;
+  }
+
+  try {
+    const payment: Payment = await invoke("burn", {
+      tokenId: tokenId,
+      amount: amount,
+      acceptUnverified: acceptUnverified ?? false,
+    });
+
+    message("Tokens burned with spend: " + payment.spend, "pay");
+    showReceipt(payment.receipt);
+
+  } catch (err) {
+    error("" + err, "pay");
+  }
+  await balance();
+}
+
+async function receive() {
+  const spendAddress = (document.querySelector("#receive-spend input") as HTMLInputElement)?.value;
+
+  try {
+    const note = await invoke("receive", {
+      spendAddress: spendAddress,
+    });
+
+    message("Tokens received." + (note ? " Payment " + note + "." : ""), "receive");
+
+  } catch (err) {
+    error("" + err, "receive");
+  }
+  await balance();
+}
+
+type ReceiptView = {
+  token_id: string,
+  symbol: string,
+  amount: string,
+  payee: string,
+  spend: string,
+  request: string | null,
+  burn: boolean,
+};
+
+async function verifyReceipt() {
+  const receipt = (document.querySelector("#receipt textarea") as HTMLTextAreaElement)?.value;
+
+  try {
+    const view: ReceiptView = await invoke("verify_receipt", {
+      receipt: receipt,
+    });
+
+    if (view.burn) {
+      message(`Valid proof of burn: ${view.amount} ${view.symbol} burned with spend ${view.spend}`, "receive");
+    } else {
+      message(`Valid receipt: ${view.amount} ${view.symbol} paid to ${view.payee} with spend ${view.spend}`
+        + (view.request ? `, fulfilling request ${view.request}` : ""), "receive");
+    }
+
+  } catch (err) {
+    error("Invalid receipt: " + err, "receive");
+  }
+}
+
+async function validateUnverified() {
+  try {
+    const left = await invoke("validate_unverified");
+
+    message(left === 0 ? "All funds verified." : "Spends still not verified: " + left, "receive");
+
+  } catch (err) {
+    error("" + err, "receive");
+  }
+  await balance();
+}
+
+async function markInvalid() {
+  const entry = (document.querySelector("#mark-invalid-entry input") as HTMLInputElement)?.value;
+
+  try {
+    const rule = await invoke("mark_invalid", {
+      entry: entry,
+    });
+
+    message("Marker published: " + rule, "receive");
+
+  } catch (err) {
+    error("" + err, "receive");
+  }
+}
+
+async function checkInbox() {
+  try {
+    const received = await invoke("check_inbox");
+
+    message("Payments received: " + received, "receive");
+
+  } catch (err) {
+    error("" + err, "receive");
+  }
+  await balance();
+}
+
+type AddressBook = {
+  contacts: { [name: string]: { [tokenId: string]: string } },
+  token_labels: { [tokenId: string]: string },
+  request_labels: { [key: string]: string },
+};
+
+let addressBook: AddressBook = { contacts: {}, token_labels: {}, request_labels: {} };
+
+async function loadAddressBook() {
+  addressBook = await invoke("address_book");
+
+  let rows = "";
+  for (const name in addressBook.contacts) {
+    for (const tokenId in addressBook.contacts[name]) {
+      const key = addressBook.contacts[name][tokenId];
+      const token = addressBook.token_labels[tokenId] ?? tokenId.substring(0, 6) + "...";
+      rows += `<tr><td>${name}</td><td title="${tokenId}">${token}</td>`
+        + `<td title="${key}">${key.substring(0, 16)}...</td>`
+        + `<td><button type="button" data-name="${name}" data-token-id="${tokenId}">Remove</button></td></tr>`;
+    }
+  }
+  for_existing_query("#contacts tbody", (el) => el.innerHTML = rows);
+
+  document.querySelectorAll("#contacts tbody button").forEach((button) =>
+    button.addEventListener("click", async (_ev) => {
+      await contactRemove(button.getAttribute("data-name") ?? "", button.getAttribute("data-token-id"));
+    })
+  );
+
+  populateContactsList();
+}
+
+/// Suggests contacts having a key for token selected in pay form.
+function populateContactsList() {
+  const tokenId = (document.querySelector("#pay-token-id input") as HTMLInputElement)?.value;
+
+  for_existing_element("contacts-list", (list) => {
+    list.replaceChildren();
+    for (const name in addressBook.contacts) {
+      if (!tokenId || tokenId in addressBook.contacts[name]) {
+        list.append(optionHtml(name, name));
+      }
+    }
+  });
+}
+
+async function contactSave() {
+  const name = (document.querySelector("#contact-name input") as HTMLInputElement)?.value;
+  const tokenId = (document.querySelector("#contact-token-id input") as HTMLInputElement)?.value;
+  const key = (document.querySelector("#contact-key input") as HTMLInputElement)?.value;
+
+  try {
+    await invoke("contact_set", {
+      name: name,
+      tokenId: tokenId,
+      key: key,
+    });
+
+    message("Contact saved.", "contacts");
+
+  } catch (err) {
+    error("" + err, "contacts");
+  }
+  await loadAddressBook();
+  await loadAccounts();
+}
+
+async function contactRemove(name: string, tokenId: string | null) {
+  try {
+    await invoke("contact_remove", {
+      name: name,
+      tokenId: tokenId,
+    });
+
+    message("Contact removed.", "contacts");
+
+  } catch (err) {
+    error("" + err, "contacts");
+  }
+  await loadAddressBook();
+}
+
+async function labelToken() {
+  const tokenId = (document.querySelector("#token-label select") as HTMLSelectElement)?.value;
+  const label = (document.querySelector("#token-label input") as HTMLInputElement)?.value;
+
+  try {
+    await invoke("label_token", {
+      tokenId: tokenId,
+      label: label,
+    });
+
+    message("Label saved.", "contacts");
+
+  } catch (err) {
+    error("" + err, "contacts");
+  }
+  await balance();
+}
+
+async function loadAccounts() {
+  const [current, names]: [number, string[]] = await invoke("accounts");
+
+  for (const query of ["#account-select select", "#transfer-to select"]) {
+    for_existing_query(query, (select) => {
+      select.replaceChildren();
+      names.forEach((name, account) => {
+        if (query === "#account-select select" || account !== current) {
+          select.append(optionHtml("" + account, name));
+        }
+      });
+      if (query === "#account-select select") {
+        (select as HTMLSelectElement).value = "" + current;
+      }
+    });
+  }
+}
+
+async function accountAdd() {
+  const name = (document.querySelector("#account-name input") as HTMLInputElement)?.value;
+
+  try {
+    const account = await invoke("account_add", {
+      name: name,
+    });
+
+    message("Account created: " + account, "accounts");
+
+  } catch (err) {
+    error("" + err, "accounts");
+  }
+  await loadAccounts();
+}
+
+async function accountSwitch(account: string) {
+  try {
+    await invoke("account_switch", {
+      account: parseInt(account),
+    });
+  } catch (err) {
+    error("" + err, "accounts");
   }
+  await balance();
 }
 
-async function connect(network: string) {
-  const pkInputEl: HTMLInputElement | null = document.querySelector("#pk-input");
+async function transfer() {
+  const tokenId = (document.querySelector("#transfer-token-id input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#transfer-amount input") as HTMLInputElement)?.value;
+  const toAccount = (document.querySelector("#transfer-to select") as HTMLSelectElement)?.value;
 
-  if (pkInputEl) {
-    const pk = pkInputEl.value || null;
-    console.log("Connecting Autonomi...");
-    const currentPk = await invoke("connect", {
-      network: network,
-      evmPk: pk,
+  try {
+    const spendAddress = await invoke("transfer", {
+      tokenId: tokenId,
+      amount: amount,
+      toAccount: parseInt(toAccount),
     });
-    console.log("Connected.");
 
-    if (pk === null) {
-//      await navigator.clipboard.writeText(currentPk);
-      window.alert("Your EVM Private Key, keep it safely: " + currentPk);
-    }
+    message("Transferred, spend: " + spendAddress, "accounts");
 
-    await refresh();
+  } catch (err) {
+    error("" + err, "accounts");
   }
+  await balance();
 }
 
-async function createToken() {
-  const name = (document.querySelector("#create-token-name input") as HTMLInputElement)?.value;
-  const symbol = (document.querySelector("#create-token-symbol input") as HTMLInputElement)?.value;
-  const supply = (document.querySelector("#create-token-supply input") as HTMLInputElement)?.value;
-  const decimals = (document.querySelector("#create-token-decimals input") as HTMLInputElement)?.value;
+function treasuryAddress(): string {
+  return (document.querySelector("#treasury-address input") as HTMLInputElement)?.value;
+}
+
+function proposalAddress(): string {
+  return (document.querySelector("#treasury-proposal input") as HTMLInputElement)?.value;
+}
 
+async function treasuryMemberKey() {
   try {
-    const tokenId = await invoke("create_token", {
+    const key: string = await invoke("treasury_member_key");
+    for_existing_query("#treasury-member-key input", (el) => (el as HTMLInputElement).value = key);
+  } catch (err) {
+    error("" + err, "treasury");
+  }
+}
+
+async function treasuryCreate() {
+  const name = (document.querySelector("#treasury-name input") as HTMLInputElement)?.value;
+  const threshold = (document.querySelector("#treasury-threshold input") as HTMLInputElement)?.value;
+  const membersText = (document.querySelector("#treasury-members textarea") as HTMLTextAreaElement)?.value ?? "";
+
+  try {
+    const address: string = await invoke("treasury_create", {
       name: name,
-      symbol: symbol,
-      decimals: parseInt(decimals),
-      totalSupply: supply,
+      threshold: parseInt(threshold),
+      members: membersText.split("\n").filter((line) => line.trim()),
     });
 
-    message("Token ID: " + tokenId, "create-token");
+    for_existing_query("#treasury-address input", (el) => (el as HTMLInputElement).value = address);
+    message("Treasury created, share its address with members: " + address, "treasury");
+  } catch (err) {
+    error("" + err, "treasury");
+  }
+}
+
+type TreasuryView = {
+  name: string,
+  threshold: number,
+  members: string[],
+  member: number | null,
+  balances: ActBalance,
+};
 
+async function treasuryOpen() {
+  try {
+    const treasury: TreasuryView = await invoke("treasury", { address: treasuryAddress() });
+
+    const balances = Object.keys(treasury.balances).length > 0 ? balanceHtml(treasury.balances) : "–";
+    const member = treasury.member === null ? "you are not a member" : `you are member ${treasury.member}`;
+    for_existing_element("treasury-info", (el) => el.innerHTML =
+      `<strong>${treasury.name}</strong>: ${treasury.threshold} of ${treasury.members.length} members needed, `
+      + `${member}. <br />Balance: ${balances}`);
   } catch (err) {
-    error("" + err, "create-token");
+    error("" + err, "treasury");
   }
-  await balance();
 }
 
-async function request() {
-  const tokenId = (document.querySelector("#request-token-id input") as HTMLInputElement)?.value;
-  console.log("tokenId: ", tokenId);
+async function treasuryDeposit() {
+  const tokenId = (document.querySelector("#treasury-token-id input") as HTMLInputElement)?.value;
 
   try {
-    const publicKey = await invoke("request", {
-      tokenId: tokenId,
-    });
+    const key: string = await invoke("treasury_deposit_key", { address: treasuryAddress(), tokenId: tokenId });
+    message("Pay to treasury's key: " + key, "treasury");
+    await showQrCode(key, "treasury");
+  } catch (err) {
+    error("" + err, "treasury");
+  }
+}
 
-    message("Public Key: " + publicKey, "request");
+async function treasuryReceive() {
+  const spendAddress = (document.querySelector("#treasury-receive input") as HTMLInputElement)?.value;
 
+  try {
+    await invoke("treasury_receive", { address: treasuryAddress(), spendAddress: spendAddress });
+    message("Received.", "treasury");
   } catch (err) {
-    error("" + err, "request");
+    error("" + err, "treasury");
   }
-  await balance();
+  await treasuryOpen();
 }
 
-async function pay() {
-  const tokenId = (document.querySelector("#pay-token-id input") as HTMLInputElement)?.value;
-  const amount = (document.querySelector("#pay-amount input") as HTMLInputElement)?.value;
-  const to = (document.querySelector("#pay-to input") as HTMLInputElement)?.value;
+async function treasuryPropose() {
+  const tokenId = (document.querySelector("#treasury-token-id input") as HTMLInputElement)?.value;
+  const amount = (document.querySelector("#treasury-amount input") as HTMLInputElement)?.value;
+  const to = (document.querySelector("#treasury-to input") as HTMLInputElement)?.value;
 
   try {
-    const spendAddress = await invoke("pay", {
+    const proposal: string = await invoke("treasury_propose", {
+      address: treasuryAddress(),
       tokenId: tokenId,
       amount: amount,
       to: to,
     });
 
-    message("Crated spend: " + spendAddress, "pay");
+    for_existing_query("#treasury-proposal input", (el) => (el as HTMLInputElement).value = proposal);
+    message("Proposed, share the proposal address with members: " + proposal, "treasury");
+  } catch (err) {
+    error("" + err, "treasury");
+  }
+}
+
+type ProposalView = {
+  token_id: string,
+  symbol: string,
+  spend: string,
+  outputs: [string, string][],
+  approvals: number[],
+  threshold: number,
+};
+
+async function treasuryProposal() {
+  try {
+    const proposal: ProposalView = await invoke("treasury_proposal", {
+      address: treasuryAddress(),
+      proposal: proposalAddress(),
+    });
 
+    const outputs = proposal.outputs
+      .map(([key, amount], i) => `${amount} ${proposal.symbol} to ${key}`
+        + (i == proposal.outputs.length - 1 ? " (change)" : ""))
+      .join("<br />");
+    for_existing_element("treasury-proposal-info", (el) => el.innerHTML =
+      `Spend ${proposal.spend}:<br />${outputs}<br />`
+      + `Approved by ${proposal.approvals.length} of ${proposal.threshold} needed`
+      + (proposal.approvals.length > 0 ? ` (members ${proposal.approvals.join(", ")})` : ""));
   } catch (err) {
-    error("" + err, "pay");
+    error("" + err, "treasury");
+  }
+}
+
+async function treasuryApprove() {
+  try {
+    await invoke("treasury_approve", { address: treasuryAddress(), proposal: proposalAddress() });
+    message("Approved.", "treasury");
+  } catch (err) {
+    error("" + err, "treasury");
+  }
+  await treasuryProposal();
+}
+
+async function treasuryPublish() {
+  try {
+    const spend: string = await invoke("treasury_publish", { address: treasuryAddress(), proposal: proposalAddress() });
+    message("Published spend: " + spend, "treasury");
+  } catch (err) {
+    error("" + err, "treasury");
+  }
+  await treasuryOpen();
+}
+
+type InvoiceView = {
+  merchant: string,
+  merchant_name: string,
+  number: string,
+  token_id: string,
+  symbol: string,
+  items: [string, string][],
+  amount_due: string,
+  created: number,
+  expiry: number | null,
+  expired: boolean,
+};
+
+function invoiceId(): string {
+  return (document.querySelector("#invoice-id input") as HTMLInputElement)?.value;
+}
+
+async function invoiceCreate() {
+  const tokenId = (document.querySelector("#invoice-token-id input") as HTMLInputElement)?.value;
+  const merchantName = (document.querySelector("#invoice-merchant input") as HTMLInputElement)?.value;
+  const number = (document.querySelector("#invoice-number input") as HTMLInputElement)?.value;
+  const itemsText = (document.querySelector("#invoice-items textarea") as HTMLTextAreaElement)?.value;
+  const expiry = (document.querySelector("#invoice-expiry input") as HTMLInputElement)?.valueAsNumber;
+
+  const items = itemsText.split("\n")
+    .filter((line) => line.trim())
+    .map((line) => {
+      const [description, amount] = line.split(";", 2);
+      return [description.trim(), (amount ?? "").trim()];
+    });
+
+  try {
+    const id: string = await invoke("invoice_create", {
+      tokenId: tokenId,
+      merchantName: merchantName,
+      number: number,
+      items: items,
+      expiry: expiry ? Math.floor(expiry / 1000) : null,
+    });
+
+    for_existing_query("#invoice-id input", (el) => (el as HTMLInputElement).value = id);
+    message("Invoice issued: " + id, "invoices");
+    await showInvoice();
+
+  } catch (err) {
+    error("" + err, "invoices");
   }
   await balance();
 }
 
-async function receive() {
-  const spendAddress = (document.querySelector("#receive-spend input") as HTMLInputElement)?.value;
+async function showInvoice() {
+  try {
+    const invoice: InvoiceView = await invoke("invoice_get", {
+      invoiceId: invoiceId(),
+    });
+
+    const rows = invoice.items.map(([description, amount]) =>
+      `<tr><td>${description}</td><td>${amount} ${invoice.symbol}</td></tr>`
+    ).join("");
+    const validity = invoice.expiry
+      ? "Valid until: " + new Date(invoice.expiry * 1000).toLocaleString() + (invoice.expired ? " (expired)" : "")
+      : "";
+
+    for_existing_query("#invoices .invoice", (el) => el.innerHTML =
+      `<p>Invoice <strong>${invoice.number}</strong> from <strong>${invoice.merchant_name}</strong>`
+      + ` <small title="${invoice.merchant}">(${invoice.merchant.substring(0, 8)}...)</small>,`
+      + ` issued ${new Date(invoice.created * 1000).toLocaleString()}</p>`
+      + `<table><tbody>${rows}</tbody>`
+      + `<tfoot><tr><th>Total</th><th>${invoice.amount_due} ${invoice.symbol}</th></tr></tfoot></table>`
+      + `<p>${validity}</p>`
+    );
+    await showQrCode(invoiceId(), "invoices");
+
+  } catch (err) {
+    error("" + err, "invoices");
+  }
+}
 
+async function invoicePay() {
   try {
-    await invoke("receive", {
-      spendAddress: spendAddress,
+    const payment: Payment = await invoke("invoice_pay", {
+      invoiceId: invoiceId(),
     });
 
-    message("Tokens received.", "receive");
+    message("Invoice paid, spend: " + payment.spend + ", receipt: " + payment.receipt, "invoices");
 
   } catch (err) {
-    error("" + err, "receive");
+    error("" + err, "invoices");
   }
   await balance();
 }
 
+async function invoiceStatus() {
+  try {
+    const status = await invoke("invoice_status", {
+      invoiceId: invoiceId(),
+    });
+
+    message("Invoice status: " + status, "invoices");
+
+  } catch (err) {
+    error("" + err, "invoices");
+  }
+}
+
+type HistoryEntry = {
+  token_id: string,
+  symbol: string,
+  direction: string,
+  amount: string,
+  counterparty: string,
+  contact: string | null,
+  spend: string,
+  burn: boolean,
+  timestamp: number,
+  memo: string | null,
+  status: string,
+};
+
+function dateInputSeconds(input: HTMLInputElement | null, dayOffset: number): number | null {
+  if (!input || !input.valueAsNumber) {
+    return null;
+  }
+  return Math.floor(input.valueAsNumber / 1000) + dayOffset * 24 * 60 * 60;
+}
+
+function historyRowHtml(entry: HistoryEntry): string {
+  const date = new Date(entry.timestamp * 1000).toLocaleString();
+  const sign = entry.direction === "Sent" ? "-" : "+";
+  return `<tr class="${entry.direction.toLowerCase()}">`
+    + `<td>${date}</td>`
+    + `<td>${entry.direction}</td>`
+    + `<td>${sign}${entry.amount} <strong>${entry.symbol}</strong></td>`
+    + (entry.burn
+      ? "<td>burned</td>"
+      : `<td title="${entry.counterparty}">${entry.contact ?? entry.counterparty.substring(0, 8) + "..."}</td>`)
+    + `<td title="${entry.spend}">${entry.spend.substring(0, 8)}...</td>`
+    + `<td>${entry.memo ?? ""}</td>`
+    + `<td>${entry.status}</td>`
+    + "</tr>";
+}
+
+async function history() {
+  const tokenId = (document.querySelector("#history-token-id input") as HTMLInputElement)?.value;
+  const dates = document.querySelectorAll("#history-dates input");
+
+  try {
+    const entries: HistoryEntry[] = await invoke("history", {
+      tokenId: tokenId || null,
+      from: dateInputSeconds(dates[0] as HTMLInputElement, 0),
+      to: dateInputSeconds(dates[1] as HTMLInputElement, 1), // include whole "to" day
+    });
+
+    for_existing_query("#history tbody", (el) => el.innerHTML =
+      entries.reverse().map(historyRowHtml).join(""));
+    message(entries.length === 0 ? "No transactions." : "", "history");
+
+  } catch (err) {
+    error("" + err, "history");
+  }
+}
+
 type ActBalance = {
   [tokenId: string]: [string, string]
 };
 
+type LockedBalance = {
+  [tokenId: string]: {
+    symbol: string,
+    locked: string,
+    spendable: string,
+    next_unlock: number | null,
+  }
+};
+
+function lockedHtml(locked: LockedBalance): string {
+  let html = "";
+  for (let tokenId in locked) {
+    const unlock = locked[tokenId].next_unlock;
+    html += `<strong>${locked[tokenId].symbol}</strong>: ${locked[tokenId].locked} locked, `
+      + `${locked[tokenId].spendable} spendable`
+      + (unlock ? ` (next unlock ${new Date(unlock * 1000).toLocaleString()})` : "") + ", ";
+  }
+  return html.substring(0, html.length - 2); // remove last comma
+}
+
 function balanceHtml(actBalance: ActBalance): string {
   let balHtml = "";
   for (let tokenId in actBalance) {
@@ -141,17 +1109,31 @@ async function balance() {
   console.log("actBalance");
   console.log(actBalance);
 
+  await loadAddressBook();
+
   populateTokenIdSelect((document.querySelector("#request-token-id select") as Element), actBalance);
   populateTokenIdSelect((document.querySelector("#pay-token-id select") as Element), actBalance);
+  populateTokenIdSelect((document.querySelector("#history-token-id select") as Element), actBalance);
+  populateTokenIdSelect((document.querySelector("#invoice-token-id select") as Element), actBalance);
+  populateTokenIdSelect((document.querySelector("#contact-token-id select") as Element), actBalance);
+  populateTokenIdSelect((document.querySelector("#token-label select") as Element), actBalance);
+  populateTokenIdSelect((document.querySelector("#transfer-token-id select") as Element), actBalance);
 
   let actBalanceHtml = "–";
   if (typeof actBalance === 'object' && Object.keys(actBalance).length > 0) {
     actBalanceHtml = balanceHtml(actBalance);
   }
   
-  for_existing_query("#balance p", (balanceEl) => balanceEl.innerHTML =
+  const locked: LockedBalance = await invoke("locked_balances");
+  let lockedBalanceHtml = "";
+  if (Object.keys(locked).length > 0) {
+    lockedBalanceHtml = "<br /><dt>Locked</dt> <dd>" + lockedHtml(locked) + "</dd>";
+  }
+
+  for_existing_query("#balance #balances", (balanceEl) => balanceEl.innerHTML =
     "<dt>EVM balance (gas)</dt> <dd>" + bal + "</dd> <br />"
-    + "<dt>ACT balance</dt> <dd>" + actBalanceHtml + "</dd>");
+    + "<dt>ACT balance</dt> <dd>" + actBalanceHtml + "</dd>"
+    + lockedBalanceHtml);
 }
 
 function optionHtml(value: string, text: string): Element {
@@ -165,7 +1147,9 @@ function populateTokenIdSelect(select: Element, actBalance: ActBalance) {
   select.replaceChildren(); // clear
   select.append(optionHtml("", "(clear)"));
   for (let tokenId in actBalance) {
-    select.append(optionHtml(tokenId, actBalance[tokenId][0] + " (" + tokenId.substring(0, 6) + "...)"));
+    const label = addressBook.token_labels[tokenId];
+    const symbol = label ? `${label}, ${actBalance[tokenId][0]}` : actBalance[tokenId][0];
+    select.append(optionHtml(tokenId, symbol + " (" + tokenId.substring(0, 6) + "...)"));
   }
 }
 
@@ -204,16 +1188,39 @@ function error(text: string, afterId: string) {
   errEl.innerHTML = text;
 }
 
+type PaymentReceived = {
+  token_id: string,
+  symbol: string,
+  amount: string,
+  note: string | null,
+};
+
 window.addEventListener("DOMContentLoaded", () => {
   for_existing_element("balance", (el) => (el as HTMLElement).hidden = true);
   refresh();
 
+  // background watcher
+
+  listen<PaymentReceived>("payment-received", (ev) => {
+    const label = addressBook.token_labels[ev.payload.token_id] ?? ev.payload.symbol;
+    message("Received " + ev.payload.amount + " " + label
+      + (ev.payload.note ? ", " + ev.payload.note : ""), "balance");
+  });
+
+  listen("balances-changed", async (_ev) => {
+    await balance();
+  });
+
   // connect
 
   document.querySelector("#main-connect-button")?.addEventListener("click", (_ev) => connect("Main"));
   document.querySelector("#local-connect-button")?.addEventListener("click", (_ev) => connect("Local"));
   document.querySelector("#alpha-connect-button")?.addEventListener("click", (_ev) => connect("Alpha"));
 
+  document.querySelector("#main-watch-button")?.addEventListener("click", (_ev) => connectWatchOnly("Main"));
+  document.querySelector("#local-watch-button")?.addEventListener("click", (_ev) => connectWatchOnly("Local"));
+  document.querySelector("#alpha-watch-button")?.addEventListener("click", (_ev) => connectWatchOnly("Alpha"));
+
   // menu
 
   let menuButtons = document.querySelectorAll("#menu li");
@@ -241,31 +1248,231 @@ window.addEventListener("DOMContentLoaded", () => {
     );
   });
 
-  document.querySelector("#request button")?.addEventListener("click", async (_ev) => {
+  document.querySelector("#request-button")?.addEventListener("click", async (_ev) => {
     await request();
   });
 
+  document.querySelector("#requests-button")?.addEventListener("click", async (_ev) => {
+    await requests();
+  });
+
   // pay
 
   document.querySelector("#pay-token-id select")?.addEventListener("change", (ev) => {
     for_existing_query("#pay-token-id input",
       (el) => (el as HTMLInputElement).value = (ev.target as HTMLInputElement)?.value
     );
+    populateContactsList();
+  });
+
+  document.querySelector("#pay-token-id input")?.addEventListener("change", (_ev) => {
+    populateContactsList();
+  });
+
+  document.querySelector("#pay-uri button")?.addEventListener("click", async (_ev) => {
+    await fillPayment();
+  });
+
+  document.querySelector("#pay-uri input[type=file]")?.addEventListener("change", async (ev) => {
+    const text = await readQrCode(ev.target as HTMLInputElement, "pay");
+    if (text === null) {
+      return;
+    }
+    if (text.toLowerCase().startsWith("act:")) {
+      for_existing_query("#pay-uri input", (el) => (el as HTMLInputElement).value = text);
+      await fillPayment();
+    } else {
+      // bare public key
+      for_existing_query("#pay-to input", (el) => (el as HTMLInputElement).value = text);
+      message("Public key read from QR code.", "pay");
+    }
   });
 
-  document.querySelector("#pay button")?.addEventListener("click", async (_ev) => {
+  document.querySelector("#pay-button")?.addEventListener("click", async (_ev) => {
     await pay();
   });
 
+  document.querySelector("#burn-button")?.addEventListener("click", async (_ev) => {
+    await burn();
+  });
+
   // receive
 
-  document.querySelector("#receive button")?.addEventListener("click", async (_ev) => {
+  document.querySelector("#receive-button")?.addEventListener("click", async (_ev) => {
     await receive();
   });
 
+  document.querySelector("#check-inbox-button")?.addEventListener("click", async (_ev) => {
+    await checkInbox();
+  });
+
+  document.querySelector("#validate-button")?.addEventListener("click", async (_ev) => {
+    await validateUnverified();
+  });
+
+  document.querySelector("#mark-invalid-button")?.addEventListener("click", async (_ev) => {
+    await markInvalid();
+  });
+
+  document.querySelector("#verify-receipt-button")?.addEventListener("click", async (_ev) => {
+    await verifyReceipt();
+  });
+
+  document.querySelector("#receive-spend input[type=file]")?.addEventListener("change", async (ev) => {
+    const text = await readQrCode(ev.target as HTMLInputElement, "receive");
+    if (text !== null) {
+      for_existing_query("#receive-spend input", (el) => (el as HTMLInputElement).value = text);
+      await receive();
+    }
+  });
+
+  // invoices
+
+  document.querySelector("#invoice-token-id select")?.addEventListener("change", (ev) => {
+    for_existing_query("#invoice-token-id input",
+      (el) => (el as HTMLInputElement).value = (ev.target as HTMLInputElement)?.value
+    );
+  });
+
+  document.querySelector("#invoice-create-button")?.addEventListener("click", async (_ev) => {
+    await invoiceCreate();
+  });
+
+  document.querySelector("#invoice-show-button")?.addEventListener("click", async (_ev) => {
+    await showInvoice();
+  });
+
+  document.querySelector("#invoice-pay-button")?.addEventListener("click", async (_ev) => {
+    await invoicePay();
+  });
+
+  document.querySelector("#invoice-status-button")?.addEventListener("click", async (_ev) => {
+    await invoiceStatus();
+  });
+
+  // history
+
+  document.querySelector("#history-token-id select")?.addEventListener("change", (ev) => {
+    for_existing_query("#history-token-id input",
+      (el) => (el as HTMLInputElement).value = (ev.target as HTMLInputElement)?.value
+    );
+  });
+
+  document.querySelector("#history button")?.addEventListener("click", async (_ev) => {
+    await history();
+  });
+
+  // accounts
+
+  document.querySelector("#account-select select")?.addEventListener("change", async (ev) => {
+    await accountSwitch((ev.target as HTMLSelectElement)?.value);
+  });
+
+  document.querySelector("#account-name button")?.addEventListener("click", async (_ev) => {
+    await accountAdd();
+  });
+
+  document.querySelector("#transfer-token-id select")?.addEventListener("change", (ev) => {
+    for_existing_query("#transfer-token-id input",
+      (el) => (el as HTMLInputElement).value = (ev.target as HTMLInputElement)?.value
+    );
+  });
+
+  document.querySelector("#transfer-button")?.addEventListener("click", async (_ev) => {
+    await transfer();
+  });
+
+  // treasury
+
+  document.querySelector("#treasury-member-key button")?.addEventListener("click", async (_ev) => {
+    await treasuryMemberKey();
+  });
+
+  document.querySelector("#treasury-create-button")?.addEventListener("click", async (_ev) => {
+    await treasuryCreate();
+  });
+
+  document.querySelector("#treasury-address button")?.addEventListener("click", async (_ev) => {
+    await treasuryOpen();
+  });
+
+  document.querySelector("#treasury-deposit-button")?.addEventListener("click", async (_ev) => {
+    await treasuryDeposit();
+  });
+
+  document.querySelector("#treasury-receive button")?.addEventListener("click", async (_ev) => {
+    await treasuryReceive();
+  });
+
+  document.querySelector("#treasury-propose-button")?.addEventListener("click", async (_ev) => {
+    await treasuryPropose();
+  });
+
+  document.querySelector("#treasury-proposal-button")?.addEventListener("click", async (_ev) => {
+    await treasuryProposal();
+  });
+
+  document.querySelector("#treasury-approve-button")?.addEventListener("click", async (_ev) => {
+    await treasuryApprove();
+  });
+
+  document.querySelector("#treasury-publish-button")?.addEventListener("click", async (_ev) => {
+    await treasuryPublish();
+  });
+
+  // contacts
+
+  document.querySelector("#contact-token-id select")?.addEventListener("change", (ev) => {
+    for_existing_query("#contact-token-id input",
+      (el) => (el as HTMLInputElement).value = (ev.target as HTMLInputElement)?.value
+    );
+  });
+
+  document.querySelector("#contact-save-button")?.addEventListener("click", async (_ev) => {
+    await contactSave();
+  });
+
+  document.querySelector("#token-label button")?.addEventListener("click", async (_ev) => {
+    await labelToken();
+  });
+
+  // watch-only
+
+  document.querySelector("#export-watch-only-button")?.addEventListener("click", async (_ev) => {
+    await exportWatchOnly();
+  });
+
+  document.querySelector("#sync-button")?.addEventListener("click", async (_ev) => {
+    await sync();
+  });
+
+  document.querySelector("#export-unsigned-button")?.addEventListener("click", async (_ev) => {
+    await exportUnsigned();
+  });
+
+  document.querySelector("#publish-signed button")?.addEventListener("click", async (_ev) => {
+    await publishSigned();
+  });
+
+  document.querySelector("#sign-offline-button")?.addEventListener("click", async (_ev) => {
+    await signOffline();
+  });
+
   // create token
 
-  document.querySelector("#create-token button")?.addEventListener("click", async (_ev) => {
+  document.querySelector("#create-token-button")?.addEventListener("click", async (_ev) => {
     await createToken();
   });
+
+  document.querySelector("#checkpoint-button")?.addEventListener("click", async (_ev) => {
+    await checkpoint();
+  });
+
+  document.querySelector("#mint-button")?.addEventListener("click", async (_ev) => {
+    await mint();
+  });
+
+  document.querySelector("#supply-button")?.addEventListener("click", async (_ev) => {
+    await supplyAudit();
+  });
 });
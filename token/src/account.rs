@@ -0,0 +1,41 @@
+use autonomi::{PublicKey, SecretKey};
+
+/// Account 0 is the main wallet itself, so wallets created before accounts were introduced stay untouched.
+pub const MAIN_ACCOUNT: u32 = 0;
+
+/// Derivation index of an account. Differs in length from 32-byte wallet indices, so they never collide.
+fn account_index(account: u32) -> Vec<u8> {
+    [b"account".as_slice(), &account.to_be_bytes()].concat()
+}
+
+/// Secret key of an account's wallet. Each account has own derivation sub-tree and own wallet scratchpad.
+pub fn account_sk(sk: &SecretKey, account: u32) -> SecretKey {
+    match account {
+        MAIN_ACCOUNT => sk.clone(),
+        _ => sk.derive_child(&account_index(account)),
+    }
+}
+
+pub fn account_pk(pk: &PublicKey, account: u32) -> PublicKey {
+    match account {
+        MAIN_ACCOUNT => *pk,
+        _ => pk.derive_child(&account_index(account)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_have_separate_keys() {
+        let sk = SecretKey::random();
+
+        assert_eq!(sk, account_sk(&sk, MAIN_ACCOUNT));
+        assert_ne!(account_sk(&sk, 1), account_sk(&sk, 2));
+        assert_eq!(
+            account_pk(&sk.public_key(), 1),
+            account_sk(&sk, 1).public_key()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::mint::MintPolicy;
 use autonomi::{
     client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
     GraphEntryAddress, PublicKey, SecretKey, XorName,
@@ -5,22 +6,73 @@ use autonomi::{
 use futures::{future::Future, stream, StreamExt};
 use ruint::aliases::U256;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TokenInfo {
     pub symbol: String,
     pub name: String,
     pub decimals: u8,
+    /// Address of the genesis spend. Besides permitted mints, it's the only GraphEntry
+    /// without parents that spends of the token may descend from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genesis: Option<PublicKey>,
+    /// Sum of genesis outputs, without mints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_supply: Option<U256>,
+    /// Key signing checkpoints, see `Checkpoint`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<PublicKey>,
+    /// Issuance after genesis, fixed supply if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint: Option<MintPolicy>,
+}
+
+/// Outputs of a genesis spend. Allocations must sum to total supply,
+/// each to a different key and none of them empty.
+pub fn genesis_outputs(
+    total_supply: U256,
+    allocations: &[(PublicKey, U256)],
+) -> Result<Vec<(PublicKey, [u8; 32])>, String> {
+    if allocations.is_empty() {
+        return Err("No allocations.".into());
+    }
+
+    let mut keys = HashSet::new();
+    let mut sum = U256::ZERO;
+    for (key, amount) in allocations.iter() {
+        if !keys.insert(*key) {
+            return Err(format!("Key allocated twice: {}", key.to_hex()));
+        }
+        if amount.is_zero() {
+            return Err(format!("Nothing allocated to {}", key.to_hex()));
+        }
+        sum = sum.checked_add(*amount).ok_or("Overflow.".to_string())?;
+    }
+    if sum != total_supply {
+        return Err(format!(
+            "Allocations sum to {}, not to total supply {}.",
+            sum, total_supply
+        ));
+    }
+
+    Ok(allocations
+        .iter()
+        .map(|(key, amount)| (*key, amount.to_be_bytes()))
+        .collect())
 }
 
 pub trait ActExt {
+    /// Genesis spend has an output for each allocation, see `genesis_outputs`.
     fn act_create(
         &self,
         name: String,
         symbol: String,
         decimals: u8,
         total_supply: U256,
-        issuer_key: PublicKey,
+        allocations: Vec<(PublicKey, U256)>,
+        checkpoint_key: Option<PublicKey>,
+        mint_policy: Option<MintPolicy>,
         payment: &PaymentOption,
     ) -> impl Future<Output = Result<(PublicKey, XorName), String>> + Send;
 
@@ -49,15 +101,31 @@ impl ActExt for Client {
         symbol: String,
         decimals: u8,
         total_supply: U256,
-        to: PublicKey,
+        allocations: Vec<(PublicKey, U256)>,
+        issuer: Option<PublicKey>,
+        mint: Option<MintPolicy>,
         payment: &PaymentOption,
     ) -> Result<(PublicKey, XorName), String> {
+        let outputs = genesis_outputs(total_supply, &allocations)?;
+        if mint.as_ref().is_some_and(|mint| mint.cap.is_zero()) {
+            return Err("Minting policy with zero cap.".into());
+        }
+
+        // genesis address is committed in token info, which address is the token ID
+        let genesis_owner = SecretKey::random();
+        println!("Genesis owner: {:?}", genesis_owner);
+        let genesis_owner_pubkey = genesis_owner.public_key();
+
         // create token info chunk
         let token_info_bytes = Bytes::from(
             serde_json::to_string(&TokenInfo {
                 name,
                 symbol,
                 decimals,
+                genesis: Some(genesis_owner_pubkey),
+                total_supply: Some(total_supply),
+                issuer,
+                mint,
             })
             .map_err(|e| format!("{}", e))?,
         );
@@ -73,16 +141,7 @@ impl ActExt for Client {
         let token_id = token_info_address.xorname();
         println!("TokenId: {}", token_id);
 
-        let genesis_owner = SecretKey::random();
-        println!("Genesis owner: {:?}", genesis_owner);
-
-        let genesis_owner_pubkey = genesis_owner.public_key();
-        let genesis = GraphEntry::new(
-            &genesis_owner,
-            vec![],
-            token_id.0.clone(),
-            vec![(to, total_supply.to_be_bytes())], // all output to issuer
-        );
+        let genesis = GraphEntry::new(&genesis_owner, vec![], token_id.0.clone(), outputs);
         let (_paid, genesis_address) = self
             .graph_entry_put(genesis, payment.clone())
             .await
@@ -160,3 +219,45 @@ impl ActExt for Client {
     //
     //	}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genesis_allocations() -> Result<(), String> {
+        let team = SecretKey::random().public_key();
+        let treasury = SecretKey::random().public_key();
+        let total_supply = U256::from(1000);
+
+        let outputs = genesis_outputs(
+            total_supply,
+            &[(team, U256::from(200)), (treasury, U256::from(800))],
+        )?;
+        assert_eq!(
+            vec![
+                (team, U256::from(200).to_be_bytes()),
+                (treasury, U256::from(800).to_be_bytes())
+            ],
+            outputs
+        );
+
+        assert!(genesis_outputs(total_supply, &[]).is_err());
+        assert!(genesis_outputs(total_supply, &[(team, U256::from(999))]).is_err());
+        assert!(genesis_outputs(
+            total_supply,
+            &[(team, U256::from(500)), (team, U256::from(500))]
+        )
+        .is_err());
+        assert!(genesis_outputs(
+            total_supply,
+            &[(team, total_supply), (treasury, U256::ZERO)]
+        )
+        .is_err());
+        assert!(
+            genesis_outputs(U256::MAX, &[(team, U256::MAX), (treasury, U256::from(1))]).is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use autonomi::{PublicKey, XorName};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Named contacts with their receive keys for each token, and user's labels for tokens and requests.
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AddressBook {
+    pub contacts: BTreeMap<String, BTreeMap<XorName, PublicKey>>,
+    pub token_labels: BTreeMap<XorName, String>,
+    /// Labels of payment requests, by requested key.
+    pub request_labels: BTreeMap<PublicKey, String>,
+}
+
+impl AddressBook {
+    pub fn set_contact(
+        &mut self,
+        name: &str,
+        token_id: XorName,
+        key: PublicKey,
+    ) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Empty contact name.".into());
+        }
+
+        self.contacts
+            .entry(name.to_string())
+            .or_default()
+            .insert(token_id, key);
+        Ok(())
+    }
+
+    /// Removes key for a single token, or whole contact if `token_id` is `None`.
+    pub fn remove_contact(&mut self, name: &str, token_id: Option<XorName>) -> Result<(), String> {
+        let keys = self
+            .contacts
+            .get_mut(name)
+            .ok_or("No such contact.".to_string())?;
+
+        match token_id {
+            None => keys.clear(),
+            Some(id) => {
+                keys.remove(&id)
+                    .ok_or("No key for this token.".to_string())?;
+            }
+        };
+
+        if keys.is_empty() {
+            self.contacts.remove(name);
+        }
+        Ok(())
+    }
+
+    pub fn contact_key(&self, name: &str, token_id: XorName) -> Option<PublicKey> {
+        self.contacts
+            .get(name.trim())
+            .and_then(|keys| keys.get(&token_id))
+            .copied()
+    }
+
+    /// Name of a contact owning the key.
+    pub fn contact_of(&self, key: &PublicKey) -> Option<&str> {
+        self.contacts
+            .iter()
+            .find(|(_name, keys)| keys.values().any(|k| k == key))
+            .map(|(name, _keys)| name.as_str())
+    }
+
+    /// Empty label removes it.
+    pub fn set_token_label(&mut self, token_id: XorName, label: &str) {
+        match label.trim() {
+            "" => self.token_labels.remove(&token_id),
+            label => self.token_labels.insert(token_id, label.to_string()),
+        };
+    }
+
+    pub fn token_label(&self, token_id: XorName) -> Option<&str> {
+        self.token_labels.get(&token_id).map(|l| l.as_str())
+    }
+
+    /// Empty label removes it.
+    pub fn set_request_label(&mut self, key: PublicKey, label: &str) {
+        match label.trim() {
+            "" => self.request_labels.remove(&key),
+            label => self.request_labels.insert(key, label.to_string()),
+        };
+    }
+
+    pub fn request_label(&self, key: &PublicKey) -> Option<&str> {
+        self.request_labels.get(key).map(|l| l.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+
+    #[test]
+    fn contacts() -> Result<(), String> {
+        let mut book = AddressBook::default();
+
+        let token1 = XorName::from_content(&[1u8]);
+        let token2 = XorName::from_content(&[2u8]);
+        let key1 = SecretKey::random().public_key();
+        let key2 = SecretKey::random().public_key();
+
+        assert!(book.set_contact("  ", token1, key1).is_err());
+
+        book.set_contact("Alice", token1, key1)?;
+        book.set_contact(" Alice ", token2, key2)?;
+
+        assert_eq!(Some(key1), book.contact_key("Alice", token1));
+        assert_eq!(Some(key2), book.contact_key("Alice", token2));
+        assert_eq!(None, book.contact_key("Bob", token1));
+        assert_eq!(Some("Alice"), book.contact_of(&key2));
+
+        book.remove_contact("Alice", Some(token1))?;
+        assert_eq!(None, book.contact_key("Alice", token1));
+        assert!(book.remove_contact("Alice", Some(token1)).is_err());
+
+        book.remove_contact("Alice", Some(token2))?;
+        assert!(book.contacts.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn labels() {
+        let mut book = AddressBook::default();
+
+        let token = XorName::from_content(&[1u8]);
+        let request = SecretKey::random().public_key();
+
+        book.set_token_label(token, "Payroll token");
+        book.set_request_label(request, "Invoice 42");
+        assert_eq!(Some("Payroll token"), book.token_label(token));
+        assert_eq!(Some("Invoice 42"), book.request_label(&request));
+
+        book.set_token_label(token, "");
+        assert_eq!(None, book.token_label(token));
+    }
+}
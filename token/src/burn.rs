@@ -0,0 +1,202 @@
+use crate::receipt::Receipt;
+use crate::validate::ValidateExt;
+use crate::wallet::{Wallet, WalletExt};
+use autonomi::{
+    client::payment::PaymentOption, Client, GraphEntry, GraphEntryAddress, SecretKey, XorName,
+};
+use futures::Future;
+use ruint::aliases::U256;
+use std::collections::{HashSet, VecDeque};
+
+/// Amount a spend burns: sum of its outputs to its own key. GraphEntry owned by that key
+/// is the spend itself, so these outputs can never be spent.
+pub fn burned(spend: &GraphEntry) -> Result<U256, String> {
+    spend
+        .descendants
+        .iter()
+        .filter(|(key, _data)| *key == spend.owner)
+        .try_fold(U256::ZERO, |sum, (_key, data)| {
+            sum.checked_add(U256::from_be_bytes(*data))
+                .ok_or("Overflow.".to_string())
+        })
+}
+
+/// Supply of a token, found by following spends forward from genesis and permitted mints.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SupplyAudit {
+    pub token_id: XorName,
+    /// Output of genesis and mints.
+    pub total_supply: U256,
+    /// Output of mints, permitted by minting policy.
+    pub minted: U256,
+    pub burned: U256,
+    /// Number of spends followed.
+    pub spends: usize,
+    /// All spends were followed, otherwise `burned` may be higher.
+    pub complete: bool,
+}
+
+impl SupplyAudit {
+    pub fn circulating(&self) -> U256 {
+        self.total_supply.saturating_sub(self.burned)
+    }
+}
+
+pub trait BurnExt {
+    /// Spends `amount` to the spend's own key, making it unspendable. Returns receipt
+    /// of the burn output, which is a proof of burn anyone can verify. Wallet is changed,
+    /// but not saved.
+    fn act_burn(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        amount: U256,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Receipt, String>> + Send;
+
+    /// Follows spends from genesis and permitted mints, at most `max_spends` of them,
+    /// summing burned amounts. Genesis is the one committed in token info.
+    fn act_supply_audit(
+        &self,
+        token_id: XorName,
+        max_spends: usize,
+    ) -> impl Future<Output = Result<SupplyAudit, String>> + Send;
+}
+
+impl BurnExt for Client {
+    async fn act_burn(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        amount: U256,
+        payment: &PaymentOption,
+    ) -> Result<Receipt, String> {
+        // main key, which will own the spend, is known only after consolidation
+        self.act_consolidate(wallet, sk, token_id, payment).await?;
+        let own_key = wallet
+            .pk_of_token(token_id)
+            .ok_or("Token not found in wallet.".to_string())?;
+
+        let receipt = self
+            .act_spend(wallet, sk, token_id, amount, own_key, None, payment)
+            .await?;
+        println!("Burned {} of {:x}", amount, token_id);
+
+        Ok(receipt)
+    }
+
+    async fn act_supply_audit(
+        &self,
+        token_id: XorName,
+        max_spends: usize,
+    ) -> Result<SupplyAudit, String> {
+        let roots = self.act_roots(&token_id).await?;
+        let genesis = roots
+            .genesis
+            .ok_or("Token info doesn't commit to a genesis.".to_string())?;
+        let genesis = self
+            .graph_entry_get(&GraphEntryAddress::new(genesis))
+            .await
+            .map_err(|e| format!("{e}"))?;
+        if !genesis.parents.is_empty() || XorName(genesis.content) != token_id {
+            return Err("Not a genesis spend of the token.".into());
+        }
+        roots.check_root(&genesis)?;
+
+        let mints = roots.mints;
+        let genesis_supply = roots
+            .total_supply
+            .ok_or("Token info doesn't commit to total supply.".to_string())?;
+
+        let mut audit = SupplyAudit {
+            token_id,
+            total_supply: genesis_supply
+                .checked_add(mints.minted)
+                .ok_or("Overflow.".to_string())?,
+            minted: mints.minted,
+            burned: U256::ZERO,
+            spends: 0,
+            complete: true,
+        };
+
+        let mut visited = HashSet::from([genesis.owner]);
+        let mut to_follow = VecDeque::from([genesis]);
+        for mint in mints.permitted {
+            visited.insert(mint);
+            to_follow.push_back(
+                self.graph_entry_get(&GraphEntryAddress::new(mint))
+                    .await
+                    .map_err(|e| format!("{e}"))?,
+            );
+        }
+
+        while let Some(spend) = to_follow.pop_front() {
+            if audit.spends >= max_spends {
+                audit.complete = false;
+                break;
+            }
+            audit.spends += 1;
+            audit.burned = audit
+                .burned
+                .checked_add(burned(&spend)?)
+                .ok_or("Overflow.".to_string())?;
+
+            // output is spent, if there's a GraphEntry owned by its key
+            for (key, _data) in spend.descendants.iter() {
+                if !visited.insert(*key) {
+                    continue;
+                }
+                let address = GraphEntryAddress::new(*key);
+                if !self
+                    .graph_entry_check_existence(&address)
+                    .await
+                    .map_err(|e| format!("{e}"))?
+                {
+                    continue;
+                }
+
+                let next = self
+                    .graph_entry_get(&address)
+                    .await
+                    .map_err(|e| format!("{e}"))?;
+                if XorName(next.content) == audit.token_id {
+                    to_follow.push_back(next);
+                }
+            }
+        }
+        println!("Supply audit: {:?}", audit);
+
+        Ok(audit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burned_amount() -> Result<(), String> {
+        let sk = SecretKey::random();
+        let spend = GraphEntry::new(
+            &sk,
+            vec![SecretKey::random().public_key()],
+            [0; 32],
+            vec![
+                (sk.public_key(), U256::from(30).to_be_bytes()),
+                (
+                    SecretKey::random().public_key(),
+                    U256::from(70).to_be_bytes(),
+                ),
+            ],
+        );
+        assert_eq!(U256::from(30), burned(&spend)?);
+
+        let receipt = Receipt::new(spend, 0)?;
+        assert!(receipt.is_burn()?);
+        assert!(!Receipt::new(receipt.spend, 1)?.is_burn()?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+use autonomi::{GraphEntry, PublicKey, XorName};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Default number of GraphEntries kept in a validation cache.
+pub const DEFAULT_CACHE_ENTRIES: usize = 100_000;
+
+/// What validation found out about a GraphEntry.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum Verdict {
+    /// Entry and all its ancestors are valid, back to genesis (if reached) or checkpoints.
+    Valid(Option<PublicKey>),
+    /// Entry is valid against its parents, but not all ancestors were checked.
+    Checked,
+    Invalid(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Cached {
+    entry: GraphEntry,
+    /// Hash of the entry, verdict is valid only for the same entry.
+    hash: XorName,
+    verdict: Option<Verdict>,
+    /// When the entry was last used, to evict least recently used ones.
+    used: u64,
+}
+
+/// Checkpoints of a token read so far.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Checkpoints {
+    /// Number of the first checkpoint not read yet.
+    next: u64,
+    /// Outputs checkpointed, as spend address and the key it pays to.
+    outputs: HashSet<(PublicKey, PublicKey)>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Stored {
+    tick: u64,
+    entries: Vec<Cached>,
+    #[serde(default)]
+    marker_slots: HashMap<XorName, u64>,
+    #[serde(default)]
+    checkpoints: HashMap<XorName, Checkpoints>,
+}
+
+/// GraphEntries fetched during validation, with their verdicts. Kept on disk, if opened
+/// with a path, so it can be shared by wallets and sessions. When there are more than
+/// `max_entries`, least recently used ones are evicted.
+#[derive(Debug)]
+pub struct ValidationCache {
+    entries: HashMap<PublicKey, Cached>,
+    max_entries: usize,
+    tick: u64,
+    /// First marker slot of each token not read yet.
+    marker_slots: HashMap<XorName, u64>,
+    checkpoints: HashMap<XorName, Checkpoints>,
+    path: Option<PathBuf>,
+    changed: bool,
+}
+
+fn entry_hash(entry: &GraphEntry) -> Result<XorName, String> {
+    Ok(XorName::from_content(
+        &rmp_serde::to_vec(entry).map_err(|e| format!("{e}"))?,
+    ))
+}
+
+impl Default for ValidationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_ENTRIES)
+    }
+}
+
+impl ValidationCache {
+    /// Cache kept only in memory.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_entries,
+            tick: 0,
+            marker_slots: HashMap::new(),
+            checkpoints: HashMap::new(),
+            path: None,
+            changed: false,
+        }
+    }
+
+    /// Reads cache from a file, or starts an empty one if there's no file yet.
+    pub fn open(path: impl Into<PathBuf>, max_entries: usize) -> Result<Self, String> {
+        let path = path.into();
+        let stored = match std::fs::read(&path) {
+            Ok(bytes) => rmp_serde::from_slice::<Stored>(&bytes).unwrap_or_else(|e| {
+                println!(
+                    "Validation cache {:?} not readable, starting empty: {}",
+                    path, e
+                );
+                Stored::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Stored::default(),
+            Err(e) => return Err(format!("{e}")),
+        };
+
+        let mut cache = Self {
+            entries: stored
+                .entries
+                .into_iter()
+                .map(|cached| (cached.entry.owner, cached))
+                .collect(),
+            max_entries,
+            tick: stored.tick,
+            marker_slots: stored.marker_slots,
+            checkpoints: stored.checkpoints,
+            path: Some(path),
+            changed: false,
+        };
+        cache.evict();
+
+        Ok(cache)
+    }
+
+    /// Writes cache to its file, if anything changed.
+    pub fn save(&mut self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref().filter(|_| self.changed) else {
+            return Ok(());
+        };
+
+        let stored = Stored {
+            tick: self.tick,
+            entries: self.entries.values().cloned().collect(),
+            marker_slots: self.marker_slots.clone(),
+            checkpoints: self.checkpoints.clone(),
+        };
+        let bytes = rmp_serde::to_vec(&stored).map_err(|e| format!("{e}"))?;
+
+        // never leave a half-written file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| format!("{e}"))?;
+        std::fs::rename(&tmp, path).map_err(|e| format!("{e}"))?;
+        self.changed = false;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, owner: &PublicKey) -> Option<&mut Cached> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(owner).map(|cached| {
+            cached.used = tick;
+            cached
+        })
+    }
+
+    /// GraphEntry with given owner, if cached.
+    pub fn entry(&mut self, owner: &PublicKey) -> Option<GraphEntry> {
+        self.touch(owner).map(|cached| cached.entry.clone())
+    }
+
+    /// Verdict about the entry, if the same entry is cached.
+    pub fn verdict(&mut self, entry: &GraphEntry) -> Option<Verdict> {
+        let hash = entry_hash(entry).ok()?;
+        self.touch(&entry.owner)
+            .filter(|cached| cached.hash == hash)
+            .and_then(|cached| cached.verdict.clone())
+    }
+
+    /// Caches an entry, keeping its verdict if it's the same entry.
+    pub fn insert(&mut self, entry: GraphEntry) -> Result<(), String> {
+        let hash = entry_hash(&entry)?;
+        if self
+            .touch(&entry.owner)
+            .is_some_and(|cached| cached.hash == hash)
+        {
+            return Ok(());
+        }
+
+        self.entries.insert(
+            entry.owner,
+            Cached {
+                entry,
+                hash,
+                verdict: None,
+                used: self.tick,
+            },
+        );
+        self.changed = true;
+        self.evict();
+
+        Ok(())
+    }
+
+    pub fn set_verdict(&mut self, entry: &GraphEntry, verdict: Verdict) -> Result<(), String> {
+        self.insert(entry.clone())?;
+        if let Some(cached) = self.entries.get_mut(&entry.owner) {
+            cached.verdict = Some(verdict);
+            self.changed = true;
+        }
+
+        Ok(())
+    }
+
+    /// First invalid marker slot of a token not read yet.
+    pub fn marker_slot(&self, token_id: &XorName) -> u64 {
+        self.marker_slots.get(token_id).copied().unwrap_or(0)
+    }
+
+    pub fn set_marker_slot(&mut self, token_id: XorName, slot: u64) {
+        if self.marker_slots.insert(token_id, slot) != Some(slot) {
+            self.changed = true;
+        }
+    }
+
+    /// Number of the first checkpoint of a token not read yet.
+    pub fn checkpoint_slot(&self, token_id: &XorName) -> u64 {
+        self.checkpoints
+            .get(token_id)
+            .map_or(0, |checkpoints| checkpoints.next)
+    }
+
+    /// Adds outputs of checkpoints read, up to checkpoint `next` (not included).
+    pub fn add_checkpoints(
+        &mut self,
+        token_id: XorName,
+        next: u64,
+        outputs: impl IntoIterator<Item = (PublicKey, PublicKey)>,
+    ) {
+        let checkpoints = self.checkpoints.entry(token_id).or_default();
+        checkpoints.outputs.extend(outputs);
+        if checkpoints.next != next {
+            checkpoints.next = next;
+            self.changed = true;
+        }
+    }
+
+    /// Whether the output of `spend` to `key` is checkpointed.
+    pub fn is_checkpointed(&self, token_id: &XorName, spend: PublicKey, key: PublicKey) -> bool {
+        self.checkpoints
+            .get(token_id)
+            .is_some_and(|checkpoints| checkpoints.outputs.contains(&(spend, key)))
+    }
+
+    /// Takes entries, marker slots and checkpoints of other cache, eg. one used meanwhile
+    /// by other task.
+    /// Its entries replace these, unless they would lose a verdict of the same entry.
+    pub fn merge(&mut self, other: ValidationCache) {
+        for (owner, cached) in other.entries {
+            let keep = self.entries.get(&owner).is_some_and(|own| {
+                own.hash == cached.hash && own.verdict.is_some() && cached.verdict.is_none()
+            });
+            if !keep {
+                self.entries.insert(owner, cached);
+            }
+        }
+        for (token_id, slot) in other.marker_slots {
+            if slot > self.marker_slot(&token_id) {
+                self.marker_slots.insert(token_id, slot);
+            }
+        }
+        for (token_id, checkpoints) in other.checkpoints {
+            if checkpoints.next > self.checkpoint_slot(&token_id) {
+                self.checkpoints.insert(token_id, checkpoints);
+            }
+        }
+        self.tick = self.tick.max(other.tick);
+        self.changed |= other.changed;
+        self.evict();
+    }
+
+    /// Evicts least recently used entries, a tenth more than needed,
+    /// so that eviction doesn't run on every insert.
+    fn evict(&mut self) {
+        if self.entries.len() <= self.max_entries {
+            return;
+        }
+
+        let excess = self.entries.len() - self.max_entries + self.max_entries / 10;
+        let mut used: Vec<(u64, PublicKey)> = self
+            .entries
+            .values()
+            .map(|cached| (cached.used, cached.entry.owner))
+            .collect();
+        used.sort_unstable_by_key(|(used, _owner)| *used);
+
+        for (_used, owner) in used.into_iter().take(excess) {
+            self.entries.remove(&owner);
+        }
+        self.changed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+
+    fn entry() -> GraphEntry {
+        GraphEntry::new(&SecretKey::random(), vec![], [0; 32], vec![])
+    }
+
+    #[test]
+    fn verdicts_and_eviction() -> Result<(), String> {
+        let mut cache = ValidationCache::new(10);
+
+        let first = entry();
+        cache.set_verdict(&first, Verdict::Checked)?;
+        assert_eq!(Some(Verdict::Checked), cache.verdict(&first));
+        assert_eq!(Some(first.clone()), cache.entry(&first.owner));
+
+        // other entry of the same owner doesn't get the verdict
+        let mut forged = first.clone();
+        forged.content = [1; 32];
+        assert_eq!(None, cache.verdict(&forged));
+
+        for _ in 0..10 {
+            cache.insert(entry())?;
+            cache.entry(&first.owner); // keep using it
+        }
+        assert!(cache.len() <= 10);
+        assert_eq!(Some(Verdict::Checked), cache.verdict(&first));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge() -> Result<(), String> {
+        let mut cache = ValidationCache::new(10);
+        let mut other = ValidationCache::new(10);
+        let token_id = XorName::from_content(&[1u8]);
+
+        let checked = entry();
+        cache.set_verdict(&checked, Verdict::Checked)?;
+        other.insert(checked.clone())?;
+        let invalid = entry();
+        other.set_verdict(&invalid, Verdict::Invalid("Bad.".to_string()))?;
+        other.set_marker_slot(token_id, 2);
+        let output = (checked.owner, invalid.owner);
+        cache.add_checkpoints(token_id, 1, []);
+        other.add_checkpoints(token_id, 3, [output]);
+
+        cache.merge(other);
+        assert_eq!(Some(Verdict::Checked), cache.verdict(&checked));
+        assert_eq!(
+            Some(Verdict::Invalid("Bad.".to_string())),
+            cache.verdict(&invalid)
+        );
+        assert_eq!(2, cache.marker_slot(&token_id));
+        assert_eq!(3, cache.checkpoint_slot(&token_id));
+        assert!(cache.is_checkpointed(&token_id, output.0, output.1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn persistence() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!(
+            "act-validation-cache-{:x}",
+            XorName::from_content(&SecretKey::random().to_bytes())
+        ));
+
+        let valid = entry();
+        let mut cache = ValidationCache::open(&path, 10)?;
+        assert!(cache.is_empty());
+        cache.set_verdict(&valid, Verdict::Valid(Some(valid.owner)))?;
+        cache.set_marker_slot(XorName(valid.content), 3);
+        let output = (valid.owner, SecretKey::random().public_key());
+        cache.add_checkpoints(XorName(valid.content), 2, [output]);
+        cache.save()?;
+
+        let mut cache = ValidationCache::open(&path, 10)?;
+        assert_eq!(3, cache.marker_slot(&XorName(valid.content)));
+        assert_eq!(2, cache.checkpoint_slot(&XorName(valid.content)));
+        assert!(cache.is_checkpointed(&XorName(valid.content), output.0, output.1));
+        assert_eq!(
+            Some(Verdict::Valid(Some(valid.owner))),
+            cache.verdict(&valid)
+        );
+        std::fs::remove_file(&path).map_err(|e| format!("{e}"))?;
+
+        Ok(())
+    }
+}
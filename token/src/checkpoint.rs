@@ -0,0 +1,324 @@
+use crate::act::ActExt;
+use crate::cache::ValidationCache;
+use crate::history::now;
+use crate::validate::{ValidateExt, MAX_VALIDATION_BUDGET};
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, SecretKey, Signature, XorName,
+};
+use futures::Future;
+use serde::{Deserialize, Serialize};
+
+/// Key signing checkpoints of tokens created by a wallet.
+pub fn issuer_pk(pk: &PublicKey) -> PublicKey {
+    pk.derive_child(b"issuer")
+}
+
+pub fn issuer_sk(sk: &SecretKey) -> SecretKey {
+    sk.derive_child(b"issuer")
+}
+
+/// Checkpoints of a token are numbered GraphEntries with keys derived from issuer's key,
+/// so anyone can find them, but only the issuer can write them. Each points to a chunk
+/// with the signed checkpoint.
+fn slot_index(token_id: &XorName, number: u64) -> Vec<u8> {
+    [token_id.0.as_slice(), &number.to_be_bytes()].concat()
+}
+
+fn slot_address(issuer: &PublicKey, token_id: &XorName, number: u64) -> GraphEntryAddress {
+    GraphEntryAddress::new(issuer.derive_child(&slot_index(token_id, number)))
+}
+
+/// Outputs signed by token's issuer, whose key is committed in TokenInfo, as known valid.
+/// Validation doesn't traverse ancestors of a checkpointed output, so it can finish without
+/// reaching genesis.
+///
+/// Trust: checkpoint is taken as proof of validity of the spend, so the issuer could
+/// checkpoint a forged output and effectively mint tokens. Tokens without issuer key
+/// are always validated back to genesis.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub token_id: XorName,
+    /// Sequence number, checkpoints are published one after another.
+    pub number: u64,
+    /// Seconds since UNIX epoch.
+    pub created: u64,
+    /// Outputs known valid, as spend address and the key it pays to.
+    pub outputs: Vec<(PublicKey, PublicKey)>,
+}
+
+impl Checkpoint {
+    fn bytes_to_sign(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| format!("{e}"))
+    }
+}
+
+/// Checkpoint with issuer's signature, as stored in a chunk.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct SignedCheckpoint {
+    pub checkpoint: Checkpoint,
+    pub signature: Signature,
+}
+
+impl SignedCheckpoint {
+    pub fn sign(checkpoint: Checkpoint, issuer_sk: &SecretKey) -> Result<Self, String> {
+        let signature = issuer_sk.sign(checkpoint.bytes_to_sign()?);
+        Ok(Self {
+            checkpoint,
+            signature,
+        })
+    }
+
+    pub fn verify(&self, issuer: &PublicKey) -> bool {
+        self.checkpoint
+            .bytes_to_sign()
+            .is_ok_and(|bytes| issuer.verify(&self.signature, bytes))
+    }
+}
+
+pub trait CheckpointExt {
+    /// Publishes a checkpoint of given outputs in the next free slot. Returns its number.
+    fn act_checkpoint(
+        &self,
+        token_id: XorName,
+        issuer_sk: &SecretKey,
+        outputs: Vec<(PublicKey, PublicKey)>,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<u64, String>> + Send;
+
+    /// Validates spends with maximal budget and checkpoints all their outputs.
+    /// Fails if any of them can't be validated back to genesis or earlier checkpoints.
+    fn act_checkpoint_spends(
+        &self,
+        token_id: XorName,
+        issuer_sk: &SecretKey,
+        spends: Vec<PublicKey>,
+        cache: &mut ValidationCache,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<u64, String>> + Send;
+
+    /// Reads token's checkpoints signed by the issuer from checkpoint `number` on, skipping
+    /// invalid ones. Stops at a checkpoint not readable yet. Returns checkpoints read and
+    /// number of the first one not read.
+    fn act_checkpoints_from(
+        &self,
+        token_id: &XorName,
+        issuer: &PublicKey,
+        number: u64,
+    ) -> impl Future<Output = Result<(Vec<Checkpoint>, u64), String>> + Send;
+
+    /// Adds checkpointed outputs of a token not read yet to the cache, nothing if TokenInfo
+    /// has no issuer key. Returns number of checkpoints read.
+    fn act_checkpointed(
+        &self,
+        token_id: &XorName,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<usize, String>> + Send;
+}
+
+impl CheckpointExt for Client {
+    async fn act_checkpoint(
+        &self,
+        token_id: XorName,
+        issuer_sk: &SecretKey,
+        outputs: Vec<(PublicKey, PublicKey)>,
+        payment: &PaymentOption,
+    ) -> Result<u64, String> {
+        let issuer = issuer_sk.public_key();
+        let mut number = 0;
+        while self
+            .graph_entry_check_existence(&slot_address(&issuer, &token_id, number))
+            .await
+            .map_err(|e| format!("{e}"))?
+        {
+            number += 1;
+        }
+
+        let signed = SignedCheckpoint::sign(
+            Checkpoint {
+                token_id,
+                number,
+                created: now(),
+                outputs,
+            },
+            issuer_sk,
+        )?;
+        let bytes = rmp_serde::to_vec(&signed).map_err(|e| format!("{e}"))?;
+        let (_paid, chunk_address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Checkpoint Chunk: {}", chunk_address);
+
+        let entry = GraphEntry::new(
+            &issuer_sk.derive_child(&slot_index(&token_id, number)),
+            vec![],
+            chunk_address.xorname().0,
+            vec![],
+        );
+        let (_paid, address) = self
+            .graph_entry_put(entry, payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Checkpoint {}: {}", number, address);
+
+        Ok(number)
+    }
+
+    async fn act_checkpoint_spends(
+        &self,
+        token_id: XorName,
+        issuer_sk: &SecretKey,
+        spends: Vec<PublicKey>,
+        cache: &mut ValidationCache,
+        payment: &PaymentOption,
+    ) -> Result<u64, String> {
+        let mut outputs = Vec::new();
+        for spend_address in spends {
+            let spend = self
+                .graph_entry_get(&GraphEntryAddress::new(spend_address))
+                .await
+                .map_err(|e| format!("{e}"))?;
+            if XorName(spend.content) != token_id {
+                return Err(format!("Spend of other token: {}.", spend_address.to_hex()));
+            }
+
+            let validation = self
+                .act_validate(&spend, MAX_VALIDATION_BUDGET, cache)
+                .await?;
+            if !validation.complete {
+                return Err(format!(
+                    "Spend {} could not be fully validated.",
+                    spend_address.to_hex()
+                ));
+            }
+            outputs.extend(spend.descendants.iter().map(|(key, _)| (spend.owner, *key)));
+        }
+
+        self.act_checkpoint(token_id, issuer_sk, outputs, payment)
+            .await
+    }
+
+    async fn act_checkpoints_from(
+        &self,
+        token_id: &XorName,
+        issuer: &PublicKey,
+        mut number: u64,
+    ) -> Result<(Vec<Checkpoint>, u64), String> {
+        let mut checkpoints = Vec::new();
+
+        loop {
+            let address = slot_address(issuer, token_id, number);
+            if !self
+                .graph_entry_check_existence(&address)
+                .await
+                .map_err(|e| format!("{e}"))?
+            {
+                break;
+            }
+            let entry = self
+                .graph_entry_get(&address)
+                .await
+                .map_err(|e| format!("{e}"))?;
+            let slot = number;
+
+            // read again next time, checkpoints after it are not skipped in the cache
+            let chunk = match self
+                .chunk_get(&ChunkAddress::new(XorName(entry.content)))
+                .await
+            {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    println!("Checkpoint {} not readable: {}", slot, e);
+                    break;
+                }
+            };
+            number += 1;
+            match rmp_serde::from_slice::<SignedCheckpoint>(chunk.value()) {
+                Ok(signed)
+                    if signed.verify(issuer)
+                        && signed.checkpoint.token_id == *token_id
+                        && signed.checkpoint.number == slot =>
+                {
+                    checkpoints.push(signed.checkpoint)
+                }
+                Ok(_) => println!("Skipping checkpoint {}: not signed by issuer", slot),
+                Err(e) => println!("Skipping checkpoint {}: {}", slot, e),
+            }
+        }
+
+        Ok((checkpoints, number))
+    }
+
+    async fn act_checkpointed(
+        &self,
+        token_id: &XorName,
+        cache: &mut ValidationCache,
+    ) -> Result<usize, String> {
+        let Some(issuer) = self.act_token_info(token_id).await?.issuer else {
+            return Ok(0);
+        };
+
+        let (checkpoints, next) = self
+            .act_checkpoints_from(token_id, &issuer, cache.checkpoint_slot(token_id))
+            .await?;
+        let read = checkpoints.len();
+        cache.add_checkpoints(
+            *token_id,
+            next,
+            checkpoints
+                .into_iter()
+                .flat_map(|checkpoint| checkpoint.outputs),
+        );
+
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoint_signature() -> Result<(), String> {
+        let sk = SecretKey::random();
+        let issuer = issuer_pk(&sk.public_key());
+        assert_eq!(issuer, issuer_sk(&sk).public_key());
+
+        let token_id = XorName::from_content(&[1u8]);
+        let signed = SignedCheckpoint::sign(
+            Checkpoint {
+                token_id,
+                number: 0,
+                created: 1000,
+                outputs: vec![(
+                    SecretKey::random().public_key(),
+                    SecretKey::random().public_key(),
+                )],
+            },
+            &issuer_sk(&sk),
+        )?;
+        assert!(signed.verify(&issuer));
+        assert!(!signed.verify(&sk.public_key()));
+
+        let mut tampered = signed.clone();
+        tampered.checkpoint.number = 1;
+        assert!(!tampered.verify(&issuer));
+
+        // slot keys are derivable from issuer's public key, but differ per token and number
+        assert_eq!(
+            slot_address(&issuer, &token_id, 0),
+            GraphEntryAddress::new(
+                issuer_sk(&sk)
+                    .derive_child(&slot_index(&token_id, 0))
+                    .public_key()
+            )
+        );
+        assert_ne!(
+            slot_address(&issuer, &token_id, 0),
+            slot_address(&issuer, &token_id, 1)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use autonomi::{PublicKey, XorName};
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum TxStatus {
+    /// Spend was created, but not yet confirmed to be stored on the network.
+    Pending,
+    Confirmed,
+    Failed,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    pub direction: Direction,
+    pub token_id: XorName,
+    pub amount: U256,
+    /// Recipient's key when sending, owner of the received spend when receiving.
+    pub counterparty: PublicKey,
+    /// Address of the spend GraphEntry.
+    pub spend: PublicKey,
+    /// Local time in seconds since UNIX epoch.
+    pub timestamp: u64,
+    pub memo: Option<String>,
+    pub status: TxStatus,
+}
+
+impl Transaction {
+    pub fn sent(
+        token_id: XorName,
+        amount: U256,
+        to: PublicKey,
+        spend: PublicKey,
+        memo: Option<String>,
+    ) -> Self {
+        Self {
+            direction: Direction::Sent,
+            token_id,
+            amount,
+            counterparty: to,
+            spend,
+            timestamp: now(),
+            memo,
+            status: TxStatus::Pending,
+        }
+    }
+
+    pub fn received(
+        token_id: XorName,
+        amount: U256,
+        spend: PublicKey,
+        memo: Option<String>,
+    ) -> Self {
+        Self {
+            direction: Direction::Received,
+            token_id,
+            amount,
+            counterparty: spend,
+            spend,
+            timestamp: now(),
+            memo,
+            status: TxStatus::Confirmed,
+        }
+    }
+
+    /// Sent to the spend's own key, which can't be spent.
+    pub fn is_burn(&self) -> bool {
+        self.direction == Direction::Sent && self.counterparty == self.spend
+    }
+}
+
+/// Seconds since UNIX epoch, according to local clock.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
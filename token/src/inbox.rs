@@ -0,0 +1,351 @@
+use crate::cache::ValidationCache;
+use crate::proof::ProofExt;
+use crate::request::Received;
+use crate::wallet::{Wallet, WalletExt};
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, SecretKey, XorName,
+};
+use blsttc::Ciphertext;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use sn_curv::elliptic::curves::ECScalar;
+
+/// How many times payer tries next slot, when other payer took the free one first.
+const NOTIFY_ATTEMPTS: u64 = 5;
+/// Most taken slots payer skips looking for a free one. Anyone can write the slots,
+/// so payer remembers where it found a free one, not to go through spam again.
+const NOTIFY_PROBES: u64 = 64;
+/// Most slots read by payee at once, the rest is read next time.
+const INBOX_READS: u64 = 64;
+/// How many times payee reads a slot, which couldn't be read or received, eg. not replicated
+/// yet. Anyone can write the slots, so one pointing nowhere is given up, not to retry it forever.
+pub const INBOX_RETRIES: u32 = 10;
+
+/// Notice of a payment, left by payer in payee's inbox.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Notice {
+    /// Address of the spend GraphEntry.
+    pub spend: PublicKey,
+    /// Address of a Chunk with spend's proof bundle.
+    #[serde(default)]
+    pub proof: Option<XorName>,
+}
+
+/// Inbox key of a wallet. Payers encrypt notices to it, payee decrypts them with `inbox_sk`.
+pub fn inbox_pk(pk: &PublicKey) -> PublicKey {
+    pk.derive_child(b"inbox")
+}
+
+pub fn inbox_sk(sk: &SecretKey) -> SecretKey {
+    sk.derive_child(b"inbox")
+}
+
+/// Owner key of n-th inbox slot. Slots are GraphEntries with keys derivable by anyone
+/// knowing the inbox key, so every payer can write to the next free one. Each slot
+/// points to a chunk with a notice encrypted to the inbox key.
+fn slot_sk(inbox: &PublicKey, slot: u64) -> Result<SecretKey, String> {
+    open_slot_sk(&XorName::from_content(&inbox.to_bytes()), slot)
+}
+
+/// Key of n-th slot of a list anyone knowing the seed can write to.
+pub(crate) fn open_slot_sk(seed: &XorName, slot: u64) -> Result<SecretKey, String> {
+    let root = SecretKey::from_bytes(sn_bls_ckd::derive_master_sk(&seed.0)?.serialize().into())
+        .map_err(|e| format!("{e}"))?;
+
+    Ok(root.derive_child(&slot.to_be_bytes()))
+}
+
+fn slot_address(inbox: &PublicKey, slot: u64) -> Result<GraphEntryAddress, String> {
+    Ok(GraphEntryAddress::new(slot_sk(inbox, slot)?.public_key()))
+}
+
+/// Notices read from an inbox.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct InboxRead {
+    /// Notices with their slots.
+    pub notices: Vec<(u64, Notice)>,
+    /// Slots which chunk couldn't be read, to read again later.
+    pub unreadable: Vec<u64>,
+    /// First slot not read, to continue from next time.
+    pub next: u64,
+}
+
+impl Notice {
+    pub fn encrypt(&self, inbox: &PublicKey) -> Result<Vec<u8>, String> {
+        let bytes = rmp_serde::to_vec(self).map_err(|e| format!("{e}"))?;
+        Ok(inbox.encrypt(bytes).to_bytes())
+    }
+
+    pub fn decrypt(bytes: &[u8], inbox_sk: &SecretKey) -> Result<Self, String> {
+        let ciphertext = Ciphertext::from_bytes(bytes).map_err(|e| format!("{e}"))?;
+        let bytes = inbox_sk
+            .decrypt(&ciphertext)
+            .ok_or("Notice not encrypted to this inbox.".to_string())?;
+        rmp_serde::from_slice(&bytes).map_err(|e| format!("{e}"))
+    }
+}
+
+pub trait InboxExt {
+    /// Leaves a notice in the first free slot of payee's inbox, looking for it from the slot
+    /// the wallet found free last time. Wallet is changed, but not saved. Returns the slot number.
+    fn act_inbox_notify(
+        &self,
+        wallet: &mut Wallet,
+        inbox: &PublicKey,
+        notice: &Notice,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<u64, String>> + Send;
+
+    /// Reads notices from slot `from` on, at most `INBOX_READS` slots. Slots that can't be
+    /// decrypted or parsed are skipped, ones that can't be read are returned to be read again.
+    fn act_inbox_read(
+        &self,
+        inbox_sk: &SecretKey,
+        from: u64,
+    ) -> impl Future<Output = Result<InboxRead, String>> + Send;
+
+    /// Notice in an inbox slot, `None` if it can't be decrypted or parsed.
+    fn act_inbox_slot(
+        &self,
+        inbox_sk: &SecretKey,
+        slot: u64,
+    ) -> impl Future<Output = Result<Option<Notice>, String>> + Send;
+
+    /// Reads new notices from wallet's inbox and receives spends they point to. Slots that
+    /// couldn't be read or received, this time or before, are tried again, at most
+    /// `INBOX_RETRIES` times. Wallet is changed, but not saved. Returns received payments.
+    fn act_inbox_receive(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<Vec<Received>, String>> + Send;
+}
+
+impl InboxExt for Client {
+    async fn act_inbox_notify(
+        &self,
+        wallet: &mut Wallet,
+        inbox: &PublicKey,
+        notice: &Notice,
+        payment: &PaymentOption,
+    ) -> Result<u64, String> {
+        let chunk = Chunk::new(Bytes::from(notice.encrypt(inbox)?));
+        let (_paid, chunk_address) = self
+            .chunk_put(&chunk, payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Notice Chunk: {}", chunk_address);
+
+        let first = wallet.notify_slot(inbox);
+        let mut slot = first;
+        while self
+            .graph_entry_check_existence(&slot_address(inbox, slot)?)
+            .await
+            .map_err(|e| format!("{e}"))?
+        {
+            slot += 1;
+            if slot - first >= NOTIFY_PROBES {
+                wallet.set_notify_slot(*inbox, slot);
+                return Err("No free inbox slot found.".into());
+            }
+        }
+
+        let mut error = String::new();
+        for _ in 0..NOTIFY_ATTEMPTS {
+            let entry = GraphEntry::new(
+                &slot_sk(inbox, slot)?,
+                vec![],
+                chunk_address.xorname().0,
+                vec![],
+            );
+
+            match self.graph_entry_put(entry, payment.clone()).await {
+                Ok((_paid, address)) => {
+                    println!("Notice slot {}: {}", slot, address);
+                    wallet.set_notify_slot(*inbox, slot + 1);
+                    return Ok(slot);
+                }
+                Err(e) => {
+                    println!("Notice slot {} taken: {}", slot, e);
+                    error = format!("{e}");
+                    slot += 1;
+                }
+            }
+        }
+        wallet.set_notify_slot(*inbox, slot);
+
+        Err(error)
+    }
+
+    async fn act_inbox_read(&self, inbox_sk: &SecretKey, from: u64) -> Result<InboxRead, String> {
+        let inbox = inbox_sk.public_key();
+        let mut read = InboxRead::default();
+        let mut slot = from;
+
+        while slot - from < INBOX_READS {
+            if !self
+                .graph_entry_check_existence(&slot_address(&inbox, slot)?)
+                .await
+                .map_err(|e| format!("{e}"))?
+            {
+                break;
+            }
+
+            match self.act_inbox_slot(inbox_sk, slot).await {
+                Ok(Some(notice)) => read.notices.push((slot, notice)),
+                Ok(None) => (),
+                Err(e) => {
+                    // maybe not replicated yet, the slots after it are read anyway
+                    println!("Inbox slot {} not readable: {}", slot, e);
+                    read.unreadable.push(slot);
+                }
+            }
+
+            slot += 1;
+        }
+        read.next = slot;
+
+        Ok(read)
+    }
+
+    async fn act_inbox_slot(
+        &self,
+        inbox_sk: &SecretKey,
+        slot: u64,
+    ) -> Result<Option<Notice>, String> {
+        let entry = self
+            .graph_entry_get(&slot_address(&inbox_sk.public_key(), slot)?)
+            .await
+            .map_err(|e| format!("{e}"))?;
+        let chunk = self
+            .chunk_get(&ChunkAddress::new(XorName(entry.content)))
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        match Notice::decrypt(chunk.value(), inbox_sk) {
+            Ok(notice) => Ok(Some(notice)),
+            Err(e) => {
+                println!("Skipping inbox slot {}: {}", slot, e);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn act_inbox_receive(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        cache: &mut ValidationCache,
+    ) -> Result<Vec<Received>, String> {
+        let inbox_sk = inbox_sk(sk);
+        let read = self.act_inbox_read(&inbox_sk, wallet.inbox_slot()).await?;
+        let mut notices = read.notices;
+        let mut failed = read.unreadable;
+
+        let retries: Vec<u64> = wallet.inbox_retries().keys().copied().collect();
+        for slot in retries {
+            match self.act_inbox_slot(&inbox_sk, slot).await {
+                Ok(Some(notice)) => notices.push((slot, notice)),
+                Ok(None) => wallet.inbox_attempted(slot, false, INBOX_RETRIES),
+                Err(e) => {
+                    println!("Inbox slot {} not readable: {}", slot, e);
+                    failed.push(slot);
+                }
+            }
+        }
+
+        let mut received = Vec::new();
+        for (slot, notice) in notices {
+            let result = match notice.proof {
+                Some(proof) => match self.act_proof_get(&proof).await {
+                    Ok(bundle) if bundle.spend.owner == notice.spend => {
+                        self.act_receive_proven(wallet, Some(sk), &bundle, cache)
+                            .await
+                    }
+                    _ => {
+                        self.act_receive(wallet, Some(sk), notice.spend, cache)
+                            .await
+                    }
+                },
+                None => {
+                    self.act_receive(wallet, Some(sk), notice.spend, cache)
+                        .await
+                }
+            };
+            match result {
+                Ok(payments) => {
+                    received.extend(payments);
+                    wallet.inbox_attempted(slot, false, INBOX_RETRIES);
+                }
+                Err(e) => {
+                    println!("Notice of spend {:.4}(...): {}", notice.spend.to_hex(), e);
+                    failed.push(slot);
+                }
+            }
+        }
+        for slot in failed {
+            wallet.inbox_attempted(slot, true, INBOX_RETRIES);
+        }
+        wallet.set_inbox_slot(read.next);
+
+        Ok(received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notice_encryption() -> Result<(), String> {
+        let sk = SecretKey::random();
+        let inbox = inbox_pk(&sk.public_key());
+        assert_eq!(inbox, inbox_sk(&sk).public_key());
+
+        let notice = Notice {
+            spend: SecretKey::random().public_key(),
+            proof: Some(XorName::from_content(&[1u8])),
+        };
+        let bytes = notice.encrypt(&inbox)?;
+
+        assert_eq!(notice, Notice::decrypt(&bytes, &inbox_sk(&sk))?);
+        assert_ne!(
+            Ok(notice.clone()),
+            Notice::decrypt(&bytes, &SecretKey::random())
+        );
+        assert!(Notice::decrypt(&bytes[1..], &inbox_sk(&sk)).is_err());
+
+        assert_eq!(slot_sk(&inbox, 1)?, slot_sk(&inbox, 1)?);
+        assert_ne!(slot_sk(&inbox, 1)?, slot_sk(&inbox, 2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn inbox_retries() -> Result<(), String> {
+        let mut wallet = Wallet::new(SecretKey::random().public_key());
+
+        // notice not received is read again, until it's received
+        wallet.inbox_attempted(3, true, INBOX_RETRIES);
+        wallet.inbox_attempted(3, true, INBOX_RETRIES);
+        assert_eq!(Some(&2), wallet.inbox_retries().get(&3));
+        wallet.inbox_attempted(3, false, INBOX_RETRIES);
+        assert!(wallet.inbox_retries().is_empty());
+
+        // slot pointing nowhere is given up
+        for _ in 1..INBOX_RETRIES {
+            wallet.inbox_attempted(5, true, INBOX_RETRIES);
+        }
+        assert_eq!(Some(&(INBOX_RETRIES - 1)), wallet.inbox_retries().get(&5));
+        wallet.inbox_attempted(5, true, INBOX_RETRIES);
+        assert!(wallet.inbox_retries().is_empty());
+
+        // kept with the wallet between reads
+        wallet.inbox_attempted(7, true, INBOX_RETRIES);
+        assert_eq!(wallet, Wallet::from_bytes(&wallet.to_bytes()?)?);
+
+        Ok(())
+    }
+}
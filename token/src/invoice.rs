@@ -0,0 +1,326 @@
+use crate::act::ActExt;
+use crate::history::now;
+use crate::inbox::{InboxExt, Notice};
+use crate::memo::Memo;
+use crate::proof::ProofExt;
+use crate::receipt::Receipt;
+use crate::wallet::{Wallet, WalletExt};
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, PublicKey, SecretKey,
+    Signature, XorName,
+};
+use futures::Future;
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct LineItem {
+    pub description: String,
+    pub amount: U256,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Invoice {
+    /// Merchant's identity, the key signing the invoice.
+    pub merchant: PublicKey,
+    pub merchant_name: String,
+    /// Merchant's own invoice number.
+    pub number: String,
+    pub token_id: XorName,
+    /// Key to pay to.
+    pub key: PublicKey,
+    pub items: Vec<LineItem>,
+    /// Seconds since UNIX epoch.
+    pub created: u64,
+    pub expiry: Option<u64>,
+    /// Merchant's inbox, to leave a notice of payment in.
+    pub inbox: Option<PublicKey>,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum InvoiceStatus {
+    Unpaid,
+    /// Amount paid so far.
+    Underpaid(U256),
+    /// Amount paid, can be more than due.
+    Paid(U256),
+    Expired,
+}
+
+impl Invoice {
+    /// Sum of line items.
+    pub fn amount_due(&self) -> Result<U256, String> {
+        self.items.iter().try_fold(U256::ZERO, |sum, item| {
+            sum.checked_add(item.amount).ok_or("Overflow.".to_string())
+        })
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.is_some_and(|expiry| now >= expiry)
+    }
+
+    /// Late payments still count, so fully paid invoice is never expired.
+    pub fn status(&self, paid: U256, now: u64) -> Result<InvoiceStatus, String> {
+        Ok(if paid >= self.amount_due()? {
+            InvoiceStatus::Paid(paid)
+        } else if self.is_expired(now) {
+            InvoiceStatus::Expired
+        } else if paid > U256::ZERO {
+            InvoiceStatus::Underpaid(paid)
+        } else {
+            InvoiceStatus::Unpaid
+        })
+    }
+
+    fn bytes_to_sign(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| format!("{e}"))
+    }
+}
+
+/// Invoice with merchant's signature, as stored in a chunk. Address of the chunk is invoice ID.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct SignedInvoice {
+    pub invoice: Invoice,
+    pub signature: Signature,
+}
+
+impl SignedInvoice {
+    pub fn sign(invoice: Invoice, merchant_sk: &SecretKey) -> Result<Self, String> {
+        if merchant_sk.public_key() != invoice.merchant {
+            return Err("Invoice must be signed by the merchant.".into());
+        }
+        invoice.amount_due()?; // check
+
+        let signature = merchant_sk.sign(invoice.bytes_to_sign()?);
+        Ok(Self { invoice, signature })
+    }
+
+    pub fn verify(&self) -> bool {
+        self.invoice
+            .bytes_to_sign()
+            .is_ok_and(|bytes| self.invoice.merchant.verify(&self.signature, bytes))
+    }
+}
+
+/// Memo attached to payments of an invoice, so merchant can tell which invoice they pay.
+pub fn invoice_reference(invoice_id: &XorName) -> String {
+    format!("invoice:{:x}", invoice_id)
+}
+
+pub trait InvoiceExt {
+    /// Stores signed invoice. Returns its ID.
+    fn act_invoice_put(
+        &self,
+        invoice: &SignedInvoice,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<XorName, String>> + Send;
+
+    /// Reads an invoice, checking merchant's signature.
+    fn act_invoice_get(
+        &self,
+        invoice_id: &XorName,
+    ) -> impl Future<Output = Result<SignedInvoice, String>> + Send;
+
+    /// Pays exact amount due to the invoice key, with invoice reference as encrypted memo,
+    /// and notifies merchant's inbox. Wallet is changed, but not saved. Returns receipt of the payment.
+    fn act_invoice_pay(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        invoice_id: &XorName,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Receipt, String>> + Send;
+
+    /// Status of an invoice, computed from spends paying it, eg. `Wallet::invoice_payments`.
+    /// Each spend is counted once.
+    fn act_invoice_status(
+        &self,
+        invoice_id: &XorName,
+        spends: Vec<PublicKey>,
+    ) -> impl Future<Output = Result<InvoiceStatus, String>> + Send;
+}
+
+impl InvoiceExt for Client {
+    async fn act_invoice_put(
+        &self,
+        invoice: &SignedInvoice,
+        payment: &PaymentOption,
+    ) -> Result<XorName, String> {
+        if !invoice.verify() {
+            return Err("Wrong invoice signature.".into());
+        }
+
+        let bytes = rmp_serde::to_vec(invoice).map_err(|e| format!("{e}"))?;
+        let (_paid, address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Invoice Chunk: {}", address);
+
+        Ok(*address.xorname())
+    }
+
+    async fn act_invoice_get(&self, invoice_id: &XorName) -> Result<SignedInvoice, String> {
+        let chunk = self
+            .chunk_get(&ChunkAddress::new(*invoice_id))
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        let invoice: SignedInvoice =
+            rmp_serde::from_slice(chunk.value()).map_err(|e| format!("{e}"))?;
+
+        match invoice.verify() {
+            true => Ok(invoice),
+            false => Err("Wrong invoice signature.".into()),
+        }
+    }
+
+    async fn act_invoice_pay(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        invoice_id: &XorName,
+        payment: &PaymentOption,
+    ) -> Result<Receipt, String> {
+        let invoice = self.act_invoice_get(invoice_id).await?.invoice;
+        println!("Paying invoice: {:?}", invoice);
+
+        if invoice.is_expired(now()) {
+            return Err("Invoice has expired.".into());
+        }
+
+        let memo = Memo::new(&invoice_reference(invoice_id), true)?;
+        let receipt = self
+            .act_spend(
+                wallet,
+                sk,
+                invoice.token_id,
+                invoice.amount_due()?,
+                invoice.key,
+                Some(&memo),
+                payment,
+            )
+            .await?;
+
+        if let Some(inbox) = invoice.inbox {
+            // without proof, merchant validates from the network
+            let proof = match self.act_proof_publish(&receipt.spend, payment).await {
+                Ok(address) => Some(address),
+                Err(e) => {
+                    println!("Could not publish proof bundle: {}", e);
+                    None
+                }
+            };
+            // invoice is paid anyway
+            if let Err(e) = self
+                .act_inbox_notify(
+                    wallet,
+                    &inbox,
+                    &Notice {
+                        spend: receipt.spend.owner,
+                        proof,
+                    },
+                    payment,
+                )
+                .await
+            {
+                println!("Could not notify merchant: {}", e);
+            }
+        }
+
+        Ok(receipt)
+    }
+
+    async fn act_invoice_status(
+        &self,
+        invoice_id: &XorName,
+        spends: Vec<PublicKey>,
+    ) -> Result<InvoiceStatus, String> {
+        let invoice = self.act_invoice_get(invoice_id).await?.invoice;
+
+        let mut paid = U256::ZERO;
+        let mut counted = HashSet::new();
+        for spend in spends.into_iter().filter(|spend| counted.insert(*spend)) {
+            let (token_id, amount) = self.act_unspent(&invoice.key, spend).await?;
+            if token_id == invoice.token_id {
+                paid = paid.checked_add(amount).ok_or("Overflow.".to_string())?;
+            }
+        }
+
+        invoice.status(paid, now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(merchant: &SecretKey) -> Invoice {
+        Invoice {
+            merchant: merchant.public_key(),
+            merchant_name: "Shop".to_string(),
+            number: "2025/01".to_string(),
+            token_id: XorName::from_content(&[1u8]),
+            key: SecretKey::random().public_key(),
+            items: vec![
+                LineItem {
+                    description: "Apples".to_string(),
+                    amount: U256::from(30),
+                },
+                LineItem {
+                    description: "Pears".to_string(),
+                    amount: U256::from(20),
+                },
+            ],
+            created: 1000,
+            expiry: Some(2000),
+            inbox: None,
+        }
+    }
+
+    #[test]
+    fn signed_invoice() -> Result<(), String> {
+        let merchant = SecretKey::random();
+
+        assert!(SignedInvoice::sign(invoice(&merchant), &SecretKey::random()).is_err());
+
+        let mut signed = SignedInvoice::sign(invoice(&merchant), &merchant)?;
+        assert!(signed.verify());
+
+        let bytes = rmp_serde::to_vec(&signed).map_err(|e| format!("{e}"))?;
+        let decoded: SignedInvoice = rmp_serde::from_slice(&bytes).map_err(|e| format!("{e}"))?;
+        assert_eq!(signed, decoded);
+
+        signed.invoice.items[0].amount = U256::from(1);
+        assert!(!signed.verify());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoice_status() -> Result<(), String> {
+        let invoice = invoice(&SecretKey::random());
+        assert_eq!(U256::from(50), invoice.amount_due()?);
+
+        assert_eq!(InvoiceStatus::Unpaid, invoice.status(U256::ZERO, 1500)?);
+        assert_eq!(
+            InvoiceStatus::Underpaid(U256::from(10)),
+            invoice.status(U256::from(10), 1500)?
+        );
+        assert_eq!(
+            InvoiceStatus::Paid(U256::from(60)),
+            invoice.status(U256::from(60), 1500)?
+        );
+        assert_eq!(
+            InvoiceStatus::Expired,
+            invoice.status(U256::from(10), 2000)?
+        );
+        assert_eq!(
+            InvoiceStatus::Paid(U256::from(50)),
+            invoice.status(U256::from(50), 2000)?
+        );
+
+        Ok(())
+    }
+}
@@ -1,8 +1,49 @@
+mod account;
 mod act;
+mod book;
+mod burn;
+mod cache;
+mod checkpoint;
+mod history;
+mod inbox;
+mod invoice;
+mod lock;
+mod marker;
+mod memo;
+mod mint;
+mod proof;
+mod proposal;
+mod receipt;
+mod request;
+mod treasury;
+mod uri;
+mod validate;
 mod wallet;
 
-pub use act::{ActExt, TokenInfo};
-pub use wallet::{Wallet, WalletExt};
+pub use account::{account_pk, account_sk, MAIN_ACCOUNT};
+pub use act::{genesis_outputs, ActExt, TokenInfo};
+pub use book::AddressBook;
+pub use burn::{burned, BurnExt, SupplyAudit};
+pub use cache::{ValidationCache, Verdict, DEFAULT_CACHE_ENTRIES};
+pub use checkpoint::{issuer_pk, issuer_sk, Checkpoint, CheckpointExt, SignedCheckpoint};
+pub use history::{now, Direction, Transaction, TxStatus};
+pub use inbox::{inbox_pk, inbox_sk, InboxExt, InboxRead, Notice, INBOX_RETRIES};
+pub use invoice::{invoice_reference, Invoice, InvoiceExt, InvoiceStatus, LineItem, SignedInvoice};
+pub use lock::{check_locks, has_locks, lock_pk, lock_sk, Lock, LockExt, LockRecord};
+pub use marker::{Marker, MarkerExt, SignedMarker, MARKER_CHECKS};
+pub use memo::{memo_address, Memo, MemoExt, MAX_MEMO_LEN};
+pub use mint::{mint_pk, mint_sk, permitted_mints, MintExt, MintPolicy, Mints};
+pub use proof::{ProofBundle, ProofExt, PROOF_VERSION, SPOT_CHECKS};
+pub use proposal::{signed_from_hex, signed_to_hex, UnsignedSpend};
+pub use receipt::{Receipt, ReceiptExt};
+pub use request::{Funds, Received, Request, RequestMatch, RequestStatus};
+pub use treasury::{treasury_member_pk, treasury_member_sk, Treasury, TreasuryExt};
+pub use uri::{PaymentRequest, URI_SCHEME};
+pub use validate::{
+    check_entry, validation_budget, violated_rule, Roots, Rule, ValidateExt, Validation,
+    MAX_VALIDATION_BUDGET, MIN_VALIDATION_BUDGET,
+};
+pub use wallet::{Wallet, WalletExt, WatchOnly};
 
 #[cfg(test)]
 mod tests {
@@ -10,7 +51,9 @@ mod tests {
     use autonomi::Wallet as EvmWallet;
     use tracing::Level;
 
-    use autonomi::{client::payment::PaymentOption, Client, GraphEntry, PublicKey, SecretKey};
+    use autonomi::{
+        client::payment::PaymentOption, Bytes, Chunk, Client, GraphEntry, PublicKey, SecretKey,
+    };
     use ruint::aliases::U256;
 
     use crate::*;
@@ -105,10 +148,16 @@ mod tests {
                 symbol.into(),
                 DECIMALS,
                 total_supply,
-                issuer_key,
+                vec![(issuer_key, total_supply)],
+                Some(issuer_pk(&sk1.public_key())),
+                None,
                 &with_wallet,
             )
             .await?;
+        assert_eq!(
+            Some(issuer_pk(&sk1.public_key())),
+            client.act_token_info(&token_id).await?.issuer
+        );
 
         // populate wallet struct
 
@@ -208,6 +257,62 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn receives_legacy_token() -> Result<(), String> {
+        const EVM_PRIVKEY: &str =
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let client = Client::init_local().await.map_err(|e| format!("{}", e))?;
+        let evm_wallet = EvmWallet::new_from_private_key(client.evm_network().clone(), EVM_PRIVKEY)
+            .map_err(|e| format!("{}", e))?;
+        let with_wallet = PaymentOption::from(evm_wallet);
+
+        // token info stored before it committed to genesis
+        let token_info = TokenInfo {
+            symbol: "LACT".into(),
+            name: "Legacy Autonomi Community Token".into(),
+            decimals: 0,
+            genesis: None,
+            total_supply: None,
+            issuer: None,
+            mint: None,
+        };
+        let bytes = serde_json::to_string(&token_info).map_err(|e| format!("{}", e))?;
+        let (_paid, address) = client
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), with_wallet.clone())
+            .await
+            .map_err(|e| format!("{}", e))?;
+        let token_id = *address.xorname();
+
+        let sk = SecretKey::random();
+        let mut wallet = Wallet::new(sk.public_key());
+        let key = wallet.request(Some(token_id))?;
+        let genesis = GraphEntry::new(
+            &SecretKey::random(),
+            vec![],
+            token_id.0,
+            vec![(key, U256::from(100).to_be_bytes())],
+        );
+        client
+            .graph_entry_put(genesis.clone(), with_wallet)
+            .await
+            .map_err(|e| format!("{}", e))?;
+
+        // credited, but its genesis can't be confirmed
+        let mut cache = ValidationCache::default();
+        let received = client
+            .act_receive(&mut wallet, Some(&sk), genesis.owner, &mut cache)
+            .await?;
+        assert_eq!(1, received.len());
+        assert!(!received[0].verified);
+        assert_eq!(vec![genesis.owner], wallet.unverified());
+        assert!(wallet.take_to_spend(token_id).is_err());
+
+        assert_eq!(1, wallet.accept_unverified(token_id));
+        assert_eq!(U256::from(100), wallet.balance(token_id)?);
+
+        Ok(())
+    }
+
     // TODO: de/serialze wallet
 
     #[test]
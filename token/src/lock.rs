@@ -0,0 +1,345 @@
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, SecretKey, XorName,
+};
+use futures::Future;
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+
+/// Key of the GraphEntry linking a spend to its lock record. Spend with locked outputs pays
+/// zero to this key, so the link is committed in the spend and can't be added later.
+pub fn lock_pk(spend: &PublicKey) -> PublicKey {
+    spend.derive_child(b"locks")
+}
+
+pub fn lock_sk(spend_sk: &SecretKey) -> SecretKey {
+    spend_sk.derive_child(b"locks")
+}
+
+/// Spending condition of an output: it unlocks in releases, like a vesting schedule.
+/// Time lock is a single release of the whole amount.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Lock {
+    /// Key of the locked output.
+    pub key: PublicKey,
+    /// From given time (seconds since UNIX epoch) on, given amount is unlocked. Ordered by time.
+    pub releases: Vec<(u64, U256)>,
+}
+
+impl Lock {
+    pub fn until(key: PublicKey, amount: U256, time: u64) -> Self {
+        Self {
+            key,
+            releases: vec![(time, amount)],
+        }
+    }
+
+    pub fn total(&self) -> Result<U256, String> {
+        self.releases
+            .iter()
+            .try_fold(U256::ZERO, |sum, (_from, amount)| sum.checked_add(*amount))
+            .ok_or("Overflow.".to_string())
+    }
+
+    /// Amount still locked at given time.
+    pub fn locked(&self, time: u64) -> U256 {
+        self.remaining(time)
+            .iter()
+            .fold(U256::ZERO, |sum, (_from, amount)| {
+                sum.saturating_add(*amount)
+            })
+    }
+
+    /// Releases not unlocked by given time.
+    pub fn remaining(&self, time: u64) -> Vec<(u64, U256)> {
+        self.releases
+            .iter()
+            .filter(|(from, _amount)| *from > time)
+            .copied()
+            .collect()
+    }
+
+    /// Relock keeps all releases of this lock, except some first ones unlocked by given time.
+    pub fn relocked_by(&self, relock: &[(u64, U256)], time: u64) -> bool {
+        let Some(unlocked) = self.releases.len().checked_sub(relock.len()) else {
+            return false;
+        };
+
+        self.releases[unlocked..] == *relock
+            && self.releases[..unlocked]
+                .iter()
+                .all(|(from, _amount)| *from <= time)
+    }
+}
+
+/// Locks of outputs of a spend, stored in a chunk linked from the spend.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct LockRecord {
+    pub token_id: XorName,
+    /// Address of the spend.
+    pub spend: PublicKey,
+    pub locks: Vec<Lock>,
+}
+
+impl LockRecord {
+    /// Checks the record against its spend: releases are ordered and not empty,
+    /// and spend pays to each key at least what's locked to it.
+    pub fn check(&self, spend: &GraphEntry) -> Result<(), String> {
+        if self.spend != spend.owner || self.token_id.0 != spend.content {
+            return Err("Lock record of other spend.".into());
+        }
+
+        for lock in self.locks.iter() {
+            if lock.releases.is_empty()
+                || lock.releases.windows(2).any(|pair| pair[0].0 > pair[1].0)
+            {
+                return Err(format!("Wrong releases of {}.", lock.key.to_hex()));
+            }
+
+            let locked = self
+                .locks
+                .iter()
+                .filter(|other| other.key == lock.key)
+                .try_fold(U256::ZERO, |sum, other| {
+                    sum.checked_add(other.total().ok()?)
+                });
+            let paid = spend
+                .descendants
+                .iter()
+                .filter(|(key, _data)| *key == lock.key)
+                .try_fold(U256::ZERO, |sum, (_key, data)| {
+                    sum.checked_add(U256::from_be_bytes(*data))
+                });
+            match locked.zip(paid) {
+                Some((locked, paid)) if locked <= paid => (),
+                _ => return Err(format!("More locked than paid to {}.", lock.key.to_hex())),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Spend commits to a lock record.
+pub fn has_locks(spend: &GraphEntry) -> bool {
+    let link = lock_pk(&spend.owner);
+    spend.descendants.iter().any(|(key, _data)| *key == link)
+}
+
+/// Checks that an entry spends locked outputs of its parents only as they unlock by time `now`.
+/// Every parent's lock of an output to the entry, not unlocked yet, must be relocked by
+/// entry's own record, keeping its remaining releases.
+pub fn check_locks(
+    entry: &GraphEntry,
+    record: Option<&LockRecord>,
+    parent_records: &[LockRecord],
+    now: u64,
+) -> Result<(), String> {
+    let mut relocks: Vec<&Lock> = record
+        .map(|record| record.locks.iter().collect())
+        .unwrap_or_default();
+
+    for lock in parent_records
+        .iter()
+        .flat_map(|record| record.locks.iter())
+        .filter(|lock| lock.key == entry.owner)
+    {
+        if lock.locked(now).is_zero() {
+            continue;
+        }
+        match relocks
+            .iter()
+            .position(|relock| lock.relocked_by(&relock.releases, now))
+        {
+            Some(index) => {
+                relocks.swap_remove(index);
+            }
+            None => return Err(format!("Locked output spent in {}.", entry.owner.to_hex())),
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks locks of entry's parents, fetching lock records from the network.
+pub(crate) async fn check_entry_locks(
+    client: &Client,
+    entry: &GraphEntry,
+    parents: &[GraphEntry],
+    now: u64,
+) -> Result<(), String> {
+    let mut parent_records = Vec::new();
+    for parent in parents.iter().filter(|parent| has_locks(parent)) {
+        if let Some(record) = client.act_lock_record(parent).await? {
+            parent_records.push(record);
+        }
+    }
+    if !parent_records
+        .iter()
+        .flat_map(|record| record.locks.iter())
+        .any(|lock| lock.key == entry.owner)
+    {
+        return Ok(());
+    }
+
+    let record = client.act_lock_record(entry).await?;
+    check_locks(entry, record.as_ref(), &parent_records, now)
+}
+
+pub trait LockExt {
+    /// Stores lock record of a spend, linked from the spend's lock key. Must be done before
+    /// the spend is stored, paying zero to `lock_pk` of the spend.
+    fn act_lock_put(
+        &self,
+        spend_sk: &SecretKey,
+        record: &LockRecord,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+
+    /// Lock record the spend commits to, checked against the spend. `None` if it has no locks.
+    /// Fails if the record is missing, so that outputs of the spend stay locked.
+    fn act_lock_record(
+        &self,
+        spend: &GraphEntry,
+    ) -> impl Future<Output = Result<Option<LockRecord>, String>> + Send;
+}
+
+impl LockExt for Client {
+    async fn act_lock_put(
+        &self,
+        spend_sk: &SecretKey,
+        record: &LockRecord,
+        payment: &PaymentOption,
+    ) -> Result<(), String> {
+        if record.spend != spend_sk.public_key() {
+            return Err("Lock record of other spend.".into());
+        }
+
+        let bytes = rmp_serde::to_vec(record).map_err(|e| format!("{e}"))?;
+        let (_paid, chunk_address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Lock record Chunk: {}", chunk_address);
+
+        let link_sk = lock_sk(spend_sk);
+        let address = GraphEntryAddress::new(link_sk.public_key());
+        if self
+            .graph_entry_check_existence(&address)
+            .await
+            .map_err(|e| format!("{e}"))?
+        {
+            // stored by an earlier attempt to spend
+            let link = self
+                .graph_entry_get(&address)
+                .await
+                .map_err(|e| format!("{e}"))?;
+            return match link.content == chunk_address.xorname().0 {
+                true => Ok(()),
+                false => Err("Spend has other lock record already.".into()),
+            };
+        }
+
+        let link = GraphEntry::new(&link_sk, vec![], chunk_address.xorname().0, vec![]);
+        let (_paid, address) = self
+            .graph_entry_put(link, payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Lock record GraphEntry: {}", address);
+
+        Ok(())
+    }
+
+    async fn act_lock_record(&self, spend: &GraphEntry) -> Result<Option<LockRecord>, String> {
+        if !has_locks(spend) {
+            return Ok(None);
+        }
+
+        let link = self
+            .graph_entry_get(&GraphEntryAddress::new(lock_pk(&spend.owner)))
+            .await
+            .map_err(|e| format!("Lock record of {}: {e}", spend.owner.to_hex()))?;
+        let chunk = self
+            .chunk_get(&ChunkAddress::new(XorName(link.content)))
+            .await
+            .map_err(|e| format!("Lock record of {}: {e}", spend.owner.to_hex()))?;
+        let record: LockRecord =
+            rmp_serde::from_slice(chunk.value()).map_err(|e| format!("{e}"))?;
+        record.check(spend)?;
+
+        Ok(Some(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vesting() -> Result<(), String> {
+        let token_id = XorName::from_content(&[1u8]);
+        let payer = SecretKey::random();
+        let payee = SecretKey::random();
+        let lock = Lock {
+            key: payee.public_key(),
+            releases: vec![(1000, U256::from(30)), (2000, U256::from(70))],
+        };
+        assert_eq!(U256::from(100), lock.total()?);
+        assert_eq!(U256::from(100), lock.locked(999));
+        assert_eq!(U256::from(70), lock.locked(1000));
+        assert!(lock.locked(2000).is_zero());
+
+        let spend = GraphEntry::new(
+            &payer,
+            vec![SecretKey::random().public_key()],
+            token_id.0,
+            vec![
+                (payee.public_key(), U256::from(100).to_be_bytes()),
+                (lock_pk(&payer.public_key()), U256::ZERO.to_be_bytes()),
+            ],
+        );
+        assert!(has_locks(&spend));
+        let record = LockRecord {
+            token_id,
+            spend: spend.owner,
+            locks: vec![lock.clone()],
+        };
+        record.check(&spend)?;
+
+        let mut too_much = record.clone();
+        too_much.locks[0].releases.push((3000, U256::from(1)));
+        assert!(too_much.check(&spend).is_err());
+
+        // payee spends part unlocked at 1500, relocking the rest
+        let relock_key = SecretKey::random().public_key();
+        let spending = GraphEntry::new(
+            &payee,
+            vec![spend.owner],
+            token_id.0,
+            vec![
+                (
+                    SecretKey::random().public_key(),
+                    U256::from(30).to_be_bytes(),
+                ),
+                (relock_key, U256::from(70).to_be_bytes()),
+                (lock_pk(&payee.public_key()), U256::ZERO.to_be_bytes()),
+            ],
+        );
+        let relock = LockRecord {
+            token_id,
+            spend: spending.owner,
+            locks: vec![Lock {
+                key: relock_key,
+                releases: lock.remaining(1500),
+            }],
+        };
+        relock.check(&spending)?;
+
+        assert!(check_locks(&spending, None, &[record.clone()], 1500).is_err());
+        check_locks(&spending, None, &[record.clone()], 2000)?;
+        assert!(check_locks(&spending, Some(&relock), &[record.clone()], 999).is_err());
+        check_locks(&spending, Some(&relock), &[record.clone()], 1500)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use crate::cache::{ValidationCache, Verdict};
+use crate::history::now;
+use crate::inbox::open_slot_sk;
+use crate::validate::{fetch_entry, violated_rule, Rule};
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, SecretKey, Signature, XorName,
+};
+use futures::Future;
+use serde::{Deserialize, Serialize};
+
+/// How many times reporter tries next slot, when other reporter took the free one first.
+const MARK_ATTEMPTS: u64 = 5;
+/// Most marker slots read by a validation. Slots read are remembered in the validation cache,
+/// so each validation reads only new ones, and spam can't slow it down much.
+pub const MARKER_CHECKS: u64 = 16;
+
+/// Invalid markers of a token are numbered GraphEntries with keys derived from token ID,
+/// so anyone can publish and find them. Each points to a chunk with a signed marker.
+fn slot_sk(token_id: &XorName, slot: u64) -> Result<SecretKey, String> {
+    open_slot_sk(
+        &XorName::from_content(&[b"invalid".as_slice(), &token_id.0].concat()),
+        slot,
+    )
+}
+
+fn slot_address(token_id: &XorName, slot: u64) -> Result<GraphEntryAddress, String> {
+    Ok(GraphEntryAddress::new(
+        slot_sk(token_id, slot)?.public_key(),
+    ))
+}
+
+/// Claim that a GraphEntry violates a rule, so that descendants of it are invalid too.
+/// Anyone can publish one, so validators only use it as a hint and check the entry themselves.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Marker {
+    pub token_id: XorName,
+    /// Address of the invalid GraphEntry.
+    pub entry: PublicKey,
+    pub rule: Rule,
+    /// Key of who published the marker, signing it.
+    pub reporter: PublicKey,
+    /// Seconds since UNIX epoch.
+    pub created: u64,
+}
+
+impl Marker {
+    fn bytes_to_sign(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| format!("{e}"))
+    }
+}
+
+/// Marker with reporter's signature, as stored in a chunk.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct SignedMarker {
+    pub marker: Marker,
+    pub signature: Signature,
+}
+
+impl SignedMarker {
+    pub fn sign(marker: Marker, reporter_sk: &SecretKey) -> Result<Self, String> {
+        if reporter_sk.public_key() != marker.reporter {
+            return Err("Marker must be signed by the reporter.".into());
+        }
+
+        let signature = reporter_sk.sign(marker.bytes_to_sign()?);
+        Ok(Self { marker, signature })
+    }
+
+    pub fn verify(&self) -> bool {
+        self.marker
+            .bytes_to_sign()
+            .is_ok_and(|bytes| self.marker.reporter.verify(&self.signature, bytes))
+    }
+}
+
+async fn fetch_with_parents(
+    client: &Client,
+    cache: &mut ValidationCache,
+    owner: &PublicKey,
+) -> Result<(GraphEntry, Vec<GraphEntry>), String> {
+    let entry = fetch_entry(client, cache, owner).await?;
+    let mut parents = Vec::new();
+    for parent in entry.parents.iter() {
+        parents.push(fetch_entry(client, cache, parent).await?);
+    }
+
+    Ok((entry, parents))
+}
+
+pub trait MarkerExt {
+    /// Checks a GraphEntry and publishes a marker of the rule it violates.
+    /// Fails if the entry is valid.
+    fn act_mark_invalid(
+        &self,
+        entry: PublicKey,
+        reporter_sk: &SecretKey,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Marker, String>> + Send;
+
+    /// Markers of the spend, with unchecked claims that it is invalid, from marker slots
+    /// of its token from `slot` on, at most `max_slots` of them. Returns them with the first
+    /// slot not read.
+    fn act_spend_markers(
+        &self,
+        spend: PublicKey,
+        slot: u64,
+        max_slots: u64,
+    ) -> impl Future<Output = Result<(Vec<Marker>, u64), String>> + Send;
+
+    /// Reads markers of a token from given slot on, at most `max_slots` of them, skipping ones
+    /// not signed by their reporters. Returns them with the first slot not read.
+    fn act_markers_from(
+        &self,
+        token_id: &XorName,
+        slot: u64,
+        max_slots: u64,
+    ) -> impl Future<Output = Result<(Vec<Marker>, u64), String>> + Send;
+
+    /// Checks entries marked in slots of a token not read yet, at most `MARKER_CHECKS` slots,
+    /// and caches verdicts about them. Returns number of entries confirmed invalid.
+    fn act_check_markers(
+        &self,
+        token_id: &XorName,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<usize, String>> + Send;
+}
+
+impl MarkerExt for Client {
+    async fn act_mark_invalid(
+        &self,
+        entry: PublicKey,
+        reporter_sk: &SecretKey,
+        payment: &PaymentOption,
+    ) -> Result<Marker, String> {
+        let mut cache = ValidationCache::default();
+        let (invalid, parents) = fetch_with_parents(self, &mut cache, &entry).await?;
+        let rule = violated_rule(&invalid, &parents).ok_or("GraphEntry is valid.".to_string())?;
+
+        let token_id = XorName(invalid.content);
+        let signed = SignedMarker::sign(
+            Marker {
+                token_id,
+                entry,
+                rule,
+                reporter: reporter_sk.public_key(),
+                created: now(),
+            },
+            reporter_sk,
+        )?;
+        let bytes = rmp_serde::to_vec(&signed).map_err(|e| format!("{e}"))?;
+        let (_paid, chunk_address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Marker Chunk: {}", chunk_address);
+
+        let mut slot = 0;
+        while self
+            .graph_entry_check_existence(&slot_address(&token_id, slot)?)
+            .await
+            .map_err(|e| format!("{e}"))?
+        {
+            slot += 1;
+        }
+
+        let mut error = String::new();
+        for _ in 0..MARK_ATTEMPTS {
+            let entry = GraphEntry::new(
+                &slot_sk(&token_id, slot)?,
+                vec![],
+                chunk_address.xorname().0,
+                vec![],
+            );
+
+            match self.graph_entry_put(entry, payment.clone()).await {
+                Ok((_paid, address)) => {
+                    println!("Marker slot {}: {}", slot, address);
+                    return Ok(signed.marker);
+                }
+                Err(e) => {
+                    println!("Marker slot {} taken: {}", slot, e);
+                    error = format!("{e}");
+                    slot += 1;
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    async fn act_markers_from(
+        &self,
+        token_id: &XorName,
+        mut slot: u64,
+        max_slots: u64,
+    ) -> Result<(Vec<Marker>, u64), String> {
+        let mut markers = Vec::new();
+        let end = slot.saturating_add(max_slots);
+
+        while slot < end {
+            let address = slot_address(token_id, slot)?;
+            if !self
+                .graph_entry_check_existence(&address)
+                .await
+                .map_err(|e| format!("{e}"))?
+            {
+                break;
+            }
+            let entry = self
+                .graph_entry_get(&address)
+                .await
+                .map_err(|e| format!("{e}"))?;
+
+            match self
+                .chunk_get(&ChunkAddress::new(XorName(entry.content)))
+                .await
+                .map_err(|e| format!("{e}"))
+                .and_then(|chunk| {
+                    rmp_serde::from_slice::<SignedMarker>(chunk.value()).map_err(|e| format!("{e}"))
+                }) {
+                Ok(signed) if signed.verify() && signed.marker.token_id == *token_id => {
+                    markers.push(signed.marker)
+                }
+                Ok(_) => println!("Skipping marker slot {}: wrong signature", slot),
+                Err(e) => println!("Skipping marker slot {}: {}", slot, e),
+            }
+
+            slot += 1;
+        }
+
+        Ok((markers, slot))
+    }
+
+    async fn act_spend_markers(
+        &self,
+        spend: PublicKey,
+        slot: u64,
+        max_slots: u64,
+    ) -> Result<(Vec<Marker>, u64), String> {
+        let entry = self
+            .graph_entry_get(&GraphEntryAddress::new(spend))
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        let (markers, next_slot) = self
+            .act_markers_from(&XorName(entry.content), slot, max_slots)
+            .await?;
+
+        Ok((
+            markers
+                .into_iter()
+                .filter(|marker| marker.entry == spend)
+                .collect(),
+            next_slot,
+        ))
+    }
+
+    async fn act_check_markers(
+        &self,
+        token_id: &XorName,
+        cache: &mut ValidationCache,
+    ) -> Result<usize, String> {
+        let mut confirmed = 0;
+        let (markers, next_slot) = self
+            .act_markers_from(token_id, cache.marker_slot(token_id), MARKER_CHECKS)
+            .await?;
+
+        for marker in markers {
+            // a marker of missing entry must not stop reading the slots after it
+            let (entry, parents) = match fetch_with_parents(self, cache, &marker.entry).await {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    println!("Marked {} not fetched: {}", marker.entry.to_hex(), e);
+                    continue;
+                }
+            };
+            if XorName(entry.content) != *token_id || cache.verdict(&entry).is_some() {
+                continue;
+            }
+
+            match violated_rule(&entry, &parents) {
+                Some(rule) => {
+                    let e = format!("{} in {}.", rule.description(), entry.owner.to_hex());
+                    println!("Marker confirmed: {}", e);
+                    cache.set_verdict(&entry, Verdict::Invalid(e))?;
+                    confirmed += 1;
+                }
+                None => {
+                    println!("False marker of {}", entry.owner.to_hex());
+                    cache.set_verdict(&entry, Verdict::Checked)?;
+                }
+            }
+        }
+        cache.set_marker_slot(*token_id, next_slot);
+
+        Ok(confirmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_signature() -> Result<(), String> {
+        let reporter = SecretKey::random();
+        let token_id = XorName::from_content(&[1u8]);
+        let marker = Marker {
+            token_id,
+            entry: SecretKey::random().public_key(),
+            rule: Rule::InputsOutputs,
+            reporter: reporter.public_key(),
+            created: 1000,
+        };
+
+        assert!(SignedMarker::sign(marker.clone(), &SecretKey::random()).is_err());
+        let signed = SignedMarker::sign(marker, &reporter)?;
+        assert!(signed.verify());
+
+        let mut tampered = signed.clone();
+        tampered.marker.rule = Rule::Signature;
+        assert!(!tampered.verify());
+
+        // anyone can derive slot keys of a token
+        assert_eq!(slot_sk(&token_id, 0)?, slot_sk(&token_id, 0)?);
+        assert_ne!(
+            slot_sk(&token_id, 0)?,
+            slot_sk(&XorName::from_content(&[2u8]), 0)?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, SecretKey, XorName,
+};
+use blsttc::Ciphertext;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+
+/// Longest memo in bytes.
+pub const MAX_MEMO_LEN: usize = 1024;
+
+/// Memo to attach to a spend. Encrypted memo can be read only by the recipient.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Memo {
+    pub text: String,
+    pub encrypted: bool,
+}
+
+impl Memo {
+    pub fn new(text: &str, encrypted: bool) -> Result<Self, String> {
+        if text.len() > MAX_MEMO_LEN {
+            return Err(format!("Memo longer than {} bytes.", MAX_MEMO_LEN));
+        }
+
+        Ok(Self {
+            text: text.to_string(),
+            encrypted,
+        })
+    }
+}
+
+/// Content of memo chunk.
+#[derive(Serialize, Deserialize)]
+enum StoredMemo {
+    Plain(String),
+    /// Encrypted to the recipient's key.
+    Encrypted(Vec<u8>),
+}
+
+/// Memo is a GraphEntry with key derived from spend's key, so it can be found by anyone
+/// knowing the spend, but written only by the payer. Its content is address of a chunk with memo text.
+pub fn memo_address(spend: &PublicKey) -> GraphEntryAddress {
+    GraphEntryAddress::new(spend.derive_child(b"memo"))
+}
+
+/// Memo chunk of a spend, `None` if there's none.
+async fn stored_memo(client: &Client, spend: &PublicKey) -> Result<Option<StoredMemo>, String> {
+    let address = memo_address(spend);
+
+    if !client
+        .graph_entry_check_existence(&address)
+        .await
+        .map_err(|e| format!("{e}"))?
+    {
+        return Ok(None);
+    }
+
+    let entry = client
+        .graph_entry_get(&address)
+        .await
+        .map_err(|e| format!("{e}"))?;
+    let chunk = client
+        .chunk_get(&ChunkAddress::new(XorName(entry.content)))
+        .await
+        .map_err(|e| format!("{e}"))?;
+
+    rmp_serde::from_slice(chunk.value())
+        .map(Some)
+        .map_err(|e| format!("{e}"))
+}
+
+pub trait MemoExt {
+    /// Attaches memo to a spend owned by `spend_sk`, before the spend is stored, so it's there
+    /// when the spend is received. Encrypted memo is readable by `to` key owner.
+    fn act_memo_put(
+        &self,
+        spend_sk: &SecretKey,
+        memo: &Memo,
+        to: &PublicKey,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+
+    /// Reads memo of a spend, `None` if there's none. Encrypted memo needs `sk` of the recipient.
+    fn act_memo_get(
+        &self,
+        spend: &PublicKey,
+        sk: Option<&SecretKey>,
+    ) -> impl Future<Output = Result<Option<String>, String>> + Send;
+}
+
+impl MemoExt for Client {
+    async fn act_memo_put(
+        &self,
+        spend_sk: &SecretKey,
+        memo: &Memo,
+        to: &PublicKey,
+        payment: &PaymentOption,
+    ) -> Result<(), String> {
+        // stored by an earlier attempt to spend, encrypted one can't be compared
+        match stored_memo(self, &spend_sk.public_key()).await? {
+            None => (),
+            Some(StoredMemo::Plain(text)) if !memo.encrypted && text == memo.text => return Ok(()),
+            Some(StoredMemo::Encrypted(_)) if memo.encrypted => return Ok(()),
+            Some(_) => return Err("Spend has other memo already.".into()),
+        }
+
+        let stored = match memo.encrypted {
+            true => StoredMemo::Encrypted(to.encrypt(memo.text.as_bytes()).to_bytes()),
+            false => StoredMemo::Plain(memo.text.clone()),
+        };
+        let bytes = rmp_serde::to_vec(&stored).map_err(|e| format!("{e}"))?;
+
+        let (_paid, chunk_address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Memo Chunk: {}", chunk_address);
+
+        let entry = GraphEntry::new(
+            &spend_sk.derive_child(b"memo"),
+            vec![],
+            chunk_address.xorname().0,
+            vec![],
+        );
+        let (_paid, address) = self
+            .graph_entry_put(entry, payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Memo GraphEntry: {}", address);
+
+        Ok(())
+    }
+
+    async fn act_memo_get(
+        &self,
+        spend: &PublicKey,
+        sk: Option<&SecretKey>,
+    ) -> Result<Option<String>, String> {
+        match stored_memo(self, spend).await? {
+            None => Ok(None),
+            Some(StoredMemo::Plain(text)) => Ok(Some(text)),
+            Some(StoredMemo::Encrypted(bytes)) => {
+                let sk = sk.ok_or("Memo is encrypted.".to_string())?;
+                let ciphertext = Ciphertext::from_bytes(&bytes).map_err(|e| format!("{e}"))?;
+                let text = sk
+                    .decrypt(&ciphertext)
+                    .ok_or("Memo is encrypted to other key.".to_string())?;
+
+                String::from_utf8(text)
+                    .map(Some)
+                    .map_err(|e| format!("{e}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memo_length() {
+        assert!(Memo::new("Invoice 42", true).is_ok());
+        assert!(Memo::new(&"x".repeat(MAX_MEMO_LEN), false).is_ok());
+        assert!(Memo::new(&"x".repeat(MAX_MEMO_LEN + 1), false).is_err());
+
+        let spend = SecretKey::random();
+        assert_ne!(
+            GraphEntryAddress::new(spend.public_key()),
+            memo_address(&spend.public_key())
+        );
+        assert_eq!(
+            memo_address(&spend.public_key()),
+            GraphEntryAddress::new(spend.derive_child(b"memo").public_key())
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use crate::act::{genesis_outputs, ActExt};
+use crate::history::now;
+use autonomi::{
+    client::payment::PaymentOption, Client, GraphEntry, GraphEntryAddress, PublicKey, SecretKey,
+    XorName,
+};
+use futures::Future;
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Key authorizing mints of tokens created by a wallet.
+pub fn mint_pk(pk: &PublicKey) -> PublicKey {
+    pk.derive_child(b"mint")
+}
+
+pub fn mint_sk(sk: &SecretKey) -> SecretKey {
+    sk.derive_child(b"mint")
+}
+
+/// Mints of a token are numbered GraphEntries without parents, with keys derived from
+/// mint authority, so anyone can find them, but only the authority can create them.
+fn slot_index(token_id: &XorName, number: u64) -> Vec<u8> {
+    [b"mint".as_slice(), &token_id.0, &number.to_be_bytes()].concat()
+}
+
+fn slot_address(authority: &PublicKey, token_id: &XorName, number: u64) -> GraphEntryAddress {
+    GraphEntryAddress::new(authority.derive_child(&slot_index(token_id, number)))
+}
+
+/// Issuance after genesis, committed in TokenInfo, so it can't be changed later.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct MintPolicy {
+    /// Key whose holder can mint.
+    pub authority: PublicKey,
+    /// Most tokens minted after genesis, in total.
+    pub cap: U256,
+    /// Releases: from given time (seconds since UNIX epoch) on, given amount more can be
+    /// minted. Whole cap can be minted at once, if there's no schedule.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<(u64, U256)>,
+}
+
+impl MintPolicy {
+    /// Most tokens minted in total by given time.
+    pub fn allowed(&self, time: u64) -> U256 {
+        if self.schedule.is_empty() {
+            return self.cap;
+        }
+
+        self.schedule
+            .iter()
+            .filter(|(from, _amount)| *from <= time)
+            .fold(U256::ZERO, |sum, (_from, amount)| {
+                sum.saturating_add(*amount)
+            })
+            .min(self.cap)
+    }
+}
+
+/// Mint entries of a token, found in the network.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Mints {
+    /// Owners of all mint entries.
+    pub all: HashSet<PublicKey>,
+    /// Owners of mint entries within cap and schedule.
+    pub permitted: HashSet<PublicKey>,
+    /// Sum of permitted mints.
+    pub minted: U256,
+}
+
+/// Checks mint entries in order of their numbers. Mints are permitted until the first one
+/// that's malformed, or exceeds what the policy allows at time `now`, so an early mint
+/// becomes permitted when its time comes.
+pub fn permitted_mints(
+    policy: &MintPolicy,
+    token_id: &XorName,
+    mints: &[GraphEntry],
+    now: u64,
+) -> Mints {
+    let allowed = policy.allowed(now);
+    let mut checked = Mints {
+        all: mints.iter().map(|entry| entry.owner).collect(),
+        ..Mints::default()
+    };
+
+    for entry in mints {
+        if !entry.parents.is_empty() || entry.content != token_id.0 || !entry.verify_signature() {
+            println!("Malformed mint {}", entry.owner.to_hex());
+            break;
+        }
+        let minted = entry
+            .descendants
+            .iter()
+            .try_fold(checked.minted, |sum, (_key, data)| {
+                sum.checked_add(U256::from_be_bytes(*data))
+            });
+        match minted {
+            Some(minted) if minted <= allowed => {
+                checked.minted = minted;
+                checked.permitted.insert(entry.owner);
+            }
+            _ => {
+                println!("Mint {} exceeds policy", entry.owner.to_hex());
+                break;
+            }
+        }
+    }
+
+    checked
+}
+
+pub trait MintExt {
+    /// Creates next mint entry of a token, paying to given outputs. Fails if the token
+    /// has no minting policy, key isn't its authority, or policy doesn't allow the amount yet.
+    /// Returns address of the mint spend.
+    fn act_mint(
+        &self,
+        token_id: XorName,
+        authority_sk: &SecretKey,
+        outputs: Vec<(PublicKey, U256)>,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<PublicKey, String>> + Send;
+
+    /// Mint entries of a token, in order of their numbers.
+    fn act_mint_entries(
+        &self,
+        token_id: &XorName,
+        authority: &PublicKey,
+    ) -> impl Future<Output = Result<Vec<GraphEntry>, String>> + Send;
+
+    /// Mints of a token, checked against its policy. Empty if TokenInfo has no policy.
+    fn act_mints(&self, token_id: &XorName) -> impl Future<Output = Result<Mints, String>> + Send;
+}
+
+impl MintExt for Client {
+    async fn act_mint(
+        &self,
+        token_id: XorName,
+        authority_sk: &SecretKey,
+        outputs: Vec<(PublicKey, U256)>,
+        payment: &PaymentOption,
+    ) -> Result<PublicKey, String> {
+        let policy = self
+            .act_token_info(&token_id)
+            .await?
+            .mint
+            .ok_or("Token is not mintable.".to_string())?;
+        if authority_sk.public_key() != policy.authority {
+            return Err("Not the mint authority of this token.".into());
+        }
+
+        let amount = outputs
+            .iter()
+            .try_fold(U256::ZERO, |sum, (_key, amount)| sum.checked_add(*amount))
+            .ok_or("Overflow.".to_string())?;
+        let outputs = genesis_outputs(amount, &outputs)?;
+
+        let entries = self.act_mint_entries(&token_id, &policy.authority).await?;
+        let now = now();
+        let mints = permitted_mints(&policy, &token_id, &entries, now);
+        if mints.permitted.len() < entries.len() {
+            return Err("Earlier mint not permitted yet.".into());
+        }
+        let allowed = policy.allowed(now);
+        match mints.minted.checked_add(amount) {
+            Some(minted) if minted <= allowed => (),
+            _ => {
+                return Err(format!(
+                    "Minting policy allows {} more now.",
+                    allowed.saturating_sub(mints.minted)
+                ))
+            }
+        }
+
+        let number = entries.len() as u64;
+        let entry = GraphEntry::new(
+            &authority_sk.derive_child(&slot_index(&token_id, number)),
+            vec![],
+            token_id.0,
+            outputs,
+        );
+        let (_paid, address) = self
+            .graph_entry_put(entry, payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Mint {}: {}", number, address);
+
+        Ok(*address.owner())
+    }
+
+    async fn act_mint_entries(
+        &self,
+        token_id: &XorName,
+        authority: &PublicKey,
+    ) -> Result<Vec<GraphEntry>, String> {
+        let mut entries = Vec::new();
+
+        loop {
+            let address = slot_address(authority, token_id, entries.len() as u64);
+            if !self
+                .graph_entry_check_existence(&address)
+                .await
+                .map_err(|e| format!("{e}"))?
+            {
+                break;
+            }
+            entries.push(
+                self.graph_entry_get(&address)
+                    .await
+                    .map_err(|e| format!("{e}"))?,
+            );
+        }
+
+        Ok(entries)
+    }
+
+    async fn act_mints(&self, token_id: &XorName) -> Result<Mints, String> {
+        let Some(policy) = self.act_token_info(token_id).await?.mint else {
+            return Ok(Mints::default());
+        };
+
+        let entries = self.act_mint_entries(token_id, &policy.authority).await?;
+        Ok(permitted_mints(&policy, token_id, &entries, now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Roots;
+
+    #[test]
+    fn policy_and_mints() -> Result<(), String> {
+        let token_id = XorName::from_content(&[1u8]);
+        let authority = SecretKey::random();
+        let policy = MintPolicy {
+            authority: authority.public_key(),
+            cap: U256::from(100),
+            schedule: vec![(1000, U256::from(50)), (2000, U256::from(80))],
+        };
+        assert_eq!(U256::ZERO, policy.allowed(999));
+        assert_eq!(U256::from(50), policy.allowed(1000));
+        assert_eq!(U256::from(100), policy.allowed(2000));
+
+        let mint = |number: u64, amount: u64| {
+            GraphEntry::new(
+                &authority.derive_child(&slot_index(&token_id, number)),
+                vec![],
+                token_id.0,
+                vec![(
+                    SecretKey::random().public_key(),
+                    U256::from(amount).to_be_bytes(),
+                )],
+            )
+        };
+        let entries = vec![mint(0, 40), mint(1, 40)];
+
+        let early = permitted_mints(&policy, &token_id, &entries, 1500);
+        assert_eq!(U256::from(40), early.minted);
+        assert_eq!(2, early.all.len());
+        assert_eq!(HashSet::from([entries[0].owner]), early.permitted);
+
+        let later = permitted_mints(&policy, &token_id, &entries, 2000);
+        assert_eq!(U256::from(80), later.minted);
+        assert_eq!(
+            HashSet::from([entries[0].owner, entries[1].owner]),
+            later.permitted
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cap_not_bypassed() -> Result<(), String> {
+        let token_id = XorName::from_content(&[1u8]);
+        let authority = SecretKey::random();
+        let policy = MintPolicy {
+            authority: authority.public_key(),
+            cap: U256::from(100),
+            schedule: vec![],
+        };
+        let over_cap = GraphEntry::new(
+            &authority.derive_child(&slot_index(&token_id, 0)),
+            vec![],
+            token_id.0,
+            vec![(
+                SecretKey::random().public_key(),
+                U256::from(101).to_be_bytes(),
+            )],
+        );
+        let roots = Roots {
+            genesis: Some(SecretKey::random().public_key()),
+            total_supply: Some(U256::from(1000)),
+            mints: permitted_mints(&policy, &token_id, &[over_cap.clone()], 0),
+        };
+        assert!(roots.mints.permitted.is_empty());
+        assert!(roots.check_root(&over_cap).is_err());
+
+        // entry without parents outside of mint slots, even by the authority
+        let outside = GraphEntry::new(
+            &authority,
+            vec![],
+            token_id.0,
+            vec![(
+                SecretKey::random().public_key(),
+                U256::from(1000).to_be_bytes(),
+            )],
+        );
+        assert!(roots.check_root(&outside).is_err());
+
+        Ok(())
+    }
+}
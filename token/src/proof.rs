@@ -0,0 +1,382 @@
+use crate::cache::{ValidationCache, Verdict};
+use crate::history::now;
+use crate::lock::check_entry_locks;
+use crate::marker::MarkerExt;
+use crate::validate::{check_entry, Roots, ValidateExt, Validation, MAX_VALIDATION_BUDGET};
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, XorName,
+};
+use futures::Future;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Version of proof bundle format.
+pub const PROOF_VERSION: u8 = 1;
+/// How many randomly chosen ancestors are compared with the network, besides spend and genesis.
+pub const SPOT_CHECKS: usize = 4;
+
+/// Spend together with its ancestors, so the recipient can validate it offline,
+/// instead of fetching the ancestors one by one.
+///
+/// Serialized with MessagePack as an array `[version, spend, ancestors]`, where GraphEntries
+/// are serialized as in autonomi. Ancestors are in breadth-first order from the spend;
+/// if there are too many, only the nearest ones are included.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub version: u8,
+    pub spend: GraphEntry,
+    pub ancestors: Vec<GraphEntry>,
+}
+
+impl ProofBundle {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| format!("{e}"))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        rmp_serde::from_slice(bytes).map_err(|e| format!("{e}"))
+    }
+
+    pub fn to_hex(&self) -> Result<String, String> {
+        self.to_bytes().map(hex::encode)
+    }
+
+    pub fn from_hex(bundle: &str) -> Result<Self, String> {
+        Self::from_bytes(&hex::decode(bundle.trim()).map_err(|e| format!("{e}"))?)
+    }
+
+    /// Validates the spend with ancestors from the bundle, without looking into the network.
+    /// It's complete, if all paths lead to token's roots within the bundle.
+    pub fn verify(&self, roots: &Roots) -> Result<Validation, String> {
+        if self.version != PROOF_VERSION {
+            return Err(format!("Unknown proof bundle version {}.", self.version));
+        }
+
+        let mut entries = HashMap::<PublicKey, &GraphEntry>::new();
+        for entry in self.ancestors.iter() {
+            if entries.insert(entry.owner, entry).is_some() {
+                return Err("Ancestor included twice.".into());
+            }
+        }
+
+        let mut visited = HashSet::from([self.spend.owner]);
+        let mut to_check = VecDeque::from([&self.spend]);
+        let mut checked = 0;
+        let mut complete = true;
+
+        while let Some(entry) = to_check.pop_front() {
+            if entry.content != self.spend.content {
+                return Err(format!(
+                    "Ancestor of other token: {}.",
+                    entry.owner.to_hex()
+                ));
+            }
+
+            let parents: Option<Vec<GraphEntry>> = entry
+                .parents
+                .iter()
+                .map(|parent| entries.get(parent).map(|parent| (*parent).clone()))
+                .collect();
+            let Some(parents) = parents else {
+                complete = false; // path continues outside of the bundle
+                continue;
+            };
+
+            check_entry(entry, &parents)?;
+            checked += 1;
+
+            if parents.is_empty() && !roots.confirms_root(entry)? {
+                complete = false; // token info doesn't commit to genesis
+            }
+
+            for parent in entry.parents.iter() {
+                if visited.insert(*parent) {
+                    to_check.push_back(entries[parent]);
+                }
+            }
+        }
+
+        Ok(Validation { checked, complete })
+    }
+}
+
+pub trait ProofExt {
+    /// Collects spend's ancestors breadth-first, at most `max_ancestors` of them.
+    fn act_proof_bundle(
+        &self,
+        spend: &GraphEntry,
+        max_ancestors: usize,
+    ) -> impl Future<Output = Result<ProofBundle, String>> + Send;
+
+    /// Stores proof bundle in a chunk. Returns its address.
+    fn act_proof_put(
+        &self,
+        bundle: &ProofBundle,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<XorName, String>> + Send;
+
+    fn act_proof_get(
+        &self,
+        address: &XorName,
+    ) -> impl Future<Output = Result<ProofBundle, String>> + Send;
+
+    /// Assembles spend's proof bundle, with as many ancestors as a payee would check
+    /// at most, and stores it. Returns its address, to be sent with the payment.
+    fn act_proof_publish(
+        &self,
+        spend: &GraphEntry,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<XorName, String>> + Send;
+
+    /// Verifies the bundle offline against token's roots, then compares the spend, roots and
+    /// `samples` randomly chosen ancestors with GraphEntries stored in the network. Locks of
+    /// bundled entries are checked with lock records from the network. Fails if any bundled
+    /// entry is known invalid from the cache, after checking token's invalid markers.
+    fn act_verify_bundle(
+        &self,
+        bundle: &ProofBundle,
+        samples: usize,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<Validation, String>> + Send;
+}
+
+impl ProofExt for Client {
+    async fn act_proof_bundle(
+        &self,
+        spend: &GraphEntry,
+        max_ancestors: usize,
+    ) -> Result<ProofBundle, String> {
+        let mut visited = HashSet::from([spend.owner]);
+        let mut to_fetch: VecDeque<PublicKey> = spend.parents.iter().copied().collect();
+        visited.extend(spend.parents.iter().copied());
+        let mut ancestors = Vec::new();
+
+        while let Some(address) = to_fetch.pop_front() {
+            if ancestors.len() >= max_ancestors {
+                break;
+            }
+
+            let entry = self
+                .graph_entry_get(&GraphEntryAddress::new(address))
+                .await
+                .map_err(|e| format!("{e}"))?;
+
+            for parent in entry.parents.iter() {
+                if visited.insert(*parent) {
+                    to_fetch.push_back(*parent);
+                }
+            }
+            ancestors.push(entry);
+        }
+
+        Ok(ProofBundle {
+            version: PROOF_VERSION,
+            spend: spend.clone(),
+            ancestors,
+        })
+    }
+
+    async fn act_proof_put(
+        &self,
+        bundle: &ProofBundle,
+        payment: &PaymentOption,
+    ) -> Result<XorName, String> {
+        let (_paid, address) = self
+            .chunk_put(
+                &Chunk::new(Bytes::from(bundle.to_bytes()?)),
+                payment.clone(),
+            )
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Proof bundle Chunk: {}", address);
+
+        Ok(*address.xorname())
+    }
+
+    async fn act_proof_get(&self, address: &XorName) -> Result<ProofBundle, String> {
+        let chunk = self
+            .chunk_get(&ChunkAddress::new(*address))
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        ProofBundle::from_bytes(chunk.value())
+    }
+
+    async fn act_proof_publish(
+        &self,
+        spend: &GraphEntry,
+        payment: &PaymentOption,
+    ) -> Result<XorName, String> {
+        let bundle = self.act_proof_bundle(spend, MAX_VALIDATION_BUDGET).await?;
+        println!("Proof bundle of {} ancestors", bundle.ancestors.len());
+
+        self.act_proof_put(&bundle, payment).await
+    }
+
+    async fn act_verify_bundle(
+        &self,
+        bundle: &ProofBundle,
+        samples: usize,
+        cache: &mut ValidationCache,
+    ) -> Result<Validation, String> {
+        let token_id = XorName(bundle.spend.content);
+        let roots = self.act_roots(&token_id).await?;
+        let validation = bundle.verify(&roots)?;
+
+        // markers are only hints, marked entries get invalid verdict if they really are
+        if let Err(e) = self.act_check_markers(&token_id, cache).await {
+            println!("Invalid markers of {:x}: {}", token_id, e);
+        }
+        for entry in std::iter::once(&bundle.spend).chain(bundle.ancestors.iter()) {
+            if let Some(Verdict::Invalid(e)) = cache.verdict(entry) {
+                return Err(e);
+            }
+        }
+
+        // roots are always compared with the network, besides their check in `verify`
+        let mut spot_checks: Vec<&GraphEntry> = vec![&bundle.spend];
+        spot_checks.extend(
+            bundle
+                .ancestors
+                .iter()
+                .filter(|entry| entry.parents.is_empty()),
+        );
+
+        let others: Vec<&GraphEntry> = bundle
+            .ancestors
+            .iter()
+            .filter(|entry| !entry.parents.is_empty())
+            .collect();
+        spot_checks.extend(
+            others
+                .choose_multiple(&mut rand::thread_rng(), samples)
+                .copied(),
+        );
+
+        for entry in spot_checks {
+            let stored = self
+                .graph_entry_get(&GraphEntryAddress::new(entry.owner))
+                .await
+                .map_err(|e| format!("{e}"))?;
+            if stored != *entry {
+                return Err(format!(
+                    "Bundled {} differs from the network.",
+                    entry.owner.to_hex()
+                ));
+            }
+        }
+
+        // locks depend on time, so they are checked here, not in `verify`
+        let bundled: HashMap<PublicKey, &GraphEntry> = bundle
+            .ancestors
+            .iter()
+            .map(|entry| (entry.owner, entry))
+            .collect();
+        for entry in std::iter::once(&bundle.spend).chain(bundle.ancestors.iter()) {
+            let parents: Option<Vec<GraphEntry>> = entry
+                .parents
+                .iter()
+                .map(|parent| bundled.get(parent).map(|parent| (*parent).clone()))
+                .collect();
+            if let Some(parents) = parents {
+                check_entry_locks(self, entry, &parents, now()).await?;
+            }
+        }
+
+        Ok(validation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+    use ruint::aliases::U256;
+
+    #[test]
+    fn bundle_verification() -> Result<(), String> {
+        let token_id = XorName::from_content(&[1u8]);
+        let issuer = SecretKey::random();
+        let payer = SecretKey::random();
+        let payee = SecretKey::random().public_key();
+
+        let genesis = GraphEntry::new(
+            &SecretKey::random(),
+            vec![],
+            token_id.0,
+            vec![(issuer.public_key(), U256::from(100).to_be_bytes())],
+        );
+        let first = GraphEntry::new(
+            &issuer,
+            vec![genesis.owner],
+            token_id.0,
+            vec![
+                (payer.public_key(), U256::from(40).to_be_bytes()),
+                (
+                    SecretKey::random().public_key(),
+                    U256::from(60).to_be_bytes(),
+                ),
+            ],
+        );
+        let spend = GraphEntry::new(
+            &payer,
+            vec![first.owner],
+            token_id.0,
+            vec![(payee, U256::from(40).to_be_bytes())],
+        );
+
+        let roots = Roots {
+            genesis: Some(genesis.owner),
+            total_supply: Some(U256::from(100)),
+            ..Roots::default()
+        };
+        let bundle = ProofBundle {
+            version: PROOF_VERSION,
+            spend: spend.clone(),
+            ancestors: vec![first.clone(), genesis.clone()],
+        };
+        assert_eq!(
+            Validation {
+                checked: 3,
+                complete: true
+            },
+            bundle.verify(&roots)?
+        );
+        assert_eq!(bundle, ProofBundle::from_hex(&bundle.to_hex()?)?);
+
+        // forged genesis, not committed in token info
+        assert!(bundle.verify(&Roots::default()).is_err());
+
+        let partial = ProofBundle {
+            ancestors: vec![first.clone()],
+            ..bundle.clone()
+        };
+        assert_eq!(
+            Validation {
+                checked: 1,
+                complete: false
+            },
+            partial.verify(&roots)?
+        );
+
+        let mut tampered = bundle.clone();
+        tampered.ancestors[0].descendants[0].1 = U256::from(50).to_be_bytes();
+        assert!(tampered.verify(&roots).is_err());
+
+        let twice = ProofBundle {
+            ancestors: vec![first.clone(), genesis.clone(), genesis],
+            ..bundle.clone()
+        };
+        assert!(twice.verify(&roots).is_err());
+
+        let unknown = ProofBundle {
+            version: PROOF_VERSION + 1,
+            ..bundle
+        };
+        assert!(unknown.verify(&roots).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use autonomi::{GraphEntry, PublicKey, SecretKey, Signature, XorName};
+use blsttc::{SecretKeyShare, SignatureShare};
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+
+/// Spend prepared by a wallet, waiting to be signed by holder(s) of the spending key.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct UnsignedSpend {
+    pub token_id: XorName,
+    /// Index of the spending key, derived from wallet's master key.
+    pub index: U256,
+    /// Spending key, address of the spend.
+    pub owner: PublicKey,
+    pub parents: Vec<PublicKey>,
+    /// Payments, including change back to the wallet.
+    pub outputs: Vec<(PublicKey, U256)>,
+    /// Index of the key receiving change, so the signing copy of the wallet finds it,
+    /// however many keys it has derived. `None` in spends prepared before it was recorded.
+    #[serde(default)]
+    pub rest_index: Option<U256>,
+}
+
+impl UnsignedSpend {
+    pub fn descendants(&self) -> Vec<(PublicKey, [u8; 32])> {
+        self.outputs
+            .iter()
+            .map(|(key, amount)| (*key, amount.to_be_bytes()))
+            .collect()
+    }
+
+    /// What the spending key signs, as in `GraphEntry`.
+    pub fn bytes_to_sign(&self) -> Vec<u8> {
+        GraphEntry::bytes_to_sign(
+            &self.owner,
+            &self.parents,
+            &self.token_id.0,
+            &self.descendants(),
+        )
+    }
+
+    /// Signs with share of wallet's master key, deriving share of the spending key.
+    pub fn sign_share(&self, master_share: &SecretKeyShare) -> SignatureShare {
+        master_share
+            .derive_child(&self.index.to_be_bytes::<32>())
+            .sign(self.bytes_to_sign())
+    }
+
+    /// Signs with wallet's master key, deriving the spending key, eg. offline.
+    pub fn sign(&self, master_sk: &SecretKey) -> Result<GraphEntry, String> {
+        let spend_sk = master_sk.derive_child(&self.index.to_be_bytes::<32>());
+        if spend_sk.public_key() != self.owner {
+            return Err("Spend of other wallet.".into());
+        }
+
+        self.with_signature(spend_sk.sign(self.bytes_to_sign()))
+    }
+
+    /// The spend, if signature is right.
+    pub fn with_signature(&self, signature: Signature) -> Result<GraphEntry, String> {
+        let spend = GraphEntry::new_with_signature(
+            self.owner,
+            self.parents.clone(),
+            self.token_id.0,
+            self.descendants(),
+            signature,
+        );
+
+        match spend.verify_signature() {
+            true => Ok(spend),
+            false => Err("Wrong spend signature.".into()),
+        }
+    }
+
+    /// Unsigned spend exported for signing elsewhere.
+    pub fn to_hex(&self) -> Result<String, String> {
+        self.to_bytes().map(hex::encode)
+    }
+
+    pub fn from_hex(unsigned: &str) -> Result<Self, String> {
+        Self::from_bytes(&hex::decode(unsigned.trim()).map_err(|e| format!("{e}"))?)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| format!("{e}"))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        rmp_serde::from_slice(bytes).map_err(|e| format!("{e}"))
+    }
+}
+
+/// Signed spend exported for publishing elsewhere.
+pub fn signed_to_hex(spend: &GraphEntry) -> Result<String, String> {
+    rmp_serde::to_vec(spend)
+        .map(hex::encode)
+        .map_err(|e| format!("{e}"))
+}
+
+pub fn signed_from_hex(signed: &str) -> Result<GraphEntry, String> {
+    let bytes = hex::decode(signed.trim()).map_err(|e| format!("{e}"))?;
+    rmp_serde::from_slice(&bytes).map_err(|e| format!("{e}"))
+}
@@ -0,0 +1,173 @@
+use crate::act::ActExt;
+use crate::uri::PaymentRequest;
+use autonomi::{Client, GraphEntry, GraphEntryAddress, PublicKey, XorName};
+use futures::Future;
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+
+/// Proof of payment, which payer can show to anyone. It's the spend together with
+/// pointer to the output paying the payee, and optionally the payment request it fulfilled.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Receipt {
+    pub spend: GraphEntry,
+    /// Index of payee's output in spend's descendants.
+    pub output: u32,
+    pub token_id: XorName,
+    pub amount: U256,
+    /// `act:` URI of the payment request.
+    pub request: Option<String>,
+}
+
+impl Receipt {
+    pub fn new(spend: GraphEntry, output: u32) -> Result<Self, String> {
+        let (_payee, data) = spend
+            .descendants
+            .get(output as usize)
+            .ok_or("No such output in the spend.".to_string())?;
+
+        Ok(Self {
+            token_id: XorName(spend.content),
+            amount: U256::from_be_bytes(*data),
+            spend,
+            output,
+            request: None,
+        })
+    }
+
+    pub fn payee(&self) -> Result<PublicKey, String> {
+        self.spend
+            .descendants
+            .get(self.output as usize)
+            .map(|(payee, _data)| *payee)
+            .ok_or("No such output in the spend.".to_string())
+    }
+
+    /// Payee is the spend's own key, so the receipt is a proof of burn.
+    pub fn is_burn(&self) -> Result<bool, String> {
+        Ok(self.payee()? == self.spend.owner)
+    }
+
+    pub fn to_hex(&self) -> Result<String, String> {
+        rmp_serde::to_vec(self)
+            .map(hex::encode)
+            .map_err(|e| format!("{e}"))
+    }
+
+    pub fn from_hex(receipt: &str) -> Result<Self, String> {
+        let bytes = hex::decode(receipt.trim()).map_err(|e| format!("{e}"))?;
+        rmp_serde::from_slice(&bytes).map_err(|e| format!("{e}"))
+    }
+
+    /// Checks consistency of the receipt, without looking into the network.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.spend.verify_signature() {
+            return Err("Wrong spend signature.".into());
+        }
+        if XorName(self.spend.content) != self.token_id {
+            return Err("Spend of other token.".into());
+        }
+
+        let expected = Self::new(self.spend.clone(), self.output)?;
+        if expected.amount != self.amount {
+            return Err("Amount differs from the spend.".into());
+        }
+
+        if let Some(uri) = &self.request {
+            let request: PaymentRequest = uri.parse()?;
+            if request.key != self.payee()? {
+                return Err("Payment request was for other key.".into());
+            }
+            if request.token_id != self.token_id {
+                return Err("Payment request was for other token.".into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub trait ReceiptExt {
+    /// Checks the receipt, that its spend is stored in the network, and that requested amount was paid.
+    fn act_verify_receipt(
+        &self,
+        receipt: &Receipt,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+impl ReceiptExt for Client {
+    async fn act_verify_receipt(&self, receipt: &Receipt) -> Result<(), String> {
+        receipt.check()?;
+
+        let stored = self
+            .graph_entry_get(&GraphEntryAddress::new(receipt.spend.owner))
+            .await
+            .map_err(|e| format!("{e}"))?;
+        if stored != receipt.spend {
+            return Err("Spend differs from the one stored in the network.".into());
+        }
+
+        if let Some(uri) = &receipt.request {
+            let request: PaymentRequest = uri.parse()?;
+            let decimals = self.act_token_info(&receipt.token_id).await?.decimals;
+
+            if request
+                .amount_units(decimals)?
+                .is_some_and(|requested| receipt.amount < requested)
+            {
+                return Err("Paid less than requested.".into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+
+    #[test]
+    fn receipt_check() -> Result<(), String> {
+        let payer = SecretKey::random();
+        let payee = SecretKey::random().public_key();
+        let token_id = XorName::from_content(&[1u8]);
+
+        let spend = GraphEntry::new(
+            &payer,
+            vec![SecretKey::random().public_key()],
+            token_id.0,
+            vec![
+                (payee, U256::from(10).to_be_bytes()),
+                (
+                    SecretKey::random().public_key(),
+                    U256::from(5).to_be_bytes(),
+                ),
+            ],
+        );
+
+        let mut receipt = Receipt::new(spend.clone(), 0)?;
+        assert_eq!(U256::from(10), receipt.amount);
+        assert_eq!(payee, receipt.payee()?);
+        assert!(Receipt::new(spend, 2).is_err());
+
+        receipt.request = Some(PaymentRequest::new(token_id, payee).to_string());
+        receipt.check()?;
+        assert_eq!(receipt, Receipt::from_hex(&receipt.to_hex()?)?);
+
+        let mut wrong = receipt.clone();
+        wrong.amount = U256::from(15);
+        assert!(wrong.check().is_err());
+
+        let mut wrong = receipt.clone();
+        wrong.output = 1;
+        wrong.amount = U256::from(5);
+        assert!(wrong.check().is_err()); // request for other key
+
+        let mut wrong = receipt.clone();
+        wrong.spend.descendants[0].1 = U256::from(15).to_be_bytes();
+        assert!(wrong.check().is_err()); // signature
+
+        Ok(())
+    }
+}
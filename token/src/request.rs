@@ -0,0 +1,174 @@
+use crate::history::now;
+use autonomi::{PublicKey, XorName};
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+
+/// Where funds received with a request's key are.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Funds {
+    /// Kept at the request key, moved to token's main key before spending.
+    Separate,
+    /// Request key became token's main key, so funds are part of the main balance.
+    Main,
+    /// Moved to token's main key with this spend. Request key can't receive anymore.
+    Consolidated(PublicKey),
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum RequestStatus {
+    Open,
+    Fulfilled,
+    Expired,
+}
+
+/// Payment request with its own key, derived from its index.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// `None` if not known yet, eg. when creating a token.
+    pub token_id: Option<XorName>,
+    /// Expected amount, any amount is accepted if `None`.
+    pub amount: Option<U256>,
+    /// Seconds since UNIX epoch.
+    pub expiry: Option<u64>,
+    pub created: u64,
+    /// Received spends with amounts.
+    pub payments: Vec<(PublicKey, U256)>,
+    pub funds: Funds,
+}
+
+impl Request {
+    pub fn new(token_id: Option<XorName>, amount: Option<U256>, expiry: Option<u64>) -> Self {
+        Self {
+            token_id,
+            amount,
+            expiry,
+            created: now(),
+            payments: Vec::new(),
+            funds: Funds::Separate,
+        }
+    }
+
+    /// Sum of all payments.
+    pub fn received(&self) -> Result<U256, String> {
+        self.payments
+            .iter()
+            .try_fold(U256::ZERO, |sum, (_spend, amount)| {
+                sum.checked_add(*amount).ok_or("Overflow.".to_string())
+            })
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.is_some_and(|expiry| now >= expiry)
+    }
+
+    /// Fulfilled request stays so, even if paid late.
+    pub fn status(&self, now: u64) -> Result<RequestStatus, String> {
+        let received = self.received()?;
+        let fulfilled = match self.amount {
+            Some(amount) => received >= amount,
+            None => !self.payments.is_empty(),
+        };
+
+        Ok(if fulfilled {
+            RequestStatus::Fulfilled
+        } else if self.is_expired(now) {
+            RequestStatus::Expired
+        } else {
+            RequestStatus::Open
+        })
+    }
+}
+
+/// How a received payment matches its request.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RequestMatch {
+    /// Received for the request so far, including the payment.
+    pub received: U256,
+    pub requested: Option<U256>,
+    /// Payment came after the request expired.
+    pub late: bool,
+}
+
+impl RequestMatch {
+    /// Amount still missing.
+    pub fn underpaid(&self) -> Option<U256> {
+        self.requested
+            .and_then(|requested| requested.checked_sub(self.received))
+            .filter(|missing| *missing > U256::ZERO)
+    }
+
+    /// Amount paid over the requested one.
+    pub fn overpaid(&self) -> Option<U256> {
+        self.requested
+            .and_then(|requested| self.received.checked_sub(requested))
+            .filter(|excess| *excess > U256::ZERO)
+    }
+}
+
+/// Payment credited to a wallet.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Received {
+    pub token_id: XorName,
+    pub amount: U256,
+    /// Wallet's key the payment was made to.
+    pub key: PublicKey,
+    /// Address of the spend GraphEntry.
+    pub spend: PublicKey,
+    /// `None` if paid to token's main key, not to a request's own key.
+    pub request: Option<RequestMatch>,
+    /// Spend's ancestry has been fully validated.
+    pub verified: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+
+    #[test]
+    fn request_status() -> Result<(), String> {
+        let mut request = Request::new(None, Some(U256::from(10)), Some(2000));
+        assert_eq!(RequestStatus::Open, request.status(1000)?);
+        assert_eq!(RequestStatus::Expired, request.status(2000)?);
+
+        request
+            .payments
+            .push((SecretKey::random().public_key(), U256::from(4)));
+        assert_eq!(RequestStatus::Open, request.status(1000)?);
+
+        request
+            .payments
+            .push((SecretKey::random().public_key(), U256::from(6)));
+        assert_eq!(RequestStatus::Fulfilled, request.status(3000)?);
+
+        let any_amount = Request::new(None, None, None);
+        assert_eq!(RequestStatus::Open, any_amount.status(u64::MAX)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn request_match() {
+        let m = RequestMatch {
+            received: U256::from(4),
+            requested: Some(U256::from(10)),
+            late: false,
+        };
+        assert_eq!(Some(U256::from(6)), m.underpaid());
+        assert_eq!(None, m.overpaid());
+
+        let m = RequestMatch {
+            received: U256::from(12),
+            ..m
+        };
+        assert_eq!(None, m.underpaid());
+        assert_eq!(Some(U256::from(2)), m.overpaid());
+
+        let m = RequestMatch {
+            requested: None,
+            ..m
+        };
+        assert_eq!(None, m.underpaid());
+        assert_eq!(None, m.overpaid());
+    }
+}
@@ -0,0 +1,407 @@
+use crate::proposal::UnsignedSpend;
+use autonomi::{
+    client::payment::PaymentOption, Bytes, Chunk, ChunkAddress, Client, GraphEntry,
+    GraphEntryAddress, PublicKey, SecretKey, XorName,
+};
+use blsttc::{Ciphertext, PublicKeySet, SecretKeySet, SecretKeyShare, SignatureShare};
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Key of a wallet as a member of treasuries.
+pub fn treasury_member_pk(pk: &PublicKey) -> PublicKey {
+    pk.derive_child(b"treasury")
+}
+
+pub fn treasury_member_sk(sk: &SecretKey) -> SecretKey {
+    sk.derive_child(b"treasury")
+}
+
+/// Key of member's approval of a proposal.
+fn approval_index(proposal: &XorName) -> Vec<u8> {
+    [b"approve".as_slice(), &proposal.0].concat()
+}
+
+/// Wallet controlled by m of n members: its master key is the public key of a threshold
+/// key set, so spending keys derived from it sign with m signature shares combined.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Treasury {
+    pub name: String,
+    pub keys: PublicKeySet,
+    /// Member keys, in order of their shares.
+    pub members: Vec<PublicKey>,
+    /// Secret key shares, each encrypted to its member.
+    pub shares: Vec<Ciphertext>,
+}
+
+impl Treasury {
+    /// Creates key set of a treasury needing `threshold` of `members` to spend. Secret key set
+    /// is dropped after dealing its shares, so no one holds the whole key.
+    pub fn deal(name: String, threshold: usize, members: Vec<PublicKey>) -> Result<Self, String> {
+        if threshold == 0 || threshold > members.len() {
+            return Err(format!(
+                "Threshold must be from 1 to {} members.",
+                members.len()
+            ));
+        }
+        if (1..members.len()).any(|i| members[..i].contains(&members[i])) {
+            return Err("Member listed twice.".into());
+        }
+
+        let set = SecretKeySet::random(threshold - 1, &mut rand::thread_rng());
+        let shares = members
+            .iter()
+            .enumerate()
+            .map(|(i, member)| member.encrypt(set.secret_key_share(i).to_bytes()))
+            .collect();
+
+        Ok(Self {
+            name,
+            keys: set.public_keys(),
+            members,
+            shares,
+        })
+    }
+
+    /// Master key of treasury's wallet.
+    pub fn pk(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    /// Approvals needed to spend.
+    pub fn threshold(&self) -> usize {
+        self.keys.threshold() + 1
+    }
+
+    /// Index and key share of a member.
+    pub fn share(&self, member_sk: &SecretKey) -> Result<(usize, SecretKeyShare), String> {
+        let index = self
+            .members
+            .iter()
+            .position(|member| *member == member_sk.public_key())
+            .ok_or("Not a member of this treasury.".to_string())?;
+
+        let bytes: [u8; 32] = self
+            .shares
+            .get(index)
+            .and_then(|share| member_sk.decrypt(share))
+            .ok_or("Key share can't be decrypted.".to_string())?
+            .try_into()
+            .map_err(|_| "Wrong key share length.".to_string())?;
+        let share = SecretKeyShare::from_bytes(bytes).map_err(|e| format!("{e}"))?;
+        if share.public_key_share() != self.keys.public_key_share(index) {
+            return Err("Key share doesn't belong to this treasury.".into());
+        }
+
+        Ok((index, share))
+    }
+
+    /// Checks that the spend is made by one of treasury's keys.
+    pub fn check_proposal(&self, proposal: &UnsignedSpend) -> Result<(), String> {
+        match self.pk().derive_child(&proposal.index.to_be_bytes::<32>()) == proposal.owner {
+            true => Ok(()),
+            false => Err("Proposal spends from other wallet.".into()),
+        }
+    }
+
+    /// Whether `share` is member `index`'s signature of the proposal.
+    pub fn verify_share(
+        &self,
+        proposal: &UnsignedSpend,
+        index: usize,
+        share: &SignatureShare,
+    ) -> bool {
+        self.keys
+            .derive_child(&proposal.index.to_be_bytes::<32>())
+            .public_key_share(index)
+            .verify(share, proposal.bytes_to_sign())
+    }
+
+    /// The spend signed by combined shares, verified ones only.
+    pub fn combine(
+        &self,
+        proposal: &UnsignedSpend,
+        shares: &BTreeMap<usize, SignatureShare>,
+    ) -> Result<GraphEntry, String> {
+        self.check_proposal(proposal)?;
+        let valid: BTreeMap<usize, &SignatureShare> = shares
+            .iter()
+            .filter(|(index, share)| self.verify_share(proposal, **index, share))
+            .map(|(index, share)| (*index, share))
+            .collect();
+        if valid.len() < self.threshold() {
+            return Err(format!(
+                "Approved by {} of {} members needed.",
+                valid.len(),
+                self.threshold()
+            ));
+        }
+
+        let signature = self
+            .keys
+            .derive_child(&proposal.index.to_be_bytes::<32>())
+            .combine_signatures(valid)
+            .map_err(|e| format!("{e}"))?;
+        proposal.with_signature(signature)
+    }
+}
+
+pub trait TreasuryExt {
+    /// Stores treasury in a Chunk, its address identifies the treasury.
+    fn act_treasury_put(
+        &self,
+        treasury: &Treasury,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<XorName, String>> + Send;
+
+    fn act_treasury_get(
+        &self,
+        address: &XorName,
+    ) -> impl Future<Output = Result<Treasury, String>> + Send;
+
+    /// Stores proposed spend of a treasury in a Chunk, its address identifies the proposal.
+    fn act_propose(
+        &self,
+        treasury: &Treasury,
+        proposal: &UnsignedSpend,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<XorName, String>> + Send;
+
+    fn act_proposal(
+        &self,
+        address: &XorName,
+    ) -> impl Future<Output = Result<UnsignedSpend, String>> + Send;
+
+    /// Signs a proposal with member's key share. Signature share is stored in a Chunk linked
+    /// from a GraphEntry owned by key derived from member's key, so others can find it.
+    fn act_approve(
+        &self,
+        treasury: &Treasury,
+        member_sk: &SecretKey,
+        proposal: &XorName,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+
+    /// Verified approvals of a proposal: signature shares by member index.
+    fn act_approvals(
+        &self,
+        treasury: &Treasury,
+        proposal: &XorName,
+    ) -> impl Future<Output = Result<BTreeMap<usize, SignatureShare>, String>> + Send;
+
+    /// Combines approvals of a proposal and stores the spend, if there are enough of them.
+    fn act_publish_approved(
+        &self,
+        treasury: &Treasury,
+        proposal: &XorName,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<GraphEntry, String>> + Send;
+}
+
+impl TreasuryExt for Client {
+    async fn act_treasury_put(
+        &self,
+        treasury: &Treasury,
+        payment: &PaymentOption,
+    ) -> Result<XorName, String> {
+        let bytes = rmp_serde::to_vec(treasury).map_err(|e| format!("{e}"))?;
+        let (_paid, address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Treasury Chunk: {}", address);
+
+        Ok(*address.xorname())
+    }
+
+    async fn act_treasury_get(&self, address: &XorName) -> Result<Treasury, String> {
+        let chunk = self
+            .chunk_get(&ChunkAddress::new(*address))
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        rmp_serde::from_slice(chunk.value()).map_err(|e| format!("{e}"))
+    }
+
+    async fn act_propose(
+        &self,
+        treasury: &Treasury,
+        proposal: &UnsignedSpend,
+        payment: &PaymentOption,
+    ) -> Result<XorName, String> {
+        treasury.check_proposal(proposal)?;
+
+        let (_paid, address) = self
+            .chunk_put(
+                &Chunk::new(Bytes::from(proposal.to_bytes()?)),
+                payment.clone(),
+            )
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Proposal Chunk: {}", address);
+
+        Ok(*address.xorname())
+    }
+
+    async fn act_proposal(&self, address: &XorName) -> Result<UnsignedSpend, String> {
+        let chunk = self
+            .chunk_get(&ChunkAddress::new(*address))
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        UnsignedSpend::from_bytes(chunk.value())
+    }
+
+    async fn act_approve(
+        &self,
+        treasury: &Treasury,
+        member_sk: &SecretKey,
+        proposal: &XorName,
+        payment: &PaymentOption,
+    ) -> Result<(), String> {
+        let (_index, share) = treasury.share(member_sk)?;
+        let unsigned = self.act_proposal(proposal).await?;
+        treasury.check_proposal(&unsigned)?;
+
+        let approval_sk = member_sk.derive_child(&approval_index(proposal));
+        let address = GraphEntryAddress::new(approval_sk.public_key());
+        if self
+            .graph_entry_check_existence(&address)
+            .await
+            .map_err(|e| format!("{e}"))?
+        {
+            return Err("Already approved.".into());
+        }
+
+        let bytes = rmp_serde::to_vec(&unsigned.sign_share(&share)).map_err(|e| format!("{e}"))?;
+        let (_paid, chunk_address) = self
+            .chunk_put(&Chunk::new(Bytes::from(bytes)), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+
+        let approval = GraphEntry::new(&approval_sk, vec![], chunk_address.xorname().0, vec![]);
+        let (_paid, address) = self
+            .graph_entry_put(approval, payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Approval GraphEntry: {}", address);
+
+        Ok(())
+    }
+
+    async fn act_approvals(
+        &self,
+        treasury: &Treasury,
+        proposal: &XorName,
+    ) -> Result<BTreeMap<usize, SignatureShare>, String> {
+        let unsigned = self.act_proposal(proposal).await?;
+        let mut approvals = BTreeMap::new();
+
+        for (index, member) in treasury.members.iter().enumerate() {
+            let address = GraphEntryAddress::new(member.derive_child(&approval_index(proposal)));
+            if !self
+                .graph_entry_check_existence(&address)
+                .await
+                .map_err(|e| format!("{e}"))?
+            {
+                continue;
+            }
+
+            let link = self
+                .graph_entry_get(&address)
+                .await
+                .map_err(|e| format!("{e}"))?;
+            let chunk = self
+                .chunk_get(&ChunkAddress::new(XorName(link.content)))
+                .await
+                .map_err(|e| format!("{e}"))?;
+            let share: SignatureShare = match rmp_serde::from_slice(chunk.value()) {
+                Ok(share) => share,
+                Err(e) => {
+                    println!("Approval of member {}: {}", index, e);
+                    continue;
+                }
+            };
+
+            match treasury.verify_share(&unsigned, index, &share) {
+                true => {
+                    approvals.insert(index, share);
+                }
+                false => println!("Wrong approval of member {}", index),
+            }
+        }
+
+        Ok(approvals)
+    }
+
+    async fn act_publish_approved(
+        &self,
+        treasury: &Treasury,
+        proposal: &XorName,
+        payment: &PaymentOption,
+    ) -> Result<GraphEntry, String> {
+        let unsigned = self.act_proposal(proposal).await?;
+        let approvals = self.act_approvals(treasury, proposal).await?;
+        let spend = treasury.combine(&unsigned, &approvals)?;
+
+        let (_paid, address) = self
+            .graph_entry_put(spend.clone(), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Treasury spend GraphEntry: {}", address);
+
+        Ok(spend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::Wallet;
+    use ruint::aliases::U256;
+
+    #[test]
+    fn two_of_three() -> Result<(), String> {
+        let members: Vec<SecretKey> = (0..3).map(|_| SecretKey::random()).collect();
+        let treasury = Treasury::deal(
+            "Treasury".into(),
+            2,
+            members.iter().map(|sk| sk.public_key()).collect(),
+        )?;
+        assert_eq!(2, treasury.threshold());
+        assert!(Treasury::deal("Too many".into(), 4, treasury.members.clone()).is_err());
+        assert!(treasury.share(&SecretKey::random()).is_err());
+
+        let token_id = XorName::from_content(&[1u8]);
+        let mut wallet = Wallet::new(treasury.pk());
+        wallet.request(Some(token_id))?;
+        wallet.receive(U256::from(100), token_id, SecretKey::random().public_key())?;
+        let payee = SecretKey::random().public_key();
+        let proposal = wallet.unsigned_spend(token_id, vec![(payee, U256::from(30))])?;
+        assert_eq!(U256::from(100), wallet.balance(token_id)?);
+        treasury.check_proposal(&proposal)?;
+
+        let mut shares = BTreeMap::new();
+        for sk in members.iter().skip(1) {
+            let (i, share) = treasury.share(sk)?;
+            let signature = proposal.sign_share(&share);
+            assert!(treasury.verify_share(&proposal, i, &signature));
+            shares.insert(i, signature);
+            if shares.len() < 2 {
+                assert!(treasury.combine(&proposal, &shares).is_err());
+            }
+        }
+
+        let spend = treasury.combine(&proposal, &shares)?;
+        assert!(spend.verify_signature());
+        assert_eq!(proposal.owner, spend.owner);
+        assert_eq!(1, wallet.follow_spend(token_id, &spend)?.len());
+        assert_eq!(U256::from(70), wallet.balance(token_id)?);
+
+        // share of other member doesn't count
+        let forged = BTreeMap::from([(0, shares[&1].clone()), (1, shares[&1].clone())]);
+        assert!(treasury.combine(&proposal, &forged).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use autonomi::{PublicKey, XorName};
+use ruint::aliases::U256;
+use std::fmt;
+use std::str::FromStr;
+
+pub const URI_SCHEME: &str = "act:";
+
+/// Payment request, which can be passed to the payer as an URI:
+///
+/// `act:<receive key hex>?token=<token id hex>&amount=<decimal>&memo=<text>&expiry=<unix time>&callback=<url>&inbox=<key hex>`
+///
+/// Only receive key and token are required. Amount is in token's decimal units, eg. `10.5`.
+/// Unknown parameters are ignored.
+#[derive(PartialEq, Clone, Debug)]
+pub struct PaymentRequest {
+    pub key: PublicKey,
+    pub token_id: XorName,
+    pub amount: Option<String>,
+    pub memo: Option<String>,
+    /// Seconds since UNIX epoch.
+    pub expiry: Option<u64>,
+    /// URL the payer can notify about the payment.
+    pub callback: Option<String>,
+    /// Payee's inbox key, to leave a notice of the payment in.
+    pub inbox: Option<PublicKey>,
+}
+
+impl PaymentRequest {
+    pub fn new(token_id: XorName, key: PublicKey) -> Self {
+        Self {
+            key,
+            token_id,
+            amount: None,
+            memo: None,
+            expiry: None,
+            callback: None,
+            inbox: None,
+        }
+    }
+
+    pub fn with_amount(mut self, amount: &str) -> Result<Self, String> {
+        check_decimal(amount)?;
+        self.amount = Some(amount.to_string());
+        Ok(self)
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.is_some_and(|expiry| now >= expiry)
+    }
+
+    /// Requested amount in token's smallest units.
+    pub fn amount_units(&self, decimals: u8) -> Result<Option<U256>, String> {
+        let Some(amount) = &self.amount else {
+            return Ok(None);
+        };
+        let amount = amount.replace('_', "");
+        let (intg, dec) = amount.split_once('.').unwrap_or((&amount, ""));
+
+        if dec.len() > decimals.into() {
+            return Err(format!("Amount has more than {} decimals.", decimals));
+        }
+        let digits = format!("{}{:0<width$}", intg, dec, width = decimals.into());
+
+        U256::from_str_radix(&digits, 10)
+            .map(Some)
+            .map_err(|e| format!("{}", e))
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}?token={:x}",
+            URI_SCHEME,
+            self.key.to_hex(),
+            self.token_id
+        )?;
+        if let Some(amount) = &self.amount {
+            write!(f, "&amount={}", amount)?;
+        }
+        if let Some(memo) = &self.memo {
+            write!(f, "&memo={}", percent_encode(memo))?;
+        }
+        if let Some(expiry) = &self.expiry {
+            write!(f, "&expiry={}", expiry)?;
+        }
+        if let Some(callback) = &self.callback {
+            write!(f, "&callback={}", percent_encode(callback))?;
+        }
+        if let Some(inbox) = &self.inbox {
+            write!(f, "&inbox={}", inbox.to_hex())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = String;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let uri = uri.trim();
+        let rest = match uri.get(..URI_SCHEME.len()) {
+            Some(scheme) if scheme.eq_ignore_ascii_case(URI_SCHEME) => &uri[URI_SCHEME.len()..],
+            _ => return Err(format!("Not an {} URI.", URI_SCHEME)),
+        };
+
+        let (key, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let key = PublicKey::from_hex(key).map_err(|e| format!("Receive key: {}", e))?;
+
+        let mut token_id = None;
+        let mut request = Self::new(XorName::default(), key);
+
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value)?;
+
+            match name {
+                "token" => token_id = Some(parse_token_id(&value)?),
+                "amount" => request = request.with_amount(&value)?,
+                "memo" => request.memo = Some(value),
+                "expiry" => {
+                    request.expiry = Some(value.parse().map_err(|e| format!("Expiry: {}", e))?)
+                }
+                "callback" => request.callback = Some(value),
+                "inbox" => {
+                    request.inbox =
+                        Some(PublicKey::from_hex(&value).map_err(|e| format!("Inbox: {}", e))?)
+                }
+                _ => (), // unknown, for forward compatibility
+            }
+        }
+
+        request.token_id = token_id.ok_or("Missing token.".to_string())?;
+        Ok(request)
+    }
+}
+
+fn parse_token_id(hex_str: &str) -> Result<XorName, String> {
+    let bytes: [u8; 32] = hex::decode(hex_str)
+        .map_err(|e| format!("Token: {}", e))?
+        .try_into()
+        .map_err(|_| "Token: wrong length".to_string())?;
+
+    Ok(XorName(bytes))
+}
+
+/// Digits with optional decimal point, `_` allowed as a separator.
+fn check_decimal(amount: &str) -> Result<(), String> {
+    let valid = !amount.is_empty()
+        && amount
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == '_')
+        && amount.chars().any(|c| c.is_ascii_digit())
+        && amount.matches('.').count() <= 1;
+
+    match valid {
+        true => Ok(()),
+        false => Err(format!("Wrong amount: {}", amount)),
+    }
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+
+    while let Some(b) = input.next() {
+        match b {
+            b'%' => {
+                let hex: Vec<u8> = input.by_ref().take(2).collect();
+                let byte = std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|h| h.len() == 2)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or(format!("Wrong percent-encoding: {}", text))?;
+                bytes.push(byte);
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(b),
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+
+    #[test]
+    fn uri_roundtrip() -> Result<(), String> {
+        let key = SecretKey::random().public_key();
+        let token_id = XorName::from_content(&[1u8]);
+
+        let mut request = PaymentRequest::new(token_id, key).with_amount("10.5")?;
+        request.memo = Some("Invoice #1: 50% & rest".to_string());
+        request.expiry = Some(1_700_000_000);
+        request.callback = Some("https://shop.example/paid?order=1".to_string());
+        request.inbox = Some(SecretKey::random().public_key());
+
+        let uri = request.to_string();
+        println!("{}", uri);
+        assert!(uri.starts_with(&format!("act:{}?token={:x}", key.to_hex(), token_id)));
+        assert!(uri.contains("&memo=Invoice%20%231%3A%2050%25%20%26%20rest"));
+
+        assert_eq!(request, uri.parse::<PaymentRequest>()?);
+
+        let minimal = PaymentRequest::new(token_id, key);
+        assert_eq!(minimal, minimal.to_string().parse::<PaymentRequest>()?);
+
+        assert_eq!(Some(U256::from(10_500)), request.amount_units(3)?);
+        assert_eq!(Some(U256::from(105)), request.amount_units(1)?);
+        assert!(request.amount_units(0).is_err());
+        assert_eq!(None, minimal.amount_units(18)?);
+
+        assert!(request.is_expired(1_700_000_000));
+        assert!(!request.is_expired(1_699_999_999));
+        assert!(!minimal.is_expired(u64::MAX));
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_uris() {
+        let key = SecretKey::random().public_key().to_hex();
+        let token = format!("{:x}", XorName::from_content(&[1u8]));
+
+        assert!(format!("bitcoin:{}?token={}", key, token)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("act:{}", key).parse::<PaymentRequest>().is_err());
+        assert!(format!("act:abcd?token={}", token)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("act:{}?token={}&amount=1.2.3", key, token)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("act:{}?token={}&memo=%4", key, token)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("act:{}?token={}&inbox=abcd", key, token)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("ACT:{}?token={}&unknown=1&memo=a+b", key, token)
+            .parse::<PaymentRequest>()
+            .is_ok_and(|r| r.memo == Some("a b".to_string())));
+    }
+}
@@ -0,0 +1,528 @@
+use crate::act::ActExt;
+use crate::cache::{ValidationCache, Verdict};
+use crate::checkpoint::CheckpointExt;
+use crate::history::now;
+use crate::lock::check_entry_locks;
+use crate::marker::MarkerExt;
+use crate::mint::{permitted_mints, MintExt, Mints};
+use crate::wallet::Wallet;
+use autonomi::{Client, GraphEntry, GraphEntryAddress, PublicKey, XorName};
+use futures::Future;
+use rand::Rng;
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Fewest GraphEntries checked when validating a payment, even the smallest one.
+pub const MIN_VALIDATION_BUDGET: usize = 16;
+/// Most GraphEntries checked when validating a payment, even the biggest one.
+pub const MAX_VALIDATION_BUDGET: usize = 1024;
+/// GraphEntries added to the budget for every bit of the amount.
+const BUDGET_PER_BIT: usize = 4;
+
+/// How many GraphEntries are worth checking for a payment: the higher the amount,
+/// the more time we are willing to spend on validation.
+pub fn validation_budget(amount: U256) -> usize {
+    (MIN_VALIDATION_BUDGET + amount.bit_len() * BUDGET_PER_BIT).min(MAX_VALIDATION_BUDGET)
+}
+
+/// Outcome of validation, which found no invalid GraphEntry.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Validation {
+    /// Number of GraphEntries checked.
+    pub checked: usize,
+    /// All ancestors back to genesis were checked within the budget.
+    pub complete: bool,
+}
+
+/// Rule of a valid GraphEntry.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Rule {
+    Signature,
+    ParentUsedTwice,
+    /// Parent's token differs.
+    OtherToken,
+    /// Parent doesn't pay to entry's owner.
+    ParentNotPaying,
+    /// Sum of inputs differs from sum of outputs.
+    InputsOutputs,
+}
+
+impl Rule {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Signature => "Wrong signature",
+            Self::ParentUsedTwice => "Parent used twice",
+            Self::OtherToken => "Parent of other token",
+            Self::ParentNotPaying => "Parent doesn't pay to the owner",
+            Self::InputsOutputs => "Inputs differ from outputs",
+        }
+    }
+}
+
+/// GraphEntries without parents that spends of a token may descend from: genesis committed
+/// in token info, and mints permitted by minting policy. Anyone can store a GraphEntry without
+/// parents with token's ID, so any other root is rejected.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Roots {
+    pub genesis: Option<PublicKey>,
+    /// What genesis must pay in total.
+    pub total_supply: Option<U256>,
+    pub mints: Mints,
+}
+
+impl Roots {
+    /// Checks a GraphEntry without parents, and that genesis pays exactly total supply.
+    pub fn check_root(&self, root: &GraphEntry) -> Result<(), String> {
+        self.check_root_owner(root.owner)?;
+        if self.genesis != Some(root.owner) {
+            return Ok(());
+        }
+
+        let paid = sum(root.descendants.iter().map(|(_key, data)| data))?;
+        match self.total_supply {
+            Some(total_supply) if total_supply == paid => Ok(()),
+            Some(total_supply) => Err(format!(
+                "Genesis pays {}, not total supply {}.",
+                paid, total_supply
+            )),
+            None => Err("Token info doesn't commit to total supply.".into()),
+        }
+    }
+
+    /// Like `check_root`, but a root of a token, which info doesn't commit to a genesis
+    /// (created before it did), is neither confirmed, nor rejected. Returns whether it's confirmed.
+    pub fn confirms_root(&self, root: &GraphEntry) -> Result<bool, String> {
+        match self.unconfirmable(root.owner) {
+            true => Ok(false),
+            false => self.check_root(root).map(|_| true),
+        }
+    }
+
+    /// Like `check_root_owner`, with unconfirmed roots as in `confirms_root`.
+    pub fn confirms_root_owner(&self, owner: PublicKey) -> Result<bool, String> {
+        match self.unconfirmable(owner) {
+            true => Ok(false),
+            false => self.check_root_owner(owner).map(|_| true),
+        }
+    }
+
+    fn unconfirmable(&self, owner: PublicKey) -> bool {
+        self.genesis.is_none() && !self.mints.all.contains(&owner)
+    }
+
+    /// Checks owner of a GraphEntry without parents.
+    pub fn check_root_owner(&self, owner: PublicKey) -> Result<(), String> {
+        if self.genesis == Some(owner) || self.mints.permitted.contains(&owner) {
+            return Ok(());
+        }
+        if self.mints.all.contains(&owner) {
+            return Err(format!(
+                "Mint {} not permitted by minting policy.",
+                owner.to_hex()
+            ));
+        }
+
+        match self.genesis {
+            Some(_) => Err(format!(
+                "Ancestor {} is neither genesis, nor a mint.",
+                owner.to_hex()
+            )),
+            None => Err("Token info doesn't commit to a genesis.".into()),
+        }
+    }
+}
+
+/// Checks a GraphEntry against its parents: signature, token, that every parent pays
+/// to entry's owner, and that inputs equal outputs. Genesis (no parents) is checked
+/// only for signature. Returns the first rule violated.
+pub fn violated_rule(entry: &GraphEntry, parents: &[GraphEntry]) -> Option<Rule> {
+    if !entry.verify_signature() {
+        return Some(Rule::Signature);
+    }
+    if parents.is_empty() {
+        return None; // genesis
+    }
+
+    let Ok(outputs) = sum(entry.descendants.iter().map(|(_key, data)| data)) else {
+        return Some(Rule::InputsOutputs);
+    };
+
+    let mut inputs = U256::ZERO;
+    let mut seen = HashSet::new();
+    for parent in parents {
+        if !seen.insert(parent.owner) {
+            return Some(Rule::ParentUsedTwice);
+        }
+        if parent.content != entry.content {
+            return Some(Rule::OtherToken);
+        }
+
+        let paid = parent
+            .descendants
+            .iter()
+            .filter(|(key, _data)| *key == entry.owner)
+            .map(|(_key, data)| data);
+        if paid.clone().next().is_none() {
+            return Some(Rule::ParentNotPaying);
+        }
+        match sum(paid).ok().and_then(|paid| inputs.checked_add(paid)) {
+            Some(sum) => inputs = sum,
+            None => return Some(Rule::InputsOutputs),
+        }
+    }
+
+    (inputs != outputs).then_some(Rule::InputsOutputs)
+}
+
+/// Like `violated_rule`, with error describing the violation.
+pub fn check_entry(entry: &GraphEntry, parents: &[GraphEntry]) -> Result<(), String> {
+    match violated_rule(entry, parents) {
+        Some(rule) => Err(format!(
+            "{} in {}.",
+            rule.description(),
+            entry.owner.to_hex()
+        )),
+        None => Ok(()),
+    }
+}
+
+fn sum<'a>(amounts: impl Iterator<Item = &'a [u8; 32]>) -> Result<U256, String> {
+    amounts.fold(Ok(U256::ZERO), |sum, data| {
+        sum?.checked_add(U256::from_be_bytes(*data))
+            .ok_or("Overflow.".to_string())
+    })
+}
+
+pub trait ValidateExt {
+    /// Validates a spend by traversing its ancestors back to genesis, in random order,
+    /// until all are checked or `budget` GraphEntries are checked. Ancestors of outputs
+    /// checkpointed by token's issuer are not traversed, nor are ancestors of entries
+    /// already known valid from the cache. Returns error if any checked GraphEntry is invalid,
+    /// ancestors lead to a GraphEntry without parents other than token's roots, or if a checked
+    /// GraphEntry spends an output still locked. Validation of a token, which info doesn't commit
+    /// to a genesis, is never complete.
+    fn act_validate(
+        &self,
+        spend: &GraphEntry,
+        budget: usize,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<Validation, String>> + Send;
+
+    /// Roots of a token, from its token info and mints found in the network.
+    fn act_roots(&self, token_id: &XorName) -> impl Future<Output = Result<Roots, String>> + Send;
+
+    /// Validates wallet's unverified spends again, with given budget each. Wallet is changed,
+    /// but not saved. Returns number of spends left unverified.
+    fn act_validate_unverified(
+        &self,
+        wallet: &mut Wallet,
+        budget: usize,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<usize, String>> + Send;
+}
+
+/// GraphEntry from the cache, or from the network if not cached.
+pub(crate) async fn fetch_entry(
+    client: &Client,
+    cache: &mut ValidationCache,
+    owner: &PublicKey,
+) -> Result<GraphEntry, String> {
+    if let Some(entry) = cache.entry(owner) {
+        return Ok(entry);
+    }
+
+    let entry = client
+        .graph_entry_get(&GraphEntryAddress::new(*owner))
+        .await
+        .map_err(|e| format!("{e}"))?;
+    cache.insert(entry.clone())?;
+
+    Ok(entry)
+}
+
+impl ValidateExt for Client {
+    async fn act_validate(
+        &self,
+        spend: &GraphEntry,
+        budget: usize,
+        cache: &mut ValidationCache,
+    ) -> Result<Validation, String> {
+        let token_id = XorName(spend.content);
+        let mut visited = HashSet::<PublicKey>::from([spend.owner]);
+        let mut to_check = vec![spend.clone()];
+        let mut valid = Vec::new();
+        let mut genesis = None;
+        // root of a token not committing to its genesis, funds stay unverified
+        let mut unconfirmed = false;
+        let mut checked = 0;
+
+        // only checkpoints published since the last validation are read
+        if let Err(e) = self.act_checkpointed(&token_id, cache).await {
+            println!("Checkpoints of {:x}: {}", token_id, e);
+        }
+
+        let roots = self.act_roots(&token_id).await?;
+
+        // markers are only hints, marked entries get invalid verdict if they really are
+        if let Err(e) = self.act_check_markers(&token_id, cache).await {
+            println!("Invalid markers of {:x}: {}", token_id, e);
+        }
+
+        while !to_check.is_empty() && checked < budget {
+            let index = rand::thread_rng().gen_range(0..to_check.len());
+            let entry = to_check.swap_remove(index);
+
+            if XorName(entry.content) != token_id {
+                return Err(format!(
+                    "Ancestor of other token: {}.",
+                    entry.owner.to_hex()
+                ));
+            }
+
+            let verdict = cache.verdict(&entry);
+            match verdict {
+                Some(Verdict::Valid(Some(owner))) => {
+                    match roots.confirms_root_owner(owner)? {
+                        true => genesis = Some(owner),
+                        false => unconfirmed = true,
+                    }
+                    continue;
+                }
+                Some(Verdict::Valid(None)) => continue,
+                Some(Verdict::Invalid(e)) => return Err(e),
+                _ => (),
+            }
+
+            let mut parents = Vec::new();
+            for parent in entry.parents.iter() {
+                parents.push(fetch_entry(self, cache, parent).await?);
+            }
+
+            if verdict != Some(Verdict::Checked) {
+                if let Err(e) = check_entry(&entry, &parents) {
+                    cache.set_verdict(&entry, Verdict::Invalid(e.clone()))?;
+                    return Err(e);
+                }
+                cache.set_verdict(&entry, Verdict::Checked)?;
+            }
+            // locks depend on time, their violations are not cached
+            check_entry_locks(self, &entry, &parents, now()).await?;
+            checked += 1;
+
+            if parents.is_empty() {
+                if !roots.confirms_root(&entry)? {
+                    println!("Root {} not confirmed by token info", entry.owner.to_hex());
+                    unconfirmed = true;
+                } else if roots.genesis == Some(entry.owner) {
+                    genesis = Some(entry.owner);
+                }
+            }
+
+            for parent in parents {
+                if cache.is_checkpointed(&token_id, parent.owner, entry.owner) {
+                    continue; // vouched for by the issuer
+                }
+                if visited.insert(parent.owner) {
+                    to_check.push(parent);
+                }
+            }
+            valid.push(entry);
+        }
+        println!(
+            "Validated {} of {}: {} left",
+            checked,
+            spend.owner.to_hex(),
+            to_check.len()
+        );
+
+        // all paths were followed, so every checked entry has valid ancestry
+        let complete = to_check.is_empty() && !unconfirmed;
+        if complete {
+            for entry in valid.iter() {
+                cache.set_verdict(entry, Verdict::Valid(genesis))?;
+            }
+        }
+
+        Ok(Validation { checked, complete })
+    }
+
+    async fn act_roots(&self, token_id: &XorName) -> Result<Roots, String> {
+        let info = self.act_token_info(token_id).await?;
+        let mints = match info.mint {
+            Some(policy) => {
+                let entries = self.act_mint_entries(token_id, &policy.authority).await?;
+                permitted_mints(&policy, token_id, &entries, now())
+            }
+            None => Mints::default(),
+        };
+
+        Ok(Roots {
+            genesis: info.genesis,
+            total_supply: info.total_supply,
+            mints,
+        })
+    }
+
+    async fn act_validate_unverified(
+        &self,
+        wallet: &mut Wallet,
+        budget: usize,
+        cache: &mut ValidationCache,
+    ) -> Result<usize, String> {
+        for spend_address in wallet.unverified() {
+            let spend = self
+                .graph_entry_get(&GraphEntryAddress::new(spend_address))
+                .await
+                .map_err(|e| format!("{e}"))?;
+
+            match self.act_validate(&spend, budget, cache).await {
+                Ok(validation) if validation.complete => {
+                    wallet.set_verified(&spend_address);
+                }
+                Ok(_) => (),
+                Err(e) => println!("Spend {:.4}(...) not valid: {}", spend_address.to_hex(), e),
+            }
+        }
+
+        Ok(wallet.unverified().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autonomi::SecretKey;
+
+    #[test]
+    fn entry_checks() -> Result<(), String> {
+        let token_id = XorName::from_content(&[1u8]);
+        let issuer = SecretKey::random();
+        let payee = SecretKey::random();
+
+        let genesis = GraphEntry::new(
+            &SecretKey::random(),
+            vec![],
+            token_id.0,
+            vec![(issuer.public_key(), U256::from(100).to_be_bytes())],
+        );
+        check_entry(&genesis, &[])?;
+
+        let spend = |outputs: Vec<(PublicKey, U256)>| {
+            GraphEntry::new(
+                &issuer,
+                vec![genesis.owner],
+                token_id.0,
+                outputs
+                    .into_iter()
+                    .map(|(key, amount)| (key, amount.to_be_bytes()))
+                    .collect(),
+            )
+        };
+
+        let valid = spend(vec![
+            (payee.public_key(), U256::from(30)),
+            (SecretKey::random().public_key(), U256::from(70)),
+        ]);
+        check_entry(&valid, &[genesis.clone()])?;
+
+        let minting = spend(vec![(payee.public_key(), U256::from(101))]);
+        assert!(check_entry(&minting, &[genesis.clone()]).is_err());
+        assert_eq!(
+            Some(Rule::InputsOutputs),
+            violated_rule(&minting, &[genesis.clone()])
+        );
+
+        assert!(check_entry(&valid, &[genesis.clone(), genesis.clone()]).is_err());
+        assert_eq!(
+            Some(Rule::ParentUsedTwice),
+            violated_rule(&valid, &[genesis.clone(), genesis.clone()])
+        );
+
+        let unrelated = GraphEntry::new(
+            &payee,
+            vec![genesis.owner],
+            token_id.0,
+            vec![(issuer.public_key(), U256::from(100).to_be_bytes())],
+        );
+        assert!(check_entry(&unrelated, &[genesis.clone()]).is_err());
+        assert_eq!(
+            Some(Rule::ParentNotPaying),
+            violated_rule(&unrelated, &[genesis.clone()])
+        );
+
+        let mut tampered = valid.clone();
+        tampered.descendants[0].1 = U256::from(31).to_be_bytes();
+        assert!(check_entry(&tampered, &[genesis.clone()]).is_err());
+        assert_eq!(
+            Some(Rule::Signature),
+            violated_rule(&tampered, &[genesis.clone()])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_root() -> Result<(), String> {
+        let token_id = XorName::from_content(&[1u8]);
+        let root = |amount: u64| {
+            GraphEntry::new(
+                &SecretKey::random(),
+                vec![],
+                token_id.0,
+                vec![(
+                    SecretKey::random().public_key(),
+                    U256::from(amount).to_be_bytes(),
+                )],
+            )
+        };
+        let genesis = root(1000);
+        let permitted = root(100);
+        let over_cap = root(100);
+        let roots = Roots {
+            genesis: Some(genesis.owner),
+            total_supply: Some(U256::from(1000)),
+            mints: Mints {
+                all: HashSet::from([permitted.owner, over_cap.owner]),
+                permitted: HashSet::from([permitted.owner]),
+                minted: U256::from(100),
+            },
+        };
+        roots.check_root(&genesis)?;
+        roots.check_root(&permitted)?;
+        assert!(roots.check_root(&over_cap).is_err());
+
+        // anyone can store a GraphEntry without parents with the token's ID
+        let foreign = root(1000);
+        assert!(roots.check_root(&foreign).is_err());
+        assert!(Roots::default().check_root(&foreign).is_err());
+
+        // genesis paying other than committed total supply
+        let inflated = Roots {
+            total_supply: Some(U256::from(999)),
+            ..roots.clone()
+        };
+        assert!(inflated.check_root(&genesis).is_err());
+
+        // token created before its info committed to a genesis
+        let legacy = Roots {
+            genesis: None,
+            total_supply: None,
+            ..roots.clone()
+        };
+        assert!(!legacy.confirms_root(&foreign)?);
+        assert!(legacy.confirms_root(&permitted)?);
+        assert!(legacy.confirms_root(&over_cap).is_err());
+        assert!(roots.confirms_root(&genesis)?);
+        assert!(roots.confirms_root(&foreign).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn budget_grows_with_amount() {
+        assert_eq!(MIN_VALIDATION_BUDGET, validation_budget(U256::ZERO));
+        assert!(validation_budget(U256::from(1000)) > validation_budget(U256::from(10)));
+        assert_eq!(MAX_VALIDATION_BUDGET, validation_budget(U256::MAX));
+    }
+}
@@ -1,47 +1,284 @@
+use crate::book::AddressBook;
+use crate::cache::ValidationCache;
+use crate::history::{now, Direction, Transaction, TxStatus};
+use crate::invoice::invoice_reference;
+use crate::lock::{lock_pk, Lock, LockExt, LockRecord};
+use crate::memo::{Memo, MemoExt};
+use crate::proof::{ProofBundle, ProofExt, SPOT_CHECKS};
+use crate::proposal::UnsignedSpend;
+use crate::receipt::Receipt;
+use crate::request::{Funds, Received, Request, RequestMatch, RequestStatus};
+use crate::validate::{validation_budget, ValidateExt, Validation};
 use autonomi::{
-    client::payment::PaymentOption, Bytes, Client, PublicKey, ScratchpadAddress, SecretKey, XorName,
+    client::payment::PaymentOption, Bytes, Client, GraphEntry, GraphEntryAddress, PublicKey,
+    ScratchpadAddress, SecretKey, XorName,
 };
 use futures::Future;
 use ruint::aliases::U256;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
-pub struct Wallet(
-    PublicKey,
-    HashMap<Option<XorName>, (U256, Vec<(PublicKey, U256)>)>,
-    U256,
-);
-// TODO: ? give index key a name
+/// Serialized with MessagePack as an array, so fields keep their order,
+/// and new ones are added at the end with defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Wallet {
+    master: PublicKey,
+    /// Main derivation index of each token (`None` for a pending request of unknown token),
+    /// with spends paying to its key and amounts.
+    tokens: HashMap<Option<XorName>, (U256, Vec<(PublicKey, U256)>)>,
+    last_index: U256,
+    #[serde(default)]
+    history: Vec<Transaction>,
+    #[serde(default)]
+    watch_only: bool,
+    #[serde(default)]
+    book: AddressBook,
+    /// Names of accounts, starting from account 1.
+    #[serde(default)]
+    accounts: Vec<String>,
+    /// First inbox slot not read yet.
+    #[serde(default)]
+    inbox_slot: u64,
+    /// Payment requests with their own keys, by index.
+    #[serde(default)]
+    requests: BTreeMap<U256, Request>,
+    /// Received spends, which ancestry has not been fully validated.
+    #[serde(default)]
+    unverified: HashSet<PublicKey>,
+    /// Locks of received outputs, with spends paying them.
+    #[serde(default)]
+    locks: Vec<(PublicKey, Lock)>,
+    /// First inbox slot of each payee, which was found free when notifying last time.
+    #[serde(default)]
+    notify_slots: HashMap<PublicKey, u64>,
+    /// Inbox slots read or received unsuccessfully, with number of failed attempts.
+    #[serde(default)]
+    inbox_retries: BTreeMap<u64, u32>,
+    #[serde(skip)]
+    key_index: KeyIndex,
+}
 // TODO: ? optional pubkey ("none" meaning waiting for payment)? this would require supplying amount arg for request.
 // TODO: read/write wallet to autonomi: serde.
-// TODO: read/write wallet to bytes: serde.
+
+impl PartialEq for Wallet {
+    /// Key index is left out, it's derived from master key and last index.
+    fn eq(&self, other: &Self) -> bool {
+        self.master == other.master
+            && self.tokens == other.tokens
+            && self.last_index == other.last_index
+            && self.history == other.history
+            && self.watch_only == other.watch_only
+            && self.book == other.book
+            && self.accounts == other.accounts
+            && self.inbox_slot == other.inbox_slot
+            && self.requests == other.requests
+            && self.unverified == other.unverified
+            && self.locks == other.locks
+            && self.notify_slots == other.notify_slots
+            && self.inbox_retries == other.inbox_retries
+    }
+}
+
+/// Keys of all indices up to wallet's last one, to find index deriving a key without deriving
+/// them all again. Not saved, filled when needed.
+#[derive(Clone, Debug, Default)]
+struct KeyIndex {
+    keys: HashMap<PublicKey, U256>,
+}
+
+/// How many not yet known indices are checked, when looking for change of a spend made by watched wallet.
+const WATCH_LOOKAHEAD: u64 = 20;
+
+/// Public part of a wallet: master public key and derivation indices with their unspent outputs.
+/// Enough to track balances without holding any secret. Incoming payments are not found by
+/// the watch-only wallet itself, as inbox notices can only be decrypted with the wallet's key:
+/// they are received by spend address, eg. from the payer's receipt.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct WatchOnly {
+    pub master: PublicKey,
+    pub tokens: HashMap<Option<XorName>, (U256, Vec<(PublicKey, U256)>)>,
+    pub last_index: U256,
+    /// Payment requests with their own keys, by index.
+    #[serde(default)]
+    pub requests: BTreeMap<U256, Request>,
+    /// Received spends, which ancestry has not been fully validated.
+    #[serde(default)]
+    pub unverified: HashSet<PublicKey>,
+    /// Locks of received outputs, with spends paying them.
+    #[serde(default)]
+    pub locks: Vec<(PublicKey, Lock)>,
+}
+
+impl WatchOnly {
+    pub fn to_hex(&self) -> Result<String, String> {
+        rmp_serde::to_vec(self)
+            .map(hex::encode)
+            .map_err(|e| format!("{e}"))
+    }
+
+    pub fn from_hex(descriptor: &str) -> Result<Self, String> {
+        let bytes = hex::decode(descriptor.trim()).map_err(|e| format!("{e}"))?;
+        rmp_serde::from_slice(&bytes).map_err(|e| format!("{e}"))
+    }
+}
+
+impl From<WatchOnly> for Wallet {
+    fn from(descriptor: WatchOnly) -> Self {
+        Self {
+            master: descriptor.master,
+            tokens: descriptor.tokens,
+            last_index: descriptor.last_index,
+            history: Vec::new(),
+            watch_only: true,
+            book: AddressBook::default(),
+            accounts: Vec::new(),
+            inbox_slot: 0,
+            requests: descriptor.requests,
+            unverified: descriptor.unverified,
+            locks: descriptor.locks,
+            notify_slots: HashMap::new(),
+            inbox_retries: BTreeMap::new(),
+            key_index: KeyIndex::default(),
+        }
+    }
+}
 
 impl Wallet {
     pub fn new(pk: PublicKey) -> Self {
-        Self(pk, HashMap::new(), U256::ZERO)
+        Self {
+            master: pk,
+            tokens: HashMap::new(),
+            last_index: U256::ZERO,
+            history: Vec::new(),
+            watch_only: false,
+            book: AddressBook::default(),
+            accounts: Vec::new(),
+            inbox_slot: 0,
+            requests: BTreeMap::new(),
+            unverified: HashSet::new(),
+            locks: Vec::new(),
+            notify_slots: HashMap::new(),
+            inbox_retries: BTreeMap::new(),
+            key_index: KeyIndex::default(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| format!("{e}"))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut wallet: Self = rmp_serde::from_slice(bytes).map_err(|e| format!("{e}"))?;
+        wallet.update_key_index();
+        Ok(wallet)
+    }
+
+    pub fn master(&self) -> PublicKey {
+        self.master
+    }
+
+    pub fn watch_only(&self) -> WatchOnly {
+        WatchOnly {
+            master: self.master,
+            tokens: self.tokens.clone(),
+            last_index: self.last_index,
+            requests: self.requests.clone(),
+            unverified: self.unverified.clone(),
+            locks: self.locks.clone(),
+        }
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
     }
 
-    /// If you're creating a token, `token_id` can be `None`.
+    pub fn book(&self) -> &AddressBook {
+        &self.book
+    }
+
+    pub fn book_mut(&mut self) -> &mut AddressBook {
+        &mut self.book
+    }
+
+    /// Names of accounts kept under this (main) wallet's key, starting from account 1.
+    pub fn accounts(&self) -> &[String] {
+        &self.accounts
+    }
+
+    /// Returns number of the new account.
+    pub fn add_account(&mut self, name: &str) -> Result<u32, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Empty account name.".into());
+        }
+        if self.accounts.iter().any(|n| n == name) {
+            return Err("Account already exists.".into());
+        }
+
+        self.accounts.push(name.to_string());
+        u32::try_from(self.accounts.len()).map_err(|e| format!("{e}"))
+    }
+
+    /// First inbox slot not read yet.
+    pub fn inbox_slot(&self) -> u64 {
+        self.inbox_slot
+    }
+
+    pub fn set_inbox_slot(&mut self, slot: u64) {
+        self.inbox_slot = slot;
+    }
+
+    /// Inbox slots to read again, with number of failed attempts.
+    pub fn inbox_retries(&self) -> &BTreeMap<u64, u32> {
+        &self.inbox_retries
+    }
+
+    /// Records an attempt to read and receive an inbox slot. Failed slot is read again,
+    /// until it fails `max_attempts` times.
+    pub fn inbox_attempted(&mut self, slot: u64, failed: bool, max_attempts: u32) {
+        let attempts = self.inbox_retries.remove(&slot).unwrap_or(0) + 1;
+        if failed && attempts < max_attempts {
+            self.inbox_retries.insert(slot, attempts);
+        }
+    }
+
+    /// Slot of payee's inbox to look for a free one from, when notifying.
+    pub fn notify_slot(&self, inbox: &PublicKey) -> u64 {
+        self.notify_slots.get(inbox).copied().unwrap_or(0)
+    }
+
+    pub fn set_notify_slot(&mut self, inbox: PublicKey, slot: u64) {
+        self.notify_slots.insert(inbox, slot);
+    }
+
+    /// If you're creating a token, `token_id` can be `None`. Each such request gets its own key,
+    /// which becomes token's main key when received with `receive_at`.
     pub fn request(&mut self, req_token_id: Option<XorName>) -> Result<PublicKey, String> {
         println!("request token_id: {:?}", req_token_id);
-        let index = self.1.get(&req_token_id).map(|(index, _spends)| index);
+        if self.watch_only {
+            return Err("Watch-only wallet cannot request payments.".into());
+        }
+        if req_token_id.is_none() {
+            return self.request_payment(None, None, None);
+        }
+        let index = self.tokens.get(&req_token_id).map(|(index, _spends)| index);
         println!("found index: {:?}", index);
 
         let index = match index {
             Some(index) => index,
             None => {
-                self.2 = self
-                    .2
+                self.last_index = self
+                    .last_index
                     .checked_add(U256::from(1)) // increment
                     .ok_or("This wallet is full".to_string())?;
-                self.1.insert(req_token_id, (self.2, Vec::new()));
-                &self.2
+                self.tokens
+                    .insert(req_token_id, (self.last_index, Vec::new()));
+                &self.last_index
             }
         };
         println!("request index: {}", index);
 
-        let request_key = self.0.derive_child(&index.to_be_bytes::<32>());
+        let request_key = self.master.derive_child(&index.to_be_bytes::<32>());
         println!(
             "request Derived PublicKey: {:.4}(...), {:?}",
             request_key.to_hex(),
@@ -51,21 +288,215 @@ impl Wallet {
         Ok(request_key)
     }
 
+    /// Requests a payment to its own key, so it can be told apart from other payments.
+    /// If `token_id` is `None`, any token is accepted.
+    pub fn request_payment(
+        &mut self,
+        token_id: Option<XorName>,
+        amount: Option<U256>,
+        expiry: Option<u64>,
+    ) -> Result<PublicKey, String> {
+        if self.watch_only {
+            return Err("Watch-only wallet cannot request payments.".into());
+        }
+
+        self.last_index = self
+            .last_index
+            .checked_add(U256::from(1))
+            .ok_or("This wallet is full".to_string())?;
+        self.requests
+            .insert(self.last_index, Request::new(token_id, amount, expiry));
+
+        Ok(self.key_at(&self.last_index))
+    }
+
+    fn key_at(&self, index: &U256) -> PublicKey {
+        self.master.derive_child(&index.to_be_bytes::<32>())
+    }
+
+    /// Payment request made with given key.
+    pub fn request_of(&self, key: &PublicKey) -> Option<(U256, &Request)> {
+        self.index_that_derives(*key)
+            .and_then(|index| self.requests.get(&index).map(|request| (index, request)))
+    }
+
+    /// Payment requests with their keys and statuses, oldest first. All of them if `status` is `None`.
+    pub fn requests(
+        &self,
+        status: Option<RequestStatus>,
+        now: u64,
+    ) -> Result<Vec<(PublicKey, &Request, RequestStatus)>, String> {
+        let mut requests = Vec::new();
+        for (index, request) in self.requests.iter() {
+            let request_status = request.status(now)?;
+            if status.is_none_or(|status| status == request_status) {
+                requests.push((self.key_at(index), request, request_status));
+            }
+        }
+
+        Ok(requests)
+    }
+
+    /// Receives payment made to a request's own key. First funds of a token make the request key
+    /// token's main key, otherwise funds stay at the request key until consolidated.
+    pub fn receive_request(
+        &mut self,
+        key: &PublicKey,
+        amount: U256,
+        received_token_id: XorName,
+        spend: PublicKey,
+        now: u64,
+    ) -> Result<RequestMatch, String> {
+        let (index, request) = self
+            .request_of(key)
+            .ok_or("No such payment request in this wallet.".to_string())?;
+
+        if request
+            .token_id
+            .is_some_and(|token_id| token_id != received_token_id)
+        {
+            return Err("Payment request was for other token.".into());
+        }
+        if request
+            .payments
+            .iter()
+            .any(|(received, _)| *received == spend)
+        {
+            return Err("Already received this spend".into());
+        }
+
+        let main = self.index_of_token(received_token_id);
+        let funds = match request.funds {
+            Funds::Separate if request.payments.is_empty() && main.is_none() => Funds::Main,
+            Funds::Separate => Funds::Separate,
+            Funds::Main if main == Some(index) => Funds::Main,
+            _ => return Err("Payment request key has already been spent.".into()),
+        };
+
+        let mut request = request.clone();
+        request.token_id = Some(received_token_id);
+        request.payments.push((spend, amount));
+        request.funds = funds;
+
+        let request_match = RequestMatch {
+            received: request.received()?,
+            requested: request.amount,
+            late: request.is_expired(now),
+        };
+
+        if funds == Funds::Main {
+            self.tokens
+                .entry(Some(received_token_id))
+                .or_insert((index, Vec::new()))
+                .1
+                .push((spend, amount));
+        }
+        self.requests.insert(index, request);
+
+        Ok(request_match)
+    }
+
+    /// Funds of a token kept at request keys: request index with received spends.
+    pub fn separate_funds(&self, token_id: XorName) -> Vec<(U256, Vec<(PublicKey, U256)>)> {
+        self.requests
+            .iter()
+            .filter(|(_index, request)| request.funds == Funds::Separate)
+            .filter(|(_index, request)| request.token_id == Some(token_id))
+            .filter(|(_index, request)| !request.payments.is_empty())
+            .map(|(index, request)| (*index, request.payments.clone()))
+            .collect()
+    }
+
+    /// Records that funds of request `index` were moved with `spend`, paying `amount` to token's main key.
+    pub fn consolidated(
+        &mut self,
+        index: U256,
+        spend: PublicKey,
+        amount: U256,
+    ) -> Result<(), String> {
+        let request = self
+            .requests
+            .get_mut(&index)
+            .ok_or("No such payment request in this wallet.".to_string())?;
+        let token_id = request
+            .token_id
+            .ok_or("Payment request has not been paid.".to_string())?;
+
+        request.funds = Funds::Consolidated(spend);
+        self.receive(amount, token_id, spend)?;
+        self.prune_locks();
+
+        Ok(())
+    }
+
+    /// Receives payment made to `key`, matching it to the request which derived the key.
+    /// Returns how the payment matches, if it was paid to a request's own key.
+    pub fn receive_at(
+        &mut self,
+        key: PublicKey,
+        amount: U256,
+        received_token_id: XorName,
+        spend: PublicKey,
+        now: u64,
+    ) -> Result<Option<RequestMatch>, String> {
+        let index = self
+            .index_that_derives(key)
+            .ok_or("No requested key in this wallet.".to_string())?;
+
+        if self.requests.contains_key(&index) {
+            return self
+                .receive_request(&key, amount, received_token_id, spend, now)
+                .map(Some);
+        }
+
+        match self.index_of_token(received_token_id) {
+            Some(main) if main == index => (),
+            // wallets saved with a single pending `None` request
+            None if self
+                .tokens
+                .get(&None)
+                .is_some_and(|(pending, _)| *pending == index) =>
+            {
+                ()
+            }
+            _ => return Err("Key was not requested for this token.".into()),
+        }
+        self.receive(amount, received_token_id, spend)?;
+
+        Ok(None)
+    }
+
+    /// Receives payment to token's main key. If the wallet has no key for the token yet,
+    /// a single pending request of unknown token takes it, eg. when creating the token.
     pub fn receive(
         &mut self,
         amount: U256,
         received_token_id: XorName,
         spend: PublicKey,
     ) -> Result<(), String> {
-        let entry = self.1.get(&Some(received_token_id));
+        if !self.tokens.contains_key(&Some(received_token_id)) && !self.tokens.contains_key(&None) {
+            let mut pending = self
+                .requests
+                .iter()
+                .filter(|(_index, request)| request.token_id.is_none())
+                .filter(|(_index, request)| request.payments.is_empty())
+                .map(|(index, _request)| *index);
+            if let (Some(index), None) = (pending.next(), pending.next()) {
+                let key = self.key_at(&index);
+                self.receive_request(&key, amount, received_token_id, spend, now())?;
+                return Ok(());
+            }
+        }
+
+        let entry = self.tokens.get(&Some(received_token_id));
 
         match entry {
             None => {
-                let none_entry = self.1.remove(&None);
+                let none_entry = self.tokens.remove(&None);
                 match none_entry {
                     Some(ne) => {
                         // receiveing tokens, which token_id was not known when requesting.
-                        self.1.insert(Some(received_token_id), ne.clone());
+                        self.tokens.insert(Some(received_token_id), ne.clone());
                     }
                     None => (),
                 }
@@ -73,7 +504,7 @@ impl Wallet {
             Some(_) => (),
         };
 
-        let entry = self.1.get_mut(&Some(received_token_id));
+        let entry = self.tokens.get_mut(&Some(received_token_id));
 
         match entry {
             Some((_index, spends)) => {
@@ -89,7 +520,7 @@ impl Wallet {
     }
 
     pub fn balance_total(&self) -> HashMap<XorName, Result<U256, String>> {
-        self.1.iter().fold(
+        let balances = self.tokens.iter().fold(
             HashMap::<XorName, Result<U256, String>>::new(),
             |mut token_balances, (token_id, (_index, spends))| {
                 if let Some(id) = *token_id {
@@ -108,16 +539,48 @@ impl Wallet {
 
                 token_balances
             },
-        )
+        );
+
+        // funds waiting at request keys
+        self.requests
+            .values()
+            .filter(|request| request.funds == Funds::Separate)
+            .fold(balances, |mut token_balances, request| {
+                if let Some(id) = request.token_id {
+                    let separate = request.received();
+                    token_balances
+                        .entry(id)
+                        .and_modify(|balance| {
+                            *balance = balance.clone().and_then(|balance| {
+                                separate.clone().and_then(|separate| {
+                                    balance.checked_add(separate).ok_or("Overflow.".to_string())
+                                })
+                            })
+                        })
+                        .or_insert(separate);
+                }
+
+                token_balances
+            })
+    }
+
+    /// Received spends with amounts, including funds received with request keys, not consolidated yet.
+    fn funds(&self, token_id: XorName) -> Vec<(PublicKey, U256)> {
+        let mut spends = self
+            .tokens
+            .get(&Some(token_id))
+            .map(|(_index, spends)| spends.clone())
+            .unwrap_or_default();
+        for (_index, payments) in self.separate_funds(token_id) {
+            spends.extend(payments);
+        }
+
+        spends
     }
 
+    /// Includes funds received with request keys, not consolidated yet, and unverified funds.
     pub fn balance(&self, token_id: XorName) -> Result<U256, String> {
-        let spends = match self.1.get(&Some(token_id)) {
-            None => {
-                return Ok(U256::ZERO);
-            }
-            Some((_index, spends)) => spends,
-        };
+        let spends = self.funds(token_id);
 
         let (balance, overflow) = spends.iter().fold(
             (U256::ZERO, false),
@@ -133,12 +596,153 @@ impl Wallet {
         }
     }
 
+    /// Marks a received spend, which ancestry has not been fully validated.
+    pub fn set_unverified(&mut self, spend: PublicKey) {
+        self.unverified.insert(spend);
+    }
+
+    /// Marks a spend as verified, after its validation completed. Returns `false` if it was not unverified.
+    pub fn set_verified(&mut self, spend: &PublicKey) -> bool {
+        self.unverified.remove(spend)
+    }
+
+    pub fn unverified(&self) -> Vec<PublicKey> {
+        self.unverified.iter().copied().collect()
+    }
+
+    /// Part of token's balance, received with spends not fully validated.
+    pub fn unverified_balance(&self, token_id: XorName) -> Result<U256, String> {
+        self.funds(token_id)
+            .iter()
+            .filter(|(spend, _amount)| self.unverified.contains(spend))
+            .try_fold(U256::ZERO, |sum, (_spend, amount)| {
+                sum.checked_add(*amount).ok_or("Overflow.".to_string())
+            })
+    }
+
+    /// Unverified funds can't be spent, until validated or accepted with `accept_unverified`.
+    pub fn check_verified(&self, token_id: XorName) -> Result<(), String> {
+        match self.unverified_balance(token_id)? > U256::ZERO {
+            true => Err("Some funds are not verified yet. Validate or accept them first.".into()),
+            false => Ok(()),
+        }
+    }
+
+    /// User accepts unverified funds of a token as they are. Returns number of accepted spends.
+    pub fn accept_unverified(&mut self, token_id: XorName) -> usize {
+        let spends: Vec<PublicKey> = self
+            .funds(token_id)
+            .iter()
+            .map(|(spend, _amount)| *spend)
+            .filter(|spend| self.unverified.contains(spend))
+            .collect();
+
+        for spend in spends.iter() {
+            self.unverified.remove(spend);
+        }
+
+        spends.len()
+    }
+
+    /// Records lock of an output paid to this wallet with `spend`.
+    pub fn add_lock(&mut self, spend: PublicKey, lock: Lock) {
+        if !self.locks.contains(&(spend, lock.clone())) {
+            self.locks.push((spend, lock));
+        }
+    }
+
+    /// Locks of outputs paid to `key` with given spends.
+    pub fn locks_of(&self, spends: &[(PublicKey, U256)], key: &PublicKey) -> Vec<&Lock> {
+        self.locks
+            .iter()
+            .filter(|(spend, lock)| {
+                lock.key == *key && spends.iter().any(|(received, _)| received == spend)
+            })
+            .map(|(_spend, lock)| lock)
+            .collect()
+    }
+
+    /// Locks of token's funds, at main key or request keys.
+    fn token_locks(&self, token_id: XorName) -> Vec<&Lock> {
+        let mut locks = Vec::new();
+        if let Some((index, spends)) = self.tokens.get(&Some(token_id)) {
+            locks.extend(self.locks_of(spends, &self.key_at(index)));
+        }
+        for (index, payments) in self.separate_funds(token_id) {
+            locks.extend(self.locks_of(&payments, &self.key_at(&index)));
+        }
+
+        locks
+    }
+
+    /// Part of token's balance, which is locked at given time.
+    pub fn locked_balance(&self, token_id: XorName, now: u64) -> Result<U256, String> {
+        self.token_locks(token_id)
+            .iter()
+            .try_fold(U256::ZERO, |sum, lock| sum.checked_add(lock.locked(now)))
+            .ok_or("Overflow.".to_string())
+    }
+
+    /// When more of token's locked funds unlock, if any are locked at given time.
+    pub fn next_unlock(&self, token_id: XorName, now: u64) -> Option<u64> {
+        self.token_locks(token_id)
+            .iter()
+            .flat_map(|lock| lock.remaining(now))
+            .map(|(from, _amount)| from)
+            .min()
+    }
+
+    /// Funds at token's main key are all spent at once, so they can't be spent while some are locked.
+    /// Locked funds at request keys are relocked when consolidated.
+    pub fn check_unlocked(&self, token_id: XorName, now: u64) -> Result<(), String> {
+        let Some((index, spends)) = self.tokens.get(&Some(token_id)) else {
+            return Ok(());
+        };
+
+        match self
+            .locks_of(spends, &self.key_at(index))
+            .iter()
+            .filter_map(|lock| lock.remaining(now).last().map(|(from, _amount)| *from))
+            .max()
+        {
+            Some(until) => Err(format!(
+                "Some funds at the main key are locked until {}.",
+                until
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Forgets locks of spends not held by the wallet anymore.
+    fn prune_locks(&mut self) {
+        let held: HashSet<PublicKey> = self
+            .tokens
+            .values()
+            .flat_map(|(_index, spends)| spends.iter())
+            .chain(
+                self.requests
+                    .values()
+                    .filter(|request| request.funds == Funds::Separate)
+                    .flat_map(|request| request.payments.iter()),
+            )
+            .map(|(spend, _amount)| *spend)
+            .collect();
+
+        self.locks.retain(|(spend, _lock)| held.contains(spend));
+    }
+
     pub fn take_to_spend(
         &mut self,
         token_id: XorName,
     ) -> Result<(Vec<PublicKey>, U256, PublicKey), String> {
+        if self.watch_only {
+            return Err("Watch-only wallet cannot spend.".into());
+        }
+        self.check_verified(token_id)?;
+        self.check_unlocked(token_id, now())?;
+
         let (spends, sum, overflow) = self
-            .1
+            .tokens
             .remove(&Some(token_id))
             .map(|(_index, spends)| {
                 let (sum, overflow) = spends.iter().fold(
@@ -156,6 +760,7 @@ impl Wallet {
                 )
             })
             .unwrap_or((Vec::new(), U256::ZERO, false));
+        self.prune_locks();
 
         match overflow {
             false => self
@@ -165,77 +770,498 @@ impl Wallet {
         }
     }
 
+    /// Spend of token's funds at the main key, paying `outputs` and the rest back to the wallet,
+    /// to be signed elsewhere, so watch-only wallets can prepare it too. Wallet is not changed,
+    /// it follows the spend once it's stored. Funds at request keys are not included, as they
+    /// need to be consolidated first.
+    pub fn unsigned_spend(
+        &self,
+        token_id: XorName,
+        mut outputs: Vec<(PublicKey, U256)>,
+    ) -> Result<UnsignedSpend, String> {
+        let mut spending = self.clone();
+        spending.watch_only = false;
+        let index = spending
+            .index_of_token(token_id)
+            .ok_or("Token not found in wallet.".to_string())?;
+        let (parents, sum, rest_key) = spending.take_to_spend(token_id)?;
+
+        let paid = outputs
+            .iter()
+            .try_fold(U256::ZERO, |sum, (_key, amount)| sum.checked_add(*amount))
+            .ok_or("Overflow.".to_string())?;
+        let rest = sum
+            .checked_sub(paid)
+            .ok_or("Insufficient funds.".to_string())?;
+        outputs.push((rest_key, rest));
+
+        Ok(UnsignedSpend {
+            token_id,
+            index,
+            owner: self.key_at(&index),
+            parents,
+            outputs,
+            rest_index: spending.index_of_token(token_id),
+        })
+    }
+
+    /// Signs a spend prepared by a copy of this wallet, eg. watch-only one on other machine,
+    /// and follows it, so both copies agree on token's funds once the spend is published.
+    pub fn sign_offline(
+        &mut self,
+        sk: &SecretKey,
+        unsigned: &UnsignedSpend,
+    ) -> Result<GraphEntry, String> {
+        if self.watch_only {
+            return Err("Watch-only wallet cannot spend.".into());
+        }
+        if sk.public_key() != self.master {
+            return Err("Key of other wallet.".into());
+        }
+        let spend = unsigned.sign(sk)?;
+
+        // this copy might not have received the funds, but it must not lose other ones
+        match self.index_of_token(unsigned.token_id) {
+            Some(index) if index != unsigned.index => {
+                return Err("Wallet copies differ in token's main key, sync this one.".into())
+            }
+            Some(_) => (),
+            None => {
+                self.tokens
+                    .insert(Some(unsigned.token_id), (unsigned.index, Vec::new()));
+            }
+        }
+        self.last_index = self.last_index.max(unsigned.index);
+        self.follow_spend_to(unsigned.token_id, &spend, unsigned.rest_index)?;
+
+        Ok(spend)
+    }
+
     pub fn index_of_token(&self, token_id: XorName) -> Option<U256> {
-        self.1.get(&Some(token_id)).map(|(index, _spends)| *index)
+        self.tokens
+            .get(&Some(token_id))
+            .map(|(index, _spends)| *index)
     }
 
     pub fn pk_of_token(&self, token_id: XorName) -> Option<PublicKey> {
         self.index_of_token(token_id)
-            .map(|index| self.0.derive_child(&index.to_be_bytes::<32>()))
+            .map(|index| self.master.derive_child(&index.to_be_bytes::<32>()))
     }
 
+    /// Index of a key, which can still receive: token's main key or request's key.
     pub fn index_that_derives(&self, request: PublicKey) -> Option<U256> {
-        self.1
-            .iter()
-            .filter(|(_, (index, _))| self.0.derive_child(&index.to_be_bytes::<32>()) == request)
-            .map(|(_, (index, _))| *index)
-            .collect::<Vec<_>>()
-            .first()
-            .copied()
+        let receiving = |index: &U256| {
+            self.tokens.values().any(|(main, _spends)| main == index)
+                || self.requests.contains_key(index)
+        };
+
+        match self.key_index.keys.get(&request) {
+            Some(index) => Some(*index).filter(receiving),
+            None => self
+                .tokens
+                .values()
+                .map(|(index, _spends)| index)
+                .chain(self.requests.keys())
+                .find(|index| self.key_at(index) == request)
+                .copied(),
+        }
+    }
+
+    /// Derives keys of indices not in the lookup yet. Indices are used one by one from 1,
+    /// so the lookup has keys of indices from 1 to its length.
+    fn update_key_index(&mut self) {
+        let mut index = U256::from(self.key_index.keys.len());
+        while index < self.last_index {
+            index += U256::from(1);
+            let key = self.key_at(&index);
+            self.key_index.keys.insert(key, index);
+        }
     }
 
+    /// Index of any key this wallet has requested, even if it has been spent already.
+    fn index_ever_requested(&mut self, key: &PublicKey) -> Option<U256> {
+        self.update_key_index();
+        self.key_index.keys.get(key).copied()
+    }
+
+    /// Checks main spends of the token and payments of requests.
     pub fn received_spend(&self, token_id: XorName, spend: PublicKey) -> bool {
-        self.1
+        let main = self
+            .tokens
             .get(&Some(token_id))
-            .and_then(|(_index, spends)| {
-                match spends
+            .is_some_and(|(_index, spends)| spends.iter().any(|(received, _)| *received == spend));
+
+        main || self
+            .requests
+            .values()
+            .filter(|request| request.token_id == Some(token_id))
+            .any(|request| {
+                request
+                    .payments
                     .iter()
-                    .filter(|(received_spend, _amount)| received_spend == &spend)
-                    .count()
-                {
-                    0 => None,
-                    _ => Some(()),
-                }
+                    .any(|(received, _)| *received == spend)
             })
-            .is_some()
     }
-}
-
-pub trait WalletExt {
-    fn act_wallet_get(
-        &self,
-        sk: &SecretKey,
-    ) -> impl Future<Output = Result<Option<Wallet>, String>> + Send;
 
-    fn act_wallet_save(
-        &mut self,
-        wallet: &Wallet,
-        sk: &SecretKey,
-        payment: &PaymentOption,
-    ) -> impl Future<Output = Result<PublicKey, String>> + Send;
-}
+    /// Receives all outputs of a spend paying to any key of this wallet. Outputs to the same key
+    /// are summed. Outputs to keys which can't receive anymore are skipped, unless there are no other.
+    pub fn receive_spend(&mut self, spend: &GraphEntry, now: u64) -> Result<Vec<Received>, String> {
+        if !spend.verify_signature() {
+            return Err("Wrong spend signature.".into());
+        }
+        let token_id = XorName(spend.content);
+        if self.received_spend(token_id, spend.owner) {
+            return Err("Already received this spend".into());
+        }
 
-impl WalletExt for Client {
-    async fn act_wallet_get(&self, sk: &SecretKey) -> Result<Option<Wallet>, String> {
-        let address = ScratchpadAddress::new(sk.public_key());
+        let mut amounts = Vec::<(PublicKey, U256)>::new();
+        for (key, data) in spend.descendants.iter() {
+            if self.index_ever_requested(key).is_none() {
+                continue;
+            }
+            let amount = U256::from_be_bytes(*data);
 
-        if !self
-            .scratchpad_check_existence(&address)
-            .await
-            .map_err(|e| format!("{e}"))?
-        {
-            return Ok(None);
+            match amounts.iter_mut().find(|(k, _)| k == key) {
+                Some((_, sum)) => *sum = sum.checked_add(amount).ok_or("Overflow".to_string())?,
+                None => amounts.push((*key, amount)),
+            }
+        }
+        if amounts.is_empty() {
+            return Err("Could not find your Public Key in the spend".into());
         }
 
-        match self.scratchpad_get(&address).await {
-            Ok(sp) => {
-                let bytes = sp.decrypt_data(sk).map_err(|e| format!("{e}"))?;
-                let wallet = rmp_serde::from_slice(&bytes).map_err(|e| format!("{e}"))?;
-                Ok(Some(wallet))
+        let mut received = Vec::new();
+        let mut errors = Vec::new();
+        for (key, amount) in amounts {
+            match self.receive_at(key, amount, token_id, spend.owner, now) {
+                Ok(request) => received.push(Received {
+                    token_id,
+                    amount,
+                    key,
+                    spend: spend.owner,
+                    request,
+                    verified: !self.unverified.contains(&spend.owner),
+                }),
+                Err(e) => {
+                    println!("Receive to {:.4}(...): {}", key.to_hex(), e);
+                    errors.push(e);
+                }
             }
-            Err(e) => Err(format!("{e}")),
         }
-    }
+
+        match received.is_empty() {
+            true => Err(errors.join(" ")),
+            false => Ok(received),
+        }
+    }
+
+    /// Updates the wallet after its key for `token_id` has been spent elsewhere,
+    /// eg. watch-only wallet following the full one. Returns outputs sent outside of the wallet.
+    pub fn follow_spend(
+        &mut self,
+        token_id: XorName,
+        spend: &GraphEntry,
+    ) -> Result<Vec<Transaction>, String> {
+        self.follow_spend_to(token_id, spend, None)
+    }
+
+    /// Follows a spend with change to the key of `rest_index`. If it's `None`, change is looked
+    /// for among keys not derived yet, at most `WATCH_LOOKAHEAD` of them.
+    fn follow_spend_to(
+        &mut self,
+        token_id: XorName,
+        spend: &GraphEntry,
+        rest_index: Option<U256>,
+    ) -> Result<Vec<Transaction>, String> {
+        if self.pk_of_token(token_id) != Some(spend.owner) {
+            return Err("Spend was not made by this wallet.".into());
+        }
+        if XorName(spend.content) != token_id {
+            return Err("Spend of other token.".into());
+        }
+        if !spend.verify_signature() {
+            return Err("Wrong spend signature.".into());
+        }
+
+        self.tokens.remove(&Some(token_id));
+
+        let mut sent = Vec::new();
+        let lock_link = lock_pk(&spend.owner);
+        for (key, data) in spend.descendants.iter() {
+            if *key == lock_link {
+                continue;
+            }
+            let amount = U256::from_be_bytes(*data);
+
+            let change = match rest_index {
+                Some(index) => Some(index).filter(|index| self.key_at(index) == *key),
+                None => self.index_ahead(key),
+            };
+            match change {
+                Some(index) => {
+                    // change
+                    self.last_index = self.last_index.max(index);
+                    self.tokens
+                        .entry(Some(token_id))
+                        .or_insert((index, Vec::new()))
+                        .1
+                        .push((spend.owner, amount));
+                }
+                None => {
+                    let mut tx = Transaction::sent(token_id, amount, *key, spend.owner, None);
+                    tx.status = TxStatus::Confirmed;
+                    self.record(tx.clone());
+                    sent.push(tx);
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Updates the wallet after funds of request `index` have been consolidated elsewhere.
+    pub fn follow_consolidation(&mut self, index: U256, spend: &GraphEntry) -> Result<(), String> {
+        let token_id = self
+            .requests
+            .get(&index)
+            .and_then(|request| request.token_id)
+            .ok_or("Payment request has not been paid.".to_string())?;
+
+        if self.key_at(&index) != spend.owner {
+            return Err("Spend was not made by this wallet.".into());
+        }
+        if XorName(spend.content) != token_id {
+            return Err("Spend of other token.".into());
+        }
+        if !spend.verify_signature() {
+            return Err("Wrong spend signature.".into());
+        }
+
+        let main = self.pk_of_token(token_id);
+        let amount = spend
+            .descendants
+            .iter()
+            .filter(|(key, _data)| Some(*key) == main)
+            .try_fold(U256::ZERO, |sum, (_key, data)| {
+                sum.checked_add(U256::from_be_bytes(*data))
+            })
+            .ok_or("Overflow.".to_string())?;
+
+        self.consolidated(index, spend.owner, amount)
+    }
+
+    fn index_ahead(&self, request: &PublicKey) -> Option<U256> {
+        (1..=WATCH_LOOKAHEAD)
+            .map(|i| self.last_index.saturating_add(U256::from(i)))
+            .find(|index| self.master.derive_child(&index.to_be_bytes::<32>()) == *request)
+    }
+
+    pub fn record(&mut self, transaction: Transaction) {
+        println!("record: {:?}", transaction);
+        self.history.push(transaction);
+    }
+
+    /// Updates status of a transaction made with given spend.
+    pub fn set_status(
+        &mut self,
+        spend: PublicKey,
+        direction: Direction,
+        status: TxStatus,
+    ) -> Result<(), String> {
+        self.history
+            .iter_mut()
+            .rev()
+            .find(|tx| tx.spend == spend && tx.direction == direction)
+            .map(|tx| tx.status = status)
+            .ok_or("Transaction not found in history.".to_string())
+    }
+
+    /// Received spends with invoice reference as a memo, each once. A spend paying
+    /// the wallet with more outputs is in history more times.
+    fn invoice_spends(&self, invoice_id: &XorName) -> Vec<PublicKey> {
+        let reference = invoice_reference(invoice_id);
+        let mut seen = HashSet::new();
+
+        self.history
+            .iter()
+            .filter(|tx| tx.direction == Direction::Received)
+            .filter(|tx| tx.memo.as_ref() == Some(&reference))
+            .map(|tx| tx.spend)
+            .filter(|spend| seen.insert(*spend))
+            .collect()
+    }
+
+    /// Verified spends paying an invoice, to be counted by `act_invoice_status`.
+    pub fn invoice_payments(&self, invoice_id: &XorName) -> Vec<PublicKey> {
+        self.invoice_spends(invoice_id)
+            .into_iter()
+            .filter(|spend| !self.unverified.contains(spend))
+            .collect()
+    }
+
+    /// Spends paying an invoice, not verified yet, so not counted as paid.
+    pub fn invoice_unverified(&self, invoice_id: &XorName) -> Vec<PublicKey> {
+        self.invoice_spends(invoice_id)
+            .into_iter()
+            .filter(|spend| self.unverified.contains(spend))
+            .collect()
+    }
+
+    /// Sum of confirmed burns of a token.
+    pub fn burned(&self, token_id: XorName) -> Result<U256, String> {
+        self.history(Some(token_id), None, None)
+            .into_iter()
+            .filter(|tx| tx.is_burn() && tx.status == TxStatus::Confirmed)
+            .try_fold(U256::ZERO, |sum, tx| {
+                sum.checked_add(tx.amount).ok_or("Overflow.".to_string())
+            })
+    }
+
+    /// Transactions, oldest first. `from` is inclusive, `to` is exclusive, both in seconds since UNIX epoch.
+    pub fn history(
+        &self,
+        token_id: Option<XorName>,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Vec<&Transaction> {
+        self.history
+            .iter()
+            .filter(|tx| token_id.is_none_or(|id| id == tx.token_id))
+            .filter(|tx| from.is_none_or(|from| tx.timestamp >= from))
+            .filter(|tx| to.is_none_or(|to| tx.timestamp < to))
+            .collect()
+    }
+}
+
+pub trait WalletExt {
+    fn act_wallet_get(
+        &self,
+        sk: &SecretKey,
+    ) -> impl Future<Output = Result<Option<Wallet>, String>> + Send;
+
+    fn act_wallet_save(
+        &mut self,
+        wallet: &Wallet,
+        sk: &SecretKey,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<PublicKey, String>> + Send;
+
+    /// Follows spends made from wallet's keys, for wallets that are spent from other machine,
+    /// like watch-only ones. Returns transactions sent out from the wallet.
+    fn act_wallet_sync(
+        &self,
+        wallet: &mut Wallet,
+    ) -> impl Future<Output = Result<Vec<Transaction>, String>> + Send;
+
+    /// Receives payments made to any of wallet's keys with a spend, after validating its ancestry
+    /// with budget scaled to the amount. If validation could not finish within the budget, funds
+    /// are marked unverified. Wallet is changed, but not saved.
+    /// Without `sk`, encrypted memo of the spend is not read. Returns what was received to each key,
+    /// and how it matches the payment request, if paid to a request's own key.
+    fn act_receive(
+        &self,
+        wallet: &mut Wallet,
+        sk: Option<&SecretKey>,
+        spend_address: PublicKey,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<Vec<Received>, String>> + Send;
+
+    /// Like `act_receive`, but validates the spend with ancestors from payer's proof bundle,
+    /// spot-checking a few of them with the network. If the bundle doesn't reach genesis,
+    /// the spend is validated from the network as usual.
+    fn act_receive_proven(
+        &self,
+        wallet: &mut Wallet,
+        sk: Option<&SecretKey>,
+        bundle: &ProofBundle,
+        cache: &mut ValidationCache,
+    ) -> impl Future<Output = Result<Vec<Received>, String>> + Send;
+
+    /// Moves funds of a token kept at request keys to token's main key, so they can be spent.
+    /// Funds still locked are relocked to a new request key. Request keys with only locked
+    /// funds are left as they are. Wallet is changed, but not saved. Returns addresses
+    /// of the consolidating spends.
+    fn act_consolidate(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Vec<PublicKey>, String>> + Send;
+
+    /// Pays `amount` of a token to `to` key, rest goes back to the wallet. Wallet is changed
+    /// only if the spend was stored, but it's not saved. Returns receipt of the payment,
+    /// with spend address being `receipt.spend.owner`.
+    #[allow(clippy::too_many_arguments)]
+    fn act_spend(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        amount: U256,
+        to: PublicKey,
+        memo: Option<&Memo>,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Receipt, String>> + Send;
+
+    /// Like `act_spend`, with paid output locked: `releases` of it unlock from given times
+    /// (seconds since UNIX epoch) on. A single release is a time lock, more of them are
+    /// a vesting schedule. Amount not in releases is not locked.
+    #[allow(clippy::too_many_arguments)]
+    fn act_spend_locked(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        amount: U256,
+        to: PublicKey,
+        releases: Vec<(u64, U256)>,
+        memo: Option<&Memo>,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Receipt, String>> + Send;
+
+    /// Stores a spend signed elsewhere, see `Wallet::unsigned_spend`, and follows it.
+    /// Wallet is changed, but not saved. Returns transactions sent out with the spend.
+    fn act_publish_signed(
+        &self,
+        wallet: &mut Wallet,
+        spend: &GraphEntry,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<Vec<Transaction>, String>> + Send;
+
+    /// Moves tokens between two wallets of the same owner, eg. accounts. None of them is saved.
+    fn act_transfer(
+        &self,
+        from: &mut Wallet,
+        from_sk: &SecretKey,
+        to: &mut Wallet,
+        token_id: XorName,
+        amount: U256,
+        payment: &PaymentOption,
+    ) -> impl Future<Output = Result<PublicKey, String>> + Send;
+}
+
+impl WalletExt for Client {
+    async fn act_wallet_get(&self, sk: &SecretKey) -> Result<Option<Wallet>, String> {
+        let address = ScratchpadAddress::new(sk.public_key());
+
+        if !self
+            .scratchpad_check_existence(&address)
+            .await
+            .map_err(|e| format!("{e}"))?
+        {
+            return Ok(None);
+        }
+
+        match self.scratchpad_get(&address).await {
+            Ok(sp) => {
+                let bytes = sp.decrypt_data(sk).map_err(|e| format!("{e}"))?;
+                let wallet = Wallet::from_bytes(&bytes)?;
+                Ok(Some(wallet))
+            }
+            Err(e) => Err(format!("{e}")),
+        }
+    }
 
     async fn act_wallet_save(
         &mut self,
@@ -245,7 +1271,7 @@ impl WalletExt for Client {
     ) -> Result<PublicKey, String> {
         println!("saving: {:?}", wallet);
         println!("sk: {:.4}(...)", sk.to_hex());
-        let data = rmp_serde::to_vec(&wallet).map_err(|e| format!("{e}"))?;
+        let data = wallet.to_bytes()?;
 
         let existing = self.act_wallet_get(sk).await?;
 
@@ -261,6 +1287,460 @@ impl WalletExt for Client {
         }
         .map_err(|e| format!("{e}"))
     }
+
+    async fn act_wallet_sync(&self, wallet: &mut Wallet) -> Result<Vec<Transaction>, String> {
+        // consolidations first, they pay to main keys as they were before following main spends
+        let separate: Vec<U256> = wallet
+            .requests
+            .iter()
+            .filter(|(_index, request)| request.funds == Funds::Separate)
+            .map(|(index, _request)| *index)
+            .collect();
+
+        for index in separate {
+            let address = GraphEntryAddress::new(wallet.key_at(&index));
+
+            if !self
+                .graph_entry_check_existence(&address)
+                .await
+                .map_err(|e| format!("{e}"))?
+            {
+                continue;
+            }
+
+            let spend = self
+                .graph_entry_get(&address)
+                .await
+                .map_err(|e| format!("{e}"))?;
+            println!("sync consolidation: {:?}", spend);
+
+            wallet.follow_consolidation(index, &spend)?;
+        }
+
+        let tokens: Vec<XorName> = wallet.tokens.keys().flatten().copied().collect();
+        let mut sent = Vec::new();
+
+        for token_id in tokens {
+            while let Some(key) = wallet.pk_of_token(token_id) {
+                let address = GraphEntryAddress::new(key);
+
+                if !self
+                    .graph_entry_check_existence(&address)
+                    .await
+                    .map_err(|e| format!("{e}"))?
+                {
+                    break;
+                }
+
+                let spend = self
+                    .graph_entry_get(&address)
+                    .await
+                    .map_err(|e| format!("{e}"))?;
+                println!("sync spend: {:?}", spend);
+
+                sent.extend(wallet.follow_spend(token_id, &spend)?);
+
+                if wallet.pk_of_token(token_id) == Some(key) {
+                    return Err("Spend outputs to its own key.".into());
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    async fn act_receive(
+        &self,
+        wallet: &mut Wallet,
+        sk: Option<&SecretKey>,
+        spend_address: PublicKey,
+        cache: &mut ValidationCache,
+    ) -> Result<Vec<Received>, String> {
+        println!("Receive spend: {}", spend_address.to_hex());
+        let spend = self
+            .graph_entry_get(&GraphEntryAddress::new(spend_address))
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Receive spend GE: {:?}", spend);
+
+        receive_validated(self, wallet, sk, &spend, None, cache).await
+    }
+
+    async fn act_receive_proven(
+        &self,
+        wallet: &mut Wallet,
+        sk: Option<&SecretKey>,
+        bundle: &ProofBundle,
+        cache: &mut ValidationCache,
+    ) -> Result<Vec<Received>, String> {
+        println!("Receive proven spend: {}", bundle.spend.owner.to_hex());
+        let validation = self
+            .act_verify_bundle(bundle, SPOT_CHECKS, cache)
+            .await
+            .map_err(|e| format!("Proof not valid: {e}"))?;
+        println!("Proof bundle: {:?}", validation);
+
+        receive_validated(
+            self,
+            wallet,
+            sk,
+            &bundle.spend,
+            Some(validation).filter(|validation| validation.complete),
+            cache,
+        )
+        .await
+    }
+
+    async fn act_consolidate(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        payment: &PaymentOption,
+    ) -> Result<Vec<PublicKey>, String> {
+        if wallet.is_watch_only() {
+            return Err("Watch-only wallet cannot spend.".into());
+        }
+
+        let mut consolidated = Vec::new();
+        for (index, payments) in wallet.separate_funds(token_id) {
+            let main = wallet
+                .pk_of_token(token_id)
+                .ok_or("Token not found in wallet.".to_string())?;
+            let sum = payments
+                .iter()
+                .try_fold(U256::ZERO, |sum, (_spend, amount)| sum.checked_add(*amount))
+                .ok_or("Overflow.".to_string())?;
+
+            let now = now();
+            let relocks: Vec<Vec<(u64, U256)>> = wallet
+                .locks_of(&payments, &wallet.key_at(&index))
+                .iter()
+                .map(|lock| lock.remaining(now))
+                .filter(|remaining| !remaining.is_empty())
+                .collect();
+            let relocked = relocks
+                .iter()
+                .flatten()
+                .try_fold(U256::ZERO, |sum, (_from, amount)| sum.checked_add(*amount))
+                .ok_or("Overflow.".to_string())?;
+            let unlocked = sum
+                .checked_sub(relocked)
+                .ok_or("More locked than received.".to_string())?;
+            if unlocked.is_zero() {
+                continue; // nothing to move yet
+            }
+
+            let spend_sk = sk.derive_child(&index.to_be_bytes::<32>());
+            let mut outputs = vec![(main, unlocked.to_be_bytes())];
+            let mut relock = None;
+            if !relocks.is_empty() {
+                let relock_key = wallet.request_payment(Some(token_id), None, None)?;
+                outputs.push((relock_key, relocked.to_be_bytes()));
+                outputs.push((lock_pk(&spend_sk.public_key()), U256::ZERO.to_be_bytes()));
+
+                let locks = LockRecord {
+                    token_id,
+                    spend: spend_sk.public_key(),
+                    locks: relocks
+                        .into_iter()
+                        .map(|releases| Lock {
+                            key: relock_key,
+                            releases,
+                        })
+                        .collect(),
+                };
+                self.act_lock_put(&spend_sk, &locks, payment).await?;
+                relock = Some((relock_key, locks));
+            }
+
+            let spend = GraphEntry::new(
+                &spend_sk,
+                payments.iter().map(|(spend, _amount)| *spend).collect(),
+                token_id.0,
+                outputs,
+            );
+            let spend_owner = spend.owner;
+
+            let (_paid, address) = self
+                .graph_entry_put(spend, payment.clone())
+                .await
+                .map_err(|e| format!("{e}"))?;
+            println!("Consolidation GraphEntry: {}", address);
+
+            wallet.consolidated(index, spend_owner, unlocked)?;
+            if let Some((relock_key, record)) = relock {
+                wallet.receive_request(&relock_key, relocked, token_id, spend_owner, now)?;
+                for lock in record.locks {
+                    wallet.add_lock(spend_owner, lock);
+                }
+            }
+            consolidated.push(spend_owner);
+        }
+
+        Ok(consolidated)
+    }
+
+    async fn act_spend(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        amount: U256,
+        to: PublicKey,
+        memo: Option<&Memo>,
+        payment: &PaymentOption,
+    ) -> Result<Receipt, String> {
+        spend_to(
+            self,
+            wallet,
+            sk,
+            token_id,
+            amount,
+            to,
+            Vec::new(),
+            memo,
+            payment,
+        )
+        .await
+    }
+
+    async fn act_spend_locked(
+        &self,
+        wallet: &mut Wallet,
+        sk: &SecretKey,
+        token_id: XorName,
+        amount: U256,
+        to: PublicKey,
+        releases: Vec<(u64, U256)>,
+        memo: Option<&Memo>,
+        payment: &PaymentOption,
+    ) -> Result<Receipt, String> {
+        if releases.is_empty() {
+            return Err("No releases of the lock.".into());
+        }
+        spend_to(
+            self, wallet, sk, token_id, amount, to, releases, memo, payment,
+        )
+        .await
+    }
+
+    async fn act_publish_signed(
+        &self,
+        wallet: &mut Wallet,
+        spend: &GraphEntry,
+        payment: &PaymentOption,
+    ) -> Result<Vec<Transaction>, String> {
+        let token_id = XorName(spend.content);
+        let mut following = wallet.clone();
+        // checks the spend before storing it
+        let sent = following.follow_spend(token_id, spend)?;
+
+        let (_paid, address) = self
+            .graph_entry_put(spend.clone(), payment.clone())
+            .await
+            .map_err(|e| format!("{e}"))?;
+        println!("Signed spend GraphEntry: {}", address);
+        *wallet = following;
+
+        Ok(sent)
+    }
+
+    async fn act_transfer(
+        &self,
+        from: &mut Wallet,
+        from_sk: &SecretKey,
+        to: &mut Wallet,
+        token_id: XorName,
+        amount: U256,
+        payment: &PaymentOption,
+    ) -> Result<PublicKey, String> {
+        let mut receiving = to.clone();
+        let key = receiving.request(Some(token_id))?;
+
+        let spend = self
+            .act_spend(from, from_sk, token_id, amount, key, None, payment)
+            .await?
+            .spend
+            .owner;
+
+        receiving.receive(amount, token_id, spend)?;
+        receiving.record(Transaction::received(token_id, amount, spend, None));
+        *to = receiving;
+
+        Ok(spend)
+    }
+}
+
+/// Pays `amount` to `to` with output locked by `releases`, if there are any.
+#[allow(clippy::too_many_arguments)]
+async fn spend_to(
+    client: &Client,
+    wallet: &mut Wallet,
+    sk: &SecretKey,
+    token_id: XorName,
+    amount: U256,
+    to: PublicKey,
+    releases: Vec<(u64, U256)>,
+    memo: Option<&Memo>,
+    payment: &PaymentOption,
+) -> Result<Receipt, String> {
+    wallet.check_verified(token_id)?;
+    client
+        .act_consolidate(wallet, sk, token_id, payment)
+        .await?;
+    let mut spending = wallet.clone();
+
+    let payer_sk = sk.derive_child(
+        &spending
+            .index_of_token(token_id)
+            .ok_or("Token not found in wallet.".to_string())?
+            .to_be_bytes::<32>(),
+    );
+
+    let (inputs, sum, rest_key) = spending.take_to_spend(token_id)?;
+    println!("Inputs: {:?}", (&inputs, sum));
+
+    let rest_amount = sum
+        .checked_sub(amount)
+        .ok_or("Insufficient funds.".to_string())?;
+
+    let mut outputs = vec![
+        (to, amount.to_be_bytes()),
+        (rest_key, rest_amount.to_be_bytes()),
+    ];
+    let locks = match releases.is_empty() {
+        true => None,
+        false => {
+            let lock = Lock { key: to, releases };
+            if lock.total()? > amount {
+                return Err("More locked than paid.".into());
+            }
+            outputs.push((lock_pk(&payer_sk.public_key()), U256::ZERO.to_be_bytes()));
+            Some(LockRecord {
+                token_id,
+                spend: payer_sk.public_key(),
+                locks: vec![lock],
+            })
+        }
+    };
+
+    let spend = GraphEntry::new(&payer_sk, inputs, token_id.0, outputs);
+    let spend_owner = spend.owner;
+    let receipt = Receipt::new(spend.clone(), 0)?;
+    let mut tx = Transaction::sent(
+        token_id,
+        amount,
+        to,
+        spend_owner,
+        memo.map(|m| m.text.clone()),
+    );
+
+    // record and memo must be there before anyone can see the spend
+    let stored = match locks {
+        Some(locks) => client.act_lock_put(&payer_sk, &locks, payment).await,
+        None => Ok(()),
+    };
+    let stored = match (stored, memo) {
+        (Ok(()), Some(memo)) => client.act_memo_put(&payer_sk, memo, &to, payment).await,
+        (stored, _) => stored,
+    };
+    let stored = match stored {
+        Ok(()) => client
+            .graph_entry_put(spend, payment.clone())
+            .await
+            .map_err(|e| format!("{e}")),
+        Err(e) => Err(e),
+    };
+
+    match stored {
+        Ok((_paid, spend_address)) => {
+            println!("Spend GraphEntry: {}", spend_address);
+
+            spending.receive(rest_amount, token_id, spend_owner)?;
+            tx.status = TxStatus::Confirmed;
+            spending.record(tx);
+            *wallet = spending;
+
+            Ok(receipt)
+        }
+        Err(e) => {
+            tx.status = TxStatus::Failed;
+            wallet.record(tx);
+
+            Err(e)
+        }
+    }
+}
+
+/// Credits a spend to a clone of the wallet, validates it from the network, unless already
+/// validated, and only then changes the wallet. Reads memo and records received transactions.
+async fn receive_validated(
+    client: &Client,
+    wallet: &mut Wallet,
+    sk: Option<&SecretKey>,
+    spend: &GraphEntry,
+    validation: Option<Validation>,
+    cache: &mut ValidationCache,
+) -> Result<Vec<Received>, String> {
+    let mut receiving = wallet.clone();
+    let mut received = receiving.receive_spend(spend, now())?;
+    println!("Received: {:?}", received);
+
+    let amount = received.iter().fold(U256::ZERO, |sum, payment| {
+        sum.saturating_add(payment.amount)
+    });
+    let validation = match validation {
+        Some(validation) => validation,
+        None => client
+            .act_validate(spend, validation_budget(amount), cache)
+            .await
+            .map_err(|e| format!("Spend not valid: {e}"))?,
+    };
+    if !validation.complete {
+        receiving.set_unverified(spend.owner);
+    }
+    if let Some(record) = client.act_lock_record(spend).await? {
+        for lock in record.locks {
+            if received.iter().any(|payment| payment.key == lock.key) {
+                receiving.add_lock(spend.owner, lock);
+            }
+        }
+    }
+    for payment in received.iter_mut() {
+        payment.verified = validation.complete;
+    }
+    *wallet = receiving;
+
+    // memo is encrypted to one of the outputs, usually the first one
+    let mut memo = None;
+    for payment in received.iter() {
+        let receiver_sk = sk
+            .zip(wallet.index_that_derives(payment.key))
+            .map(|(sk, index)| sk.derive_child(&index.to_be_bytes::<32>()));
+
+        match client
+            .act_memo_get(&spend.owner, receiver_sk.as_ref())
+            .await
+        {
+            Ok(text) => {
+                memo = text;
+                break;
+            }
+            Err(e) => println!("Receive memo: {}", e),
+        }
+    }
+
+    for payment in received.iter() {
+        wallet.record(Transaction::received(
+            payment.token_id,
+            payment.amount,
+            payment.spend,
+            memo.clone(),
+        ));
+    }
+
+    Ok(received)
 }
 
 #[cfg(test)]
@@ -288,13 +1768,19 @@ mod tests {
 
         assert_eq!(
 			format!("{:x}", data),
-			"93dc0030cc876006073c4eccf1cc9d23ccdfcc94cc8fccea3e7ecc9539cc8d6a7b5acccc6a510f24cce2ccd5160bccbbccd9ccf636cca5cc8cccdfcce6cc9ccc8eccba42ccb1cccfccce0fcca60a81dc0020cca4ccfe1bccc8cca631ccbe22ccaecc96ccad524b13ccf64d68ccefccc503cced40cc86ccd6ccaf4ecca906ccc915cce8ccf492c42000000000000000000000000000000000000000000000000000000000000000019192dc0030cca625cc836bcc8970244eccae677e6338145fcce90dcc97cc89777a47311d13ccaf4141ccc16eccfdccebcca60d60ccdbcc86ccd7cc972ecc86cce6ccd17ecc8b4dccb0ccafc4200000000000000000000000000000000000000000000000000000000000000001c4200000000000000000000000000000000000000000000000000000000000000001".to_string()
+			"9ddc0030cc876006073c4eccf1cc9d23ccdfcc94cc8fccea3e7ecc9539cc8d6a7b5acccc6a510f24cce2ccd5160bccbbccd9ccf636cca5cc8cccdfcce6cc9ccc8eccba42ccb1cccfccce0fcca60a81dc0020cca4ccfe1bccc8cca631ccbe22ccaecc96ccad524b13ccf64d68ccefccc503cced40cc86ccd6ccaf4ecca906ccc915cce8ccf492c42000000000000000000000000000000000000000000000000000000000000000019192dc0030cca625cc836bcc8970244eccae677e6338145fcce90dcc97cc89777a47311d13ccaf4141ccc16eccfdccebcca60d60ccdbcc86ccd7cc972ecc86cce6ccd17ecc8b4dccb0ccafc4200000000000000000000000000000000000000000000000000000000000000001c420000000000000000000000000000000000000000000000000000000000000000190c29380808090008090908080".to_string()
 		);
 
         let w2 = rmp_serde::from_slice::<Wallet>(&data).map_err(|e| format!("{e}"))?;
 
         assert_eq!(w, w2);
 
+        // wallets saved before transaction history was introduced
+        let legacy = hex::decode("93dc0030cc876006073c4eccf1cc9d23ccdfcc94cc8fccea3e7ecc9539cc8d6a7b5acccc6a510f24cce2ccd5160bccbbccd9ccf636cca5cc8cccdfcce6cc9ccc8eccba42ccb1cccfccce0fcca60a81dc0020cca4ccfe1bccc8cca631ccbe22ccaecc96ccad524b13ccf64d68ccefccc503cced40cc86ccd6ccaf4ecca906ccc915cce8ccf492c42000000000000000000000000000000000000000000000000000000000000000019192dc0030cca625cc836bcc8970244eccae677e6338145fcce90dcc97cc89777a47311d13ccaf4141ccc16eccfdccebcca60d60ccdbcc86ccd7cc972ecc86cce6ccd17ecc8b4dccb0ccafc4200000000000000000000000000000000000000000000000000000000000000001c4200000000000000000000000000000000000000000000000000000000000000001").map_err(|e| format!("{e}"))?;
+        let w3 = rmp_serde::from_slice::<Wallet>(&legacy).map_err(|e| format!("{e}"))?;
+
+        assert_eq!(w, w3);
+
         Ok(())
     }
 
@@ -319,4 +1805,519 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn history_by_token_and_date() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+
+        let token1 = XorName::from_content(&[1u8]);
+        let token2 = XorName::from_content(&[2u8]);
+        let spend1 = SecretKey::random().public_key();
+        let spend2 = SecretKey::random().public_key();
+        let to = SecretKey::random().public_key();
+
+        let mut received = Transaction::received(token1, U256::from(10), spend1, None);
+        received.timestamp = 1000;
+        w.record(received);
+
+        let mut sent = Transaction::sent(token2, U256::from(3), to, spend2, Some("a memo".into()));
+        sent.timestamp = 2000;
+        w.record(sent);
+
+        assert_eq!(2, w.history(None, None, None).len());
+        assert_eq!(1, w.history(Some(token1), None, None).len());
+        assert_eq!(spend2, w.history(None, Some(1001), None)[0].spend);
+        assert_eq!(spend1, w.history(None, None, Some(2000))[0].spend);
+        assert_eq!(0, w.history(Some(token1), Some(1500), None).len());
+
+        assert_eq!(
+            TxStatus::Pending,
+            w.history(Some(token2), None, None)[0].status
+        );
+        w.set_status(spend2, Direction::Sent, TxStatus::Confirmed)?;
+        assert_eq!(
+            TxStatus::Confirmed,
+            w.history(Some(token2), None, None)[0].status
+        );
+        assert!(w
+            .set_status(spend2, Direction::Received, TxStatus::Failed)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn watch_only_follows_spends() -> Result<(), String> {
+        use super::*;
+
+        let sk = SecretKey::random();
+        let mut w = Wallet::new(sk.public_key());
+
+        let token_id = XorName::from_content(&[3u8]);
+        let genesis = SecretKey::random().public_key();
+
+        w.request(Some(token_id))?;
+        w.receive(U256::from(100), token_id, genesis)?;
+
+        let descriptor = w.watch_only().to_hex()?;
+        let mut watch = Wallet::from(WatchOnly::from_hex(&descriptor)?);
+
+        assert!(watch.is_watch_only());
+        assert_eq!(U256::from(100), watch.balance(token_id)?);
+        assert!(watch.request(Some(token_id)).is_err());
+        assert!(watch.take_to_spend(token_id).is_err());
+
+        // spend made by the full wallet
+        let spender = sk.derive_child(&w.index_of_token(token_id).unwrap().to_be_bytes::<32>());
+        let (inputs, sum, rest_key) = w.take_to_spend(token_id)?;
+        let to = SecretKey::random().public_key();
+        let spend = GraphEntry::new(
+            &spender,
+            inputs,
+            token_id.0,
+            vec![
+                (to, U256::from(30).to_be_bytes()),
+                (rest_key, (sum - U256::from(30)).to_be_bytes()),
+            ],
+        );
+        w.receive(sum - U256::from(30), token_id, spend.owner)?;
+
+        let sent = watch.follow_spend(token_id, &spend)?;
+
+        assert_eq!(1, sent.len());
+        assert_eq!(to, sent[0].counterparty);
+        assert_eq!(U256::from(30), sent[0].amount);
+        assert_eq!(w.balance(token_id)?, watch.balance(token_id)?);
+        assert_eq!(w.pk_of_token(token_id), watch.pk_of_token(token_id));
+
+        // same spend cannot be followed twice
+        assert!(watch.follow_spend(token_id, &spend).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn payment_requests() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let token_id = XorName::from_content(&[4u8]);
+        let other_token = XorName::from_content(&[5u8]);
+
+        let first = w.request_payment(Some(token_id), Some(U256::from(10)), Some(2000))?;
+        let second = w.request_payment(None, None, None)?;
+        assert_ne!(first, second);
+        assert_eq!(2, w.requests(Some(RequestStatus::Open), 1000)?.len());
+        assert_eq!(1, w.requests(Some(RequestStatus::Expired), 2000)?.len());
+
+        assert!(w
+            .receive_request(
+                &first,
+                U256::from(4),
+                other_token,
+                SecretKey::random().public_key(),
+                1000
+            )
+            .is_err());
+
+        // first funds of the token, request key becomes main key
+        let spend = SecretKey::random().public_key();
+        let m = w.receive_request(&first, U256::from(4), token_id, spend, 1000)?;
+        assert_eq!(Some(U256::from(6)), m.underpaid());
+        assert!(!m.late);
+        assert_eq!(Some(first), w.pk_of_token(token_id));
+        assert!(w
+            .receive_request(&first, U256::from(4), token_id, spend, 1000)
+            .is_err());
+
+        let m = w.receive_request(
+            &first,
+            U256::from(8),
+            token_id,
+            SecretKey::random().public_key(),
+            3000,
+        )?;
+        assert_eq!(Some(U256::from(2)), m.overpaid());
+        assert!(m.late);
+        assert_eq!(1, w.requests(Some(RequestStatus::Fulfilled), 3000)?.len());
+
+        // funds stay at the request key
+        let spend = SecretKey::random().public_key();
+        w.receive_request(&second, U256::from(5), token_id, spend, 1000)?;
+        assert_eq!(Some(first), w.pk_of_token(token_id));
+        assert_eq!(U256::from(17), w.balance(token_id)?);
+        assert_eq!(Ok(U256::from(17)), w.balance_total()[&token_id]);
+
+        let (index, payments) = w.separate_funds(token_id)[0].clone();
+        assert_eq!(vec![(spend, U256::from(5))], payments);
+        assert_eq!(Some(index), w.index_that_derives(second));
+
+        let descriptor = w.watch_only().to_hex()?;
+        let watch = Wallet::from(WatchOnly::from_hex(&descriptor)?);
+        assert_eq!(U256::from(17), watch.balance(token_id)?);
+
+        let consolidation = SecretKey::random().public_key();
+        w.consolidated(index, consolidation, U256::from(5))?;
+        assert!(w.separate_funds(token_id).is_empty());
+        assert_eq!(U256::from(17), w.balance(token_id)?);
+        assert!(w.received_spend(token_id, consolidation));
+        assert!(w
+            .receive_request(
+                &second,
+                U256::from(1),
+                token_id,
+                SecretKey::random().public_key(),
+                1000
+            )
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_token_requests() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let token1 = XorName::from_content(&[6u8]);
+        let token2 = XorName::from_content(&[7u8]);
+
+        // two tokens created at the same time
+        let key1 = w.request(None)?;
+        let key2 = w.request(None)?;
+        assert_ne!(key1, key2);
+
+        let genesis2 = SecretKey::random().public_key();
+        w.receive_at(key2, U256::from(200), token2, genesis2, 0)?;
+        w.receive_at(
+            key1,
+            U256::from(100),
+            token1,
+            SecretKey::random().public_key(),
+            0,
+        )?;
+
+        assert_eq!(Some(key1), w.pk_of_token(token1));
+        assert_eq!(Some(key2), w.pk_of_token(token2));
+        assert_eq!(U256::from(100), w.balance(token1)?);
+        assert_eq!(U256::from(200), w.balance(token2)?);
+
+        assert!(w
+            .receive_at(key2, U256::from(1), token2, genesis2, 0)
+            .is_err());
+        assert!(w
+            .receive_at(
+                SecretKey::random().public_key(),
+                U256::from(1),
+                token1,
+                SecretKey::random().public_key(),
+                0
+            )
+            .is_err());
+
+        // single pending request takes a token received without its key
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let key = w.request(None)?;
+        w.receive(U256::from(100), token1, SecretKey::random().public_key())?;
+        assert_eq!(Some(key), w.pk_of_token(token1));
+        assert_eq!(U256::from(100), w.balance(token1)?);
+
+        // which one of more pending requests is ambiguous
+        w.request(None)?;
+        w.request(None)?;
+        assert!(w
+            .receive(U256::from(1), token2, SecretKey::random().public_key())
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoice_payments() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let token_id = XorName::from_content(&[1u8]);
+        let invoice_id = XorName::from_content(&[2u8]);
+        let memo = Some(invoice_reference(&invoice_id));
+
+        // spend with two outputs to the wallet is recorded twice
+        let verified = SecretKey::random().public_key();
+        let unverified = SecretKey::random().public_key();
+        for spend in [verified, verified, unverified] {
+            w.record(Transaction::received(
+                token_id,
+                U256::from(10),
+                spend,
+                memo.clone(),
+            ));
+        }
+        w.record(Transaction::received(
+            token_id,
+            U256::from(10),
+            SecretKey::random().public_key(),
+            None,
+        ));
+        w.set_unverified(unverified);
+
+        assert_eq!(vec![verified], w.invoice_payments(&invoice_id));
+        assert_eq!(vec![unverified], w.invoice_unverified(&invoice_id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn receive_all_outputs() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let token_id = XorName::from_content(&[8u8]);
+
+        let main = w.request(Some(token_id))?;
+        let requested = w.request_payment(Some(token_id), Some(U256::from(5)), None)?;
+
+        let payer = SecretKey::random();
+        let spend = GraphEntry::new(
+            &payer,
+            vec![SecretKey::random().public_key()],
+            token_id.0,
+            vec![
+                (main, U256::from(10).to_be_bytes()),
+                (requested, U256::from(5).to_be_bytes()),
+                (main, U256::from(1).to_be_bytes()),
+                (
+                    SecretKey::random().public_key(),
+                    U256::from(3).to_be_bytes(),
+                ),
+            ],
+        );
+
+        let mut tampered = spend.clone();
+        tampered.descendants[0].1 = U256::from(20).to_be_bytes();
+        assert!(w.receive_spend(&tampered, 0).is_err());
+
+        let received = w.receive_spend(&spend, 0)?;
+        assert_eq!(2, received.len());
+        assert_eq!(
+            (main, U256::from(11)),
+            (received[0].key, received[0].amount)
+        );
+        assert_eq!(None, received[0].request);
+        assert_eq!(requested, received[1].key);
+        assert_eq!(None, received[1].request.and_then(|m| m.underpaid()));
+        assert_eq!(U256::from(16), w.balance(token_id)?);
+
+        assert!(w.receive_spend(&spend, 0).is_err());
+
+        let elsewhere = GraphEntry::new(
+            &payer,
+            vec![],
+            token_id.0,
+            vec![(
+                SecretKey::random().public_key(),
+                U256::from(3).to_be_bytes(),
+            )],
+        );
+        assert!(w.receive_spend(&elsewhere, 0).is_err());
+
+        // lookup is rebuilt for loaded wallets
+        let loaded = Wallet::from_bytes(&w.to_bytes()?)?;
+        assert_eq!(w, loaded);
+        assert_eq!(
+            w.index_that_derives(requested),
+            loaded.index_that_derives(requested)
+        );
+        assert!(loaded.index_that_derives(requested).is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unverified_funds() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let token_id = XorName::from_content(&[9u8]);
+        let verified = SecretKey::random().public_key();
+        let unverified = SecretKey::random().public_key();
+
+        w.request(Some(token_id))?;
+        w.receive(U256::from(10), token_id, verified)?;
+        w.receive(U256::from(5), token_id, unverified)?;
+        w.set_unverified(unverified);
+
+        assert_eq!(U256::from(15), w.balance(token_id)?);
+        assert_eq!(U256::from(5), w.unverified_balance(token_id)?);
+        assert!(w.check_verified(token_id).is_err());
+        assert!(w.take_to_spend(token_id).is_err());
+
+        let watch = Wallet::from(WatchOnly::from_hex(&w.watch_only().to_hex()?)?);
+        assert_eq!(vec![unverified], watch.unverified());
+
+        assert_eq!(1, w.accept_unverified(token_id));
+        assert_eq!(U256::ZERO, w.unverified_balance(token_id)?);
+        assert!(!w.set_verified(&unverified));
+        w.take_to_spend(token_id)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn locked_funds() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+        let token_id = XorName::from_content(&[11u8]);
+        let input = SecretKey::random().public_key();
+
+        let main = w.request(Some(token_id))?;
+        w.receive(U256::from(100), token_id, input)?;
+        let lock = Lock {
+            key: main,
+            releases: vec![(1000, U256::from(30)), (u64::MAX, U256::from(30))],
+        };
+        w.add_lock(input, lock.clone());
+        w.add_lock(input, lock);
+
+        assert_eq!(U256::from(60), w.locked_balance(token_id, 999)?);
+        assert_eq!(U256::from(30), w.locked_balance(token_id, 1000)?);
+        assert_eq!(Some(1000), w.next_unlock(token_id, 999));
+        assert_eq!(Some(u64::MAX), w.next_unlock(token_id, 1000));
+        assert!(w.check_unlocked(token_id, 1000).is_err());
+        assert!(w.take_to_spend(token_id).is_err());
+        w.check_unlocked(token_id, u64::MAX)?;
+
+        let watch = Wallet::from(WatchOnly::from_hex(&w.watch_only().to_hex()?)?);
+        assert_eq!(U256::from(30), watch.locked_balance(token_id, 1000)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn offline_signing() -> Result<(), String> {
+        use super::*;
+        use crate::proposal::{signed_from_hex, signed_to_hex};
+
+        let sk = SecretKey::random();
+        let mut offline = Wallet::new(sk.public_key());
+        let token_id = XorName::from_content(&[12u8]);
+        offline.request(Some(token_id))?;
+
+        let mut online = Wallet::from(offline.watch_only());
+        online.receive(U256::from(100), token_id, SecretKey::random().public_key())?;
+
+        let payee = SecretKey::random().public_key();
+        let unsigned = online.unsigned_spend(token_id, vec![(payee, U256::from(30))])?;
+        let unsigned = UnsignedSpend::from_hex(&unsigned.to_hex()?)?;
+        assert!(online.sign_offline(&sk, &unsigned).is_err());
+        assert!(offline
+            .sign_offline(&SecretKey::random(), &unsigned)
+            .is_err());
+
+        let spend = signed_from_hex(&signed_to_hex(&offline.sign_offline(&sk, &unsigned)?)?)?;
+        assert!(spend.verify_signature());
+        let sent = online.follow_spend(token_id, &spend)?;
+        assert_eq!(1, sent.len());
+
+        for wallet in [&online, &offline] {
+            assert_eq!(U256::from(70), wallet.balance(token_id)?);
+            assert_eq!(Some(spend.descendants[1].0), wallet.pk_of_token(token_id));
+        }
+        assert_eq!(online.history(None, None, None).len(), 1);
+        assert_eq!(offline.history(None, None, None).len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn offline_signing_far_behind() -> Result<(), String> {
+        use super::*;
+
+        let sk = SecretKey::random();
+        let mut offline = Wallet::new(sk.public_key());
+        let token_id = XorName::from_content(&[13u8]);
+        offline.request(Some(token_id))?;
+
+        // online copy derives more keys than the offline one looks ahead
+        let mut online = offline.clone();
+        online.receive(U256::from(100), token_id, SecretKey::random().public_key())?;
+        for _ in 0..WATCH_LOOKAHEAD + 5 {
+            online.request_payment(None, None, None)?;
+        }
+        let payee = SecretKey::random().public_key();
+        let unsigned = online.unsigned_spend(token_id, vec![(payee, U256::from(30))])?;
+
+        // other main key of the token is not overwritten
+        let mut other = Wallet::new(sk.public_key());
+        other.request_payment(None, None, None)?;
+        other.request(Some(token_id))?;
+        assert!(other.sign_offline(&sk, &unsigned).is_err());
+        assert_eq!(Some(U256::from(2)), other.index_of_token(token_id));
+
+        let spend = offline.sign_offline(&sk, &unsigned)?;
+        let rest_key = spend.descendants[1].0;
+        assert_eq!(Some(rest_key), offline.pk_of_token(token_id));
+        assert_eq!(U256::from(70), offline.balance(token_id)?);
+        assert_eq!(unsigned.rest_index, Some(offline.last_index));
+        assert_eq!(offline.history(None, None, None).len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn burned_total() -> Result<(), String> {
+        use super::*;
+
+        let sk = SecretKey::random();
+        let mut w = Wallet::new(sk.public_key());
+        let token_id = XorName::from_content(&[10u8]);
+        let input = SecretKey::random().public_key();
+
+        let main = w.request(Some(token_id))?;
+        w.receive(U256::from(100), token_id, input)?;
+
+        let index = w.index_of_token(token_id).ok_or("No main key.")?;
+        let change = sk
+            .public_key()
+            .derive_child(&(index + U256::from(1)).to_be_bytes::<32>());
+        let burn = GraphEntry::new(
+            &sk.derive_child(&index.to_be_bytes::<32>()),
+            vec![input],
+            token_id.0,
+            vec![
+                (main, U256::from(30).to_be_bytes()),
+                (change, U256::from(70).to_be_bytes()),
+            ],
+        );
+
+        // burn made elsewhere is found by sync
+        let sent = w.follow_spend(token_id, &burn)?;
+        assert_eq!(1, sent.len());
+        assert!(sent[0].is_burn());
+        assert_eq!(U256::from(30), w.burned(token_id)?);
+        assert_eq!(U256::from(70), w.balance(token_id)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn accounts() -> Result<(), String> {
+        use super::*;
+
+        let mut w = Wallet::new(SecretKey::random().public_key());
+
+        assert_eq!(1, w.add_account("Operations")?);
+        assert_eq!(2, w.add_account(" Payroll ")?);
+        assert!(w.add_account("Payroll").is_err());
+        assert!(w.add_account("").is_err());
+
+        assert_eq!(
+            &["Operations".to_string(), "Payroll".to_string()],
+            w.accounts()
+        );
+
+        Ok(())
+    }
 }